@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::menu::MenuItem;
+use tauri::{command, Emitter, Manager, State};
+
+use crate::{load_config, read_config, save_config, FrpcProcesses};
+
+// 未创建任何账户时使用的默认账户，其凭据沿用未分账户时的存储位置
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+// 账户级别的 frpc 启动参数，未设置的项沿用全局环境变量
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct LaunchOptions {
+    pub use_doh: Option<bool>,
+    pub doh_addr: Option<String>,
+    pub debug: Option<bool>,
+    pub force_tls: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct AccountProfile {
+    pub name: String,
+    // 切换到该账户后自动启动的隧道 ID
+    #[serde(default)]
+    pub default_tunnels: Vec<String>,
+    #[serde(default)]
+    pub launch_options: LaunchOptions,
+}
+
+// 切换账户时对正在运行的隧道的处理方式
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchPolicy {
+    // 停止原账户启动的隧道
+    Stop,
+    // 保持原账户的隧道继续运行
+    Keep,
+}
+
+// 托盘中显示当前账户的菜单项
+pub struct AccountMenuItem(pub MenuItem<tauri::Wry>);
+
+pub fn active_account() -> String {
+    read_config()
+        .ok()
+        .and_then(|cfg| cfg.active_account)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_ACCOUNT.to_string())
+}
+
+pub fn active_profile() -> AccountProfile {
+    let active = active_account();
+    read_config()
+        .ok()
+        .and_then(|cfg| cfg.accounts)
+        .and_then(|list| list.into_iter().find(|p| p.name == active))
+        .unwrap_or(AccountProfile {
+            name: active,
+            ..Default::default()
+        })
+}
+
+pub fn tray_label(account: &str) -> String {
+    format!("当前账户: {}", account)
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > 64 {
+        return Err("账户名称长度应为 1-64 个字符".to_string());
+    }
+    if name.contains(':') {
+        return Err("账户名称不能包含 ':'".to_string());
+    }
+    Ok(())
+}
+
+#[command]
+pub fn list_accounts() -> Result<serde_json::Value, String> {
+    let cfg = read_config()?;
+    Ok(serde_json::json!({
+        "active": active_account(),
+        "accounts": cfg.accounts.unwrap_or_default(),
+    }))
+}
+
+// 新建或更新账户配置
+#[command]
+pub fn save_account(profile: AccountProfile) -> Result<(), String> {
+    check_name(&profile.name)?;
+    let mut cfg = load_config()?;
+    let mut list = cfg.accounts.unwrap_or_default();
+    match list.iter_mut().find(|p| p.name == profile.name) {
+        Some(existing) => *existing = profile,
+        None => list.push(profile),
+    }
+    cfg.accounts = Some(list);
+    save_config(&cfg)
+}
+
+#[command]
+pub fn delete_account(name: String) -> Result<(), String> {
+    if name == active_account() {
+        return Err("不能删除当前正在使用的账户".to_string());
+    }
+    let mut cfg = load_config()?;
+    let mut list = cfg.accounts.unwrap_or_default();
+    list.retain(|p| p.name != name);
+    cfg.accounts = Some(list);
+    save_config(&cfg)?;
+
+    // 同时清理该账户的凭据
+    crate::credentials::clear_for(&name, crate::credentials::TOKEN)?;
+    crate::credentials::clear_for(&name, crate::credentials::AUTHORIZATION)?;
+    Ok(())
+}
+
+#[command]
+pub async fn switch_account(
+    app: tauri::AppHandle,
+    processes: State<'_, FrpcProcesses>,
+    name: String,
+    policy: SwitchPolicy,
+) -> Result<serde_json::Value, String> {
+    let previous = active_account();
+    let mut cfg = load_config()?;
+    let profile = if name == DEFAULT_ACCOUNT {
+        AccountProfile {
+            name: name.clone(),
+            ..Default::default()
+        }
+    } else {
+        cfg.accounts
+            .as_ref()
+            .and_then(|list| list.iter().find(|p| p.name == name))
+            .cloned()
+            .ok_or_else(|| format!("账户不存在: {}", name))?
+    };
+
+    // 按策略处理原账户启动的隧道
+    let mut stopped = Vec::new();
+    if policy == SwitchPolicy::Stop {
+        if let Ok(mut map) = processes.0.lock() {
+            let ids: Vec<String> = map
+                .iter()
+                .filter(|(_, info)| info.account == previous)
+                .map(|(id, _)| id.clone())
+                .collect();
+            for id in ids {
+                if let Some(mut process_info) = map.remove(&id) {
+                    crate::kill_process(&mut process_info);
+                    stopped.push(id);
+                }
+            }
+            crate::instance_registry::sync(&map);
+        }
+    }
+
+    cfg.active_account = Some(name.clone());
+    save_config(&cfg)?;
+
+    if let Some(item) = app.try_state::<AccountMenuItem>() {
+        let _ = item.0.set_text(tray_label(&name));
+    }
+
+    // 启动新账户的默认隧道
+    let mut started = Vec::new();
+    let mut failed = Vec::new();
+    for tunnel_id in &profile.default_tunnels {
+        match crate::spawn_frpc(
+            &processes,
+            Arc::new(app.clone()),
+            tunnel_id.clone(),
+            None,
+            tunnel_id.clone(),
+        ) {
+            Ok(_) => started.push(tunnel_id.clone()),
+            Err(e) => failed.push(serde_json::json!({ "id": tunnel_id, "error": e })),
+        }
+    }
+
+    let result = serde_json::json!({
+        "previous": previous,
+        "active": name,
+        "stopped": stopped,
+        "started": started,
+        "failed": failed,
+    });
+    // 前端持有的仍是原账户的 Authorization，收到事件后清除并重新加载页面
+    let _ = app.emit("account-switched", result.clone());
+    Ok(result)
+}
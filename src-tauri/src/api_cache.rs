@@ -0,0 +1,132 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// 接口响应的本地缓存：保存最近一次成功获取的用户信息、隧道列表和节点列表，
+// 接口不可用时返回缓存数据并标记为过期，离线时仍可查看并启动已知隧道
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CacheKind {
+    UserInfo,
+    UserProxies,
+    NodeList,
+}
+
+impl CacheKind {
+    // 根据接口路径判断是否需要缓存
+    pub fn from_path(path: &str) -> Option<Self> {
+        let path = path.trim_start_matches('/');
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        match path {
+            "getUserInfo" => Some(CacheKind::UserInfo),
+            "getUserProxies" => Some(CacheKind::UserProxies),
+            "getNodeList" => Some(CacheKind::NodeList),
+            _ => None,
+        }
+    }
+
+    // 会使该类缓存过时的修改接口
+    pub fn modified_by(path: &str) -> Option<Self> {
+        let path = path.trim_start_matches('/');
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        match path {
+            "newProxy" | "editProxy" | "removeProxy" => Some(CacheKind::UserProxies),
+            _ => None,
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            CacheKind::UserInfo => "user_info.json",
+            CacheKind::UserProxies => "user_proxies.json",
+            CacheKind::NodeList => "node_list.json",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CacheEntry {
+    // 保存时间（Unix 时间戳，秒）
+    pub saved_at: i64,
+    // 接口返回的完整响应体
+    pub body: serde_json::Value,
+}
+
+// 本进程最近一次写入各缓存的时间，键为账户目录名与缓存类型，
+// 后台检查在有效期内直接使用缓存，不重复请求接口
+static FRESH: Lazy<Mutex<HashMap<(String, CacheKind), Instant>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn account_dir() -> String {
+    crate::accounts::active_account()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// 每个账户单独缓存
+fn cache_path(kind: CacheKind) -> PathBuf {
+    crate::get_app_dir()
+        .join("cache")
+        .join(account_dir())
+        .join(kind.file_name())
+}
+
+// 保存接口响应，用户密钥已在凭据存储中，不写入缓存
+pub fn store(kind: CacheKind, body: &serde_json::Value) {
+    let mut body = body.clone();
+    if kind == CacheKind::UserInfo {
+        if let Some(data) = body["data"].as_object_mut() {
+            data.remove("token");
+        }
+    }
+    let entry = CacheEntry {
+        saved_at: chrono::Local::now().timestamp(),
+        body,
+    };
+
+    let path = cache_path(kind);
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| {
+            let content = serde_json::to_string(&entry).map_err(std::io::Error::other)?;
+            crate::write_atomic(&path, content.as_bytes())
+        });
+    match result {
+        Ok(()) => {
+            FRESH
+                .lock()
+                .unwrap()
+                .insert((account_dir(), kind), Instant::now());
+        }
+        Err(e) => println!("写入接口缓存失败: {}", e),
+    }
+}
+
+// 数据已修改，之后的读取需要重新请求接口
+pub fn invalidate(kind: CacheKind) {
+    FRESH.lock().unwrap().remove(&(account_dir(), kind));
+}
+
+// 本进程在 max_age 内获取过的缓存
+pub fn load_fresh(kind: CacheKind, max_age: Duration) -> Option<CacheEntry> {
+    let fresh = FRESH
+        .lock()
+        .unwrap()
+        .get(&(account_dir(), kind))
+        .is_some_and(|at| at.elapsed() < max_age);
+    if fresh {
+        load(kind)
+    } else {
+        None
+    }
+}
+
+pub fn load(kind: CacheKind) -> Option<CacheEntry> {
+    let content = std::fs::read_to_string(cache_path(kind)).ok()?;
+    serde_json::from_str(&content).ok()
+}
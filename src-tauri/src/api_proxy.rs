@@ -1,68 +1,357 @@
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Method;
+use serde::Serialize;
 use serde_json::Value;
 use std::str::FromStr;
+use std::time::Duration;
 use tauri::command;
 
+use crate::{accounts, api_cache, auth_refresh, credentials, endpoints, http};
+
+// 允许前端通过 proxy_api 访问的接口路径
+const ALLOWED_PATHS: &[&str] = &[
+    "argoAccept",
+    "changeProxy",
+    "editProxy",
+    "forceOff",
+    "forceOffAll",
+    "getAllGroups",
+    "getAvailableNode",
+    "getNodeConf",
+    "getNodeList",
+    "getNodeStatus",
+    "getSignInfo",
+    "getTrafficHistory",
+    "getUserInfo",
+    "getUserProxies",
+    "logout",
+    "logoutAll",
+    "newProxy",
+    "refreshProxyStatus",
+    "removeProxy",
+    "resetToken",
+    "userSign",
+];
+
+// 允许前端设置的请求头，其余请求头会被忽略
+const ALLOWED_HEADERS: &[&str] = &["authorization", "content-type", "accept", "accept-language"];
+
+const MAX_TIMEOUT_MS: u64 = 120_000;
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProxyApiError {
+    // 路径、方法或参数不被允许
+    Rejected {
+        message: String,
+    },
+    Network {
+        message: String,
+    },
+    Timeout {
+        message: String,
+    },
+    // 请求过于频繁，retry_after 秒后可以重试
+    RateLimited {
+        message: String,
+        retry_after: u64,
+    },
+    // 服务器返回错误状态码且响应不是 JSON，body 为原始响应文本
+    Http {
+        status: u16,
+        message: String,
+        body: String,
+    },
+    // 响应状态正常但内容无法解析为 JSON
+    Parse {
+        status: u16,
+        message: String,
+        body: String,
+    },
+    // 保存凭据失败
+    Storage {
+        message: String,
+    },
+}
+
+impl From<reqwest::Error> for ProxyApiError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            ProxyApiError::Timeout {
+                message: format!("请求超时: {}", error),
+            }
+        } else {
+            ProxyApiError::Network {
+                message: error.to_string(),
+            }
+        }
+    }
+}
+
+impl From<http::Error> for ProxyApiError {
+    fn from(error: http::Error) -> Self {
+        match error {
+            http::Error::RateLimited { retry_after } => ProxyApiError::RateLimited {
+                message: error.to_string(),
+                retry_after: retry_after.as_secs(),
+            },
+            http::Error::Request(e) => e.into(),
+        }
+    }
+}
+
+// 检查请求路径（不含查询参数）是否在允许列表中
+fn check_path(url: &str) -> Result<(), ProxyApiError> {
+    let path = url.trim_start_matches('/');
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    if ALLOWED_PATHS.contains(&path) {
+        Ok(())
+    } else {
+        Err(ProxyApiError::Rejected {
+            message: format!("不允许访问的接口: {}", path),
+        })
+    }
+}
+
+// 截取过长的响应文本，避免把整页 HTML 传给前端
+fn truncate_body(text: &str) -> String {
+    const LIMIT: usize = 4096;
+    if text.len() <= LIMIT {
+        return text.to_string();
+    }
+    let mut end = LIMIT;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…", &text[..end])
+}
+
+impl ProxyApiError {
+    // 接口暂时不可用（网络错误、超时、限流或服务器错误），可以使用缓存数据
+    fn is_unavailable(&self) -> bool {
+        match self {
+            ProxyApiError::Network { .. }
+            | ProxyApiError::Timeout { .. }
+            | ProxyApiError::RateLimited { .. } => true,
+            ProxyApiError::Http { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+}
+
 #[command]
 pub async fn proxy_api(
     url: String,
     method: String,
     headers: Option<Value>,
     body: Option<Value>,
-) -> Result<Value, String> {
-    let client_builder = reqwest::Client::builder();
-
-    // 获取绕过代理设置
-    let bypass_proxy = std::env::var("BYPASS_PROXY").unwrap_or_else(|_| "false".to_string());
-    let client = if bypass_proxy == "true" {
-        // 绕过系统代理 - 清除所有代理环境变量
-        client_builder
-            .no_proxy()
-            .build()
-            .map_err(|e| e.to_string())?
-    } else {
-        // 使用系统代理
-        client_builder.build().map_err(|e| e.to_string())?
+    timeout_ms: Option<u64>,
+) -> Result<Value, ProxyApiError> {
+    check_path(&url)?;
+
+    let cache_kind = api_cache::CacheKind::from_path(&url);
+    let auth = request_auth(headers.as_ref());
+    let result = forward_with_auth(&url, &method, headers, body, timeout_ms, &auth).await;
+    if let Some(kind) = api_cache::CacheKind::modified_by(&url) {
+        api_cache::invalidate(kind);
+    }
+    let Some(kind) = cache_kind else {
+        return result;
     };
 
-    // 构建请求
-    let base_url = "https://of-dev-api.bfsea.com/frp/api";
-    let full_url = format!("{}/{}", base_url, url);
+    match result {
+        Ok(mut value) => {
+            // 请求期间切换了账户时不写入新账户的缓存
+            if value["data"]["flag"].as_bool().unwrap_or(false) && auth.is_active() {
+                api_cache::store(kind, &value["data"]);
+            }
+            value["stale"] = Value::Bool(false);
+            Ok(value)
+        }
+        // 接口不可用时返回上次成功获取的数据
+        Err(e) if e.is_unavailable() => match api_cache::load(kind) {
+            Some(entry) => {
+                println!("接口 {} 不可用，使用缓存数据: {:?}", url, e);
+                Ok(serde_json::json!({
+                    "data": entry.body,
+                    "headers": {},
+                    "stale": true,
+                    "cached_at": entry.saved_at,
+                }))
+            }
+            None => Err(e),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+// 请求使用的 Authorization 及其所属账户
+struct RequestAuth {
+    account: String,
+    authorization: Option<String>,
+    // 前端携带的、尚未保存的 Authorization，获取用户信息成功后保存到该账户
+    new_login: bool,
+}
+
+impl RequestAuth {
+    // 所属账户仍是当前账户，请求期间没有切换账户
+    fn is_active(&self) -> bool {
+        self.account == accounts::active_account()
+    }
+}
+
+fn header_authorization(headers: Option<&Value>) -> Option<String> {
+    headers
+        .and_then(Value::as_object)
+        .and_then(|obj| {
+            obj.iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("authorization"))
+                .and_then(|(_, value)| value.as_str())
+        })
+        .filter(|auth| !auth.is_empty())
+        .map(str::to_string)
+}
 
-    let mut request_builder = match method.to_lowercase().as_str() {
-        "get" => client.get(&full_url),
-        "post" => client.post(&full_url),
-        "put" => client.put(&full_url),
-        "delete" => client.delete(&full_url),
-        _ => return Err("不支持的HTTP方法".into()),
+// 是否为其他账户保存的 Authorization
+fn belongs_to_other_account(active: &str, authorization: &str) -> bool {
+    let mut names: Vec<String> = crate::read_config()
+        .ok()
+        .and_then(|cfg| cfg.accounts)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|profile| profile.name)
+        .collect();
+    names.push(accounts::DEFAULT_ACCOUNT.to_string());
+    names.iter().filter(|name| *name != active).any(|name| {
+        credentials::get_for(name, credentials::AUTHORIZATION).as_deref() == Some(authorization)
+    })
+}
+
+// 优先使用当前账户安全存储中的 Authorization。前端携带的值属于其他账户时
+// （切换账户后页面尚未刷新）忽略；未保存过的值视为当前账户重新登录得到的凭据
+fn request_auth(headers: Option<&Value>) -> RequestAuth {
+    let account = accounts::active_account();
+    let stored = credentials::get_for(&account, credentials::AUTHORIZATION);
+    match header_authorization(headers) {
+        Some(auth)
+            if stored.as_deref() != Some(auth.as_str())
+                && !belongs_to_other_account(&account, &auth) =>
+        {
+            RequestAuth {
+                account,
+                authorization: Some(auth),
+                new_login: true,
+            }
+        }
+        _ => RequestAuth {
+            account,
+            authorization: stored,
+            new_login: false,
+        },
+    }
+}
+
+fn auth_expired(value: &Value) -> bool {
+    auth_refresh::is_expired(value["status"].as_u64().unwrap_or(0) as u16, &value["data"])
+}
+
+fn expired_reason(value: &Value) -> String {
+    value["data"]["msg"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("服务器返回 {}", value["status"]))
+}
+
+// 登录失效时换用安全存储中更新的 Authorization 重试一次，仍然失效时发起重新授权
+async fn forward_with_auth(
+    url: &str,
+    method: &str,
+    headers: Option<Value>,
+    body: Option<Value>,
+    timeout_ms: Option<u64>,
+    auth: &RequestAuth,
+) -> Result<Value, ProxyApiError> {
+    let value = forward(url, method, headers.clone(), body.clone(), timeout_ms, auth).await?;
+    if !auth_expired(&value) {
+        return Ok(value);
+    }
+    let fresh = auth_refresh::refreshed_authorization(&auth.account, auth.authorization.as_deref());
+    let Some(fresh) = fresh else {
+        auth_refresh::handle_expired(&expired_reason(&value));
+        return Ok(value);
     };
 
-    // 添加统一的 User-Agent
-    let os_name = std::env::consts::OS;
-    let version = env!("CARGO_PKG_VERSION");
-    let user_agent = format!("OpenFrp-CPL/{}-{}", os_name, version);
-    request_builder = request_builder.header("User-Agent", &user_agent);
+    let retry = RequestAuth {
+        account: auth.account.clone(),
+        authorization: Some(fresh.clone()),
+        new_login: false,
+    };
+    let mut value = forward(url, method, headers, body, timeout_ms, &retry).await?;
+    if auth_expired(&value) {
+        auth_refresh::handle_expired(&expired_reason(&value));
+    } else if value["headers"]["authorization"].is_null() {
+        // 让前端同步新的 Authorization
+        value["headers"]["authorization"] = Value::String(fresh);
+    }
+    Ok(value)
+}
+
+async fn forward(
+    url: &str,
+    method: &str,
+    headers: Option<Value>,
+    body: Option<Value>,
+    timeout_ms: Option<u64>,
+    auth: &RequestAuth,
+) -> Result<Value, ProxyApiError> {
+    let timeout = timeout_ms
+        .map(|ms| Duration::from_millis(ms.clamp(1, MAX_TIMEOUT_MS)))
+        .unwrap_or(http::REQUEST_TIMEOUT);
+    let client = http::client().map_err(|message| ProxyApiError::Network { message })?;
 
-    // 添加请求头
-    if let Some(headers_value) = headers {
-        // 创建一个新的 HeaderMap
-        let mut header_map = HeaderMap::new();
+    let http_method = match method.to_lowercase().as_str() {
+        "get" => Method::GET,
+        "post" => Method::POST,
+        "put" => Method::PUT,
+        "patch" => Method::PATCH,
+        "delete" => Method::DELETE,
+        "head" => Method::HEAD,
+        _ => {
+            return Err(ProxyApiError::Rejected {
+                message: format!("不支持的HTTP方法: {}", method),
+            })
+        }
+    };
 
+    // 添加请求头
+    let mut header_map = HeaderMap::new();
+    if let Some(Value::Object(obj)) = headers {
         // 直接处理 JSON 对象，避免生命周期问题
-        if let Value::Object(obj) = headers_value {
-            for (key, value) in obj {
-                if let Value::String(value_str) = value {
-                    // 将 key 转换为 HeaderName
-                    if let Ok(header_name) = HeaderName::from_str(&key) {
-                        if let Ok(header_value) = HeaderValue::from_str(&value_str) {
-                            header_map.insert(header_name, header_value);
-                        }
+        for (key, value) in obj {
+            if let Value::String(value_str) = value {
+                // Authorization 由 auth 决定
+                if value_str.is_empty() || key.eq_ignore_ascii_case("authorization") {
+                    continue;
+                }
+                // 将 key 转换为 HeaderName
+                if let Ok(header_name) = HeaderName::from_str(&key) {
+                    if !ALLOWED_HEADERS.contains(&header_name.as_str()) {
+                        continue;
+                    }
+                    if let Ok(header_value) = HeaderValue::from_str(&value_str) {
+                        header_map.insert(header_name, header_value);
                     }
                 }
             }
         }
+    }
 
-        request_builder = request_builder.headers(header_map);
+    if let Some(header_value) = auth
+        .authorization
+        .as_deref()
+        .and_then(|value| HeaderValue::from_str(value).ok())
+    {
+        header_map.insert("authorization", header_value);
     }
 
     // // 添加平台特定的请求头
@@ -71,26 +360,124 @@ pub async fn proxy_api(
     //     request_builder = request_builder.header("User-Agent", "OpenFrp-Launcher/macOS");
     // }
 
-    // 添加请求体
-    if let Some(body_value) = body {
-        request_builder = request_builder.json(&body_value);
-    }
+    // 依次尝试当前环境的各个 API 地址，网络错误时切换到下一个，
+    // 非幂等请求只在连接失败时切换
+    let mut last_error = ProxyApiError::Network {
+        message: "未配置 API 地址".to_string(),
+    };
+    let mut response = None;
+    for host in endpoints::api_hosts() {
+        let full_url = format!("{}/frp/api/{}", host, url.trim_start_matches('/'));
+        let mut request_builder = client
+            .request(http_method.clone(), &full_url)
+            .timeout(timeout)
+            .headers(header_map.clone());
 
-    // 发送请求
-    let response = request_builder.send().await.map_err(|e| e.to_string())?;
+        // 添加请求体
+        if let Some(body_value) = &body {
+            if http_method != Method::GET && http_method != Method::HEAD {
+                request_builder = request_builder.json(body_value);
+            }
+        }
+
+        // 发送请求
+        match http::send(request_builder).await {
+            Ok(res) => {
+                endpoints::mark_healthy(&host);
+                response = Some(res);
+                break;
+            }
+            // 各地址是同一服务，限流时不再切换地址
+            Err(e @ http::Error::RateLimited { .. }) => return Err(e.into()),
+            Err(e) => {
+                endpoints::mark_failed(&host);
+                if !e.can_fail_over(&http_method) {
+                    return Err(e.into());
+                }
+                last_error = e.into();
+            }
+        }
+    }
+    let response = response.ok_or(last_error)?;
+    let status = response.status();
 
     // 获取响应头
-    let response_headers: std::collections::HashMap<String, String> = response
+    let mut response_headers: std::collections::HashMap<String, String> = response
         .headers()
         .iter()
         .map(|(name, value)| (name.to_string(), value.to_str().unwrap_or("").to_string()))
         .collect();
 
-    // 解析响应
-    let response_body = response.json::<Value>().await.map_err(|e| e.to_string())?;
+    // 服务端可能下发新的 Authorization，同步到请求所属账户的安全存储
+    let renewed = response_headers
+        .get("authorization")
+        .filter(|value| !value.is_empty())
+        .cloned();
+    if let Some(renewed) = &renewed {
+        credentials::set_for(&auth.account, credentials::AUTHORIZATION, renewed)
+            .map_err(|message| ProxyApiError::Storage { message })?;
+    }
+
+    // 解析响应，HEAD 请求和空响应没有响应体
+    let text = response.text().await?;
+    let response_body = if http_method == Method::HEAD || text.trim().is_empty() {
+        Value::Null
+    } else {
+        match serde_json::from_str::<Value>(&text) {
+            Ok(value) => value,
+            // 例如 CDN 返回的 502 页面
+            Err(_) if !status.is_success() => {
+                return Err(ProxyApiError::Http {
+                    status: status.as_u16(),
+                    message: format!("服务器返回 {}", status),
+                    body: truncate_body(&text),
+                })
+            }
+            Err(e) => {
+                return Err(ProxyApiError::Parse {
+                    status: status.as_u16(),
+                    message: format!("解析响应失败: {}", e),
+                    body: truncate_body(&text),
+                })
+            }
+        }
+    };
+    if response_body.is_null() && !status.is_success() {
+        return Err(ProxyApiError::Http {
+            status: status.as_u16(),
+            message: format!("服务器返回 {}", status),
+            body: String::new(),
+        });
+    }
+
+    // 获取用户信息时保存用户密钥，启动隧道时无需再由前端传入。
+    // 请求期间切换了账户时不保存，避免把原账户的密钥写入新账户
+    let token = response_body["data"]["token"].as_str();
+    if let Some(token) = token.filter(|_| url.trim_start_matches('/').starts_with("getUserInfo")) {
+        if auth.is_active() {
+            credentials::set_for(&auth.account, credentials::TOKEN, token)
+                .map_err(|message| ProxyApiError::Storage { message })?;
+            if auth.new_login && renewed.is_none() {
+                if let Some(used) = &auth.authorization {
+                    credentials::set_for(&auth.account, credentials::AUTHORIZATION, used)
+                        .map_err(|message| ProxyApiError::Storage { message })?;
+                }
+            }
+        } else {
+            println!("请求期间已切换账户，不保存 {} 的用户密钥", auth.account);
+        }
+    }
+
+    // 返回实际使用的 Authorization，切换账户后前端据此同步新账户的登录状态
+    if renewed.is_none() && !auth.new_login {
+        if let Some(used) = &auth.authorization {
+            response_headers.insert("authorization".to_string(), used.clone());
+        }
+    }
 
-    // 返回包含响应头和响应体的对象
+    // 返回包含状态码、响应头和响应体的对象
     let result = serde_json::json!({
+        "status": status.as_u16(),
         "data": response_body,
         "headers": response_headers
     });
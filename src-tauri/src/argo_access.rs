@@ -4,6 +4,7 @@ use reqwest::header::HeaderMap;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::sync::Mutex;
+use std::time::Duration;
 use tauri::command;
 
 // 纯 Rust：X25519 + XSalsa20-Poly1305
@@ -46,6 +47,39 @@ pub struct PollLoginData {
     pub request_uuid: String,
 }
 
+// 单次轮询的结果，用于区分需要继续等待和需要停止的情况
+enum PollError {
+    // 用户尚未完成授权
+    Pending,
+    // 请求过于频繁，retry_after 后重试
+    RateLimited(Duration),
+    // 找不到本次请求的密钥，例如进程已重启
+    MissingKey,
+    Failed(String),
+}
+
+impl From<PollError> for String {
+    fn from(error: PollError) -> Self {
+        match error {
+            PollError::Pending => "未授权".into(),
+            PollError::RateLimited(retry_after) => {
+                crate::http::Error::RateLimited { retry_after }.to_string()
+            }
+            PollError::MissingKey => "未找到对应请求的密钥，请重新发起登录".into(),
+            PollError::Failed(message) => message,
+        }
+    }
+}
+
+impl From<crate::http::Error> for PollError {
+    fn from(error: crate::http::Error) -> Self {
+        match error {
+            crate::http::Error::RateLimited { retry_after } => PollError::RateLimited(retry_after),
+            e => PollError::Failed(e.to_string()),
+        }
+    }
+}
+
 fn generate_keypair() -> (X25519Secret, X25519Public) {
     let sk = X25519Secret::new(OsRng);
     let pk = X25519Public::from(&sk);
@@ -95,17 +129,17 @@ pub async fn argo_request_login() -> Result<RequestLoginData, String> {
     let (sk, pk) = generate_keypair();
     let public_key_b64 = b64_urlsafe_padded(pk.as_bytes());
 
-    let client = reqwest::Client::new();
-    let resp = client
-        .post("https://access.openfrp.net/argoAccess/requestLogin")
-        .json(&serde_json::json!({"public_key": public_key_b64}))
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if resp.status().as_u16() == 429 {
-        return Err("请求过于频繁 (429)".into());
-    }
+    let client = crate::http::client()?;
+    let resp = crate::http::send(
+        client
+            .post(format!(
+                "{}/argoAccess/requestLogin",
+                crate::endpoints::access_host()
+            ))
+            .json(&serde_json::json!({"public_key": public_key_b64})),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
 
     let body: RequestLoginResp = resp.json().await.map_err(|e| e.to_string())?;
     if body.code != 200 {
@@ -122,72 +156,79 @@ pub async fn argo_request_login() -> Result<RequestLoginData, String> {
 
 #[command]
 pub async fn argo_poll_login(request_uuid: String) -> Result<String, String> {
-    // 返回明文 Authorization
+    poll_login(&request_uuid).await.map_err(String::from)
+}
+
+// 轮询一次授权结果，成功时返回明文 Authorization
+async fn poll_login(request_uuid: &str) -> Result<String, PollError> {
     let (sk, _pk) = REQ_KP_MAP
         .lock()
         .unwrap()
-        .get(&request_uuid)
+        .get(request_uuid)
         .cloned()
-        .ok_or_else(|| "未找到对应请求的密钥，请重新发起登录".to_string())?;
-    let client = reqwest::Client::new();
+        .ok_or(PollError::MissingKey)?;
+    let client = crate::http::client().map_err(PollError::Failed)?;
     // 按文档使用 GET + query 参数 request_uuid
-    let resp = client
-        .get("https://access.openfrp.net/argoAccess/pollLogin")
-        .query(&[("request_uuid", request_uuid.clone())])
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if resp.status().as_u16() == 429 {
-        return Err("请求过于频繁 (429)".into());
-    }
+    let resp = crate::http::send(
+        client
+            .get(format!(
+                "{}/argoAccess/pollLogin",
+                crate::endpoints::access_host()
+            ))
+            .query(&[("request_uuid", request_uuid)]),
+    )
+    .await?;
 
     // 先抓取响应头，再解析 Body
     let status = resp.status().as_u16();
     let headers: HeaderMap = resp.headers().clone();
     if status == 204 {
         println!("[Argo] 授权未完成 (204)");
-        return Err("未授权".into());
-    }
-    if status == 429 {
-        println!("[Argo] 命中阈值限制 (429)");
-        return Err("429 Too Many Requests".into());
+        return Err(PollError::Pending);
     }
     if status != 200 {
         let txt = resp.text().await.unwrap_or_default();
         println!("[Argo] 非200状态: {}, 响应文本: {}", status, txt);
-        return Err(format!("轮询失败: HTTP {}", status));
+        return Err(PollError::Failed(format!("轮询失败: HTTP {}", status)));
     }
-    let body: PollLoginResp = resp.json().await.map_err(|e| e.to_string())?;
+    let body: PollLoginResp = resp
+        .json()
+        .await
+        .map_err(|e| PollError::Failed(e.to_string()))?;
     if body.code != 200 {
-        return Err(format!("轮询失败: {}", body.msg));
+        return Err(PollError::Failed(format!("轮询失败: {}", body.msg)));
     }
-    let data = body.data.ok_or_else(|| "响应缺少 data".to_string())?;
+    let data = body
+        .data
+        .ok_or_else(|| PollError::Failed("响应缺少 data".into()))?;
     let server_pk_b64 = match headers.get("x-request-public-key") {
-        Some(v) => v.to_str().map_err(|e| e.to_string())?.to_string(),
+        Some(v) => v
+            .to_str()
+            .map_err(|e| PollError::Failed(e.to_string()))?
+            .to_string(),
         None => {
             // 调试输出全部响应头，便于定位问题
             println!("[Argo] 缺少服务器公钥，响应头如下：");
             for (k, v) in headers.iter() {
                 println!("[Argo] header {}: {}", k, v.to_str().unwrap_or("<bin>"));
             }
-            return Err("缺少服务器公钥".into());
+            return Err(PollError::Failed("缺少服务器公钥".into()));
         }
     };
 
     // 服务器公钥
-    let server_pk_bytes = b64_decode_any(&server_pk_b64)?;
+    let server_pk_bytes = b64_decode_any(&server_pk_b64).map_err(PollError::Failed)?;
     if server_pk_bytes.len() != 32 {
-        return Err("服务器公钥长度错误".into());
+        return Err(PollError::Failed("服务器公钥长度错误".into()));
     }
     let mut pk_arr = [0u8; 32];
     pk_arr.copy_from_slice(&server_pk_bytes);
     let server_pk = X25519Public::from(pk_arr);
 
     // 解码 authorization_data: 前24字节为 nonce，后面为密文
-    let cipher_all = b64_decode_any(&data.authorization_data)?;
+    let cipher_all = b64_decode_any(&data.authorization_data).map_err(PollError::Failed)?;
     if cipher_all.len() < 24 {
-        return Err("密文长度不合法".into());
+        return Err(PollError::Failed("密文长度不合法".into()));
     }
     let mut nonce_bytes = [0u8; 24];
     nonce_bytes.copy_from_slice(&cipher_all[..24]);
@@ -200,16 +241,18 @@ pub async fn argo_poll_login(request_uuid: String) -> Result<String, String> {
     match sbox.decrypt(&nonce_bytes.into(), cipher) {
         Ok(plain) => {
             let auth = String::from_utf8_lossy(&plain).to_string();
-            REQ_KP_MAP.lock().unwrap().remove(&request_uuid);
+            REQ_KP_MAP.lock().unwrap().remove(request_uuid);
+            crate::credentials::set(crate::credentials::AUTHORIZATION, &auth)
+                .map_err(PollError::Failed)?;
             Ok(auth)
         }
-        Err(_e) => Err("解密失败".into()),
+        Err(_e) => Err(PollError::Failed("解密失败".into())),
     }
 }
 
 #[command]
 pub async fn argo_wait_authorization(request_uuid: String) -> Result<String, String> {
-    use tokio::time::{sleep, Duration};
+    use tokio::time::sleep;
     println!("[Argo] 开始轮询授权，request_uuid={}", request_uuid);
     let mut attempts: u32 = 0;
     let max_attempts: u32 = 60; // 5分钟，每5秒一次
@@ -222,24 +265,24 @@ pub async fn argo_wait_authorization(request_uuid: String) -> Result<String, Str
         }
         attempts += 1;
         println!("[Argo] 第 {} 次轮询...", attempts);
-        match argo_poll_login(request_uuid.clone()).await {
+        let mut interval = Duration::from_secs(5);
+        match poll_login(&request_uuid).await {
             Ok(auth) => {
                 println!("[Argo] 成功获取授权明文，结束轮询");
                 return Ok(auth);
             }
-            Err(e) => {
-                let msg = e.to_string();
-                // 未完成授权或服务端限流等，继续等待
-                if msg.contains("429") {
-                    println!("[Argo] 命中限流，延迟后重试");
-                } else if msg.contains("解密算法未完成") {
-                    println!("[Argo] 收到加密数据但后端解密尚未实现，将继续等待...");
-                } else if msg.contains("未找到对应请求的密钥") {
-                    println!("[Argo] 未找到请求密钥，可能进程已重启或缓存丢失，停止轮询");
-                    return Err(msg);
-                } else {
-                    println!("[Argo] 轮询返回: {}", msg);
-                }
+            // 未完成授权或服务端限流等，继续等待
+            Err(PollError::Pending) => {}
+            Err(PollError::RateLimited(retry_after)) => {
+                println!("[Argo] 命中限流，{} 秒后重试", retry_after.as_secs());
+                interval = interval.max(retry_after);
+            }
+            Err(PollError::MissingKey) => {
+                println!("[Argo] 未找到请求密钥，可能进程已重启或缓存丢失，停止轮询");
+                return Err(PollError::MissingKey.into());
+            }
+            Err(PollError::Failed(msg)) => {
+                println!("[Argo] 轮询返回: {}", msg);
             }
         }
 
@@ -247,7 +290,7 @@ pub async fn argo_wait_authorization(request_uuid: String) -> Result<String, Str
             println!("[Argo] 轮询超时，结束等待");
             return Err("轮询超时，未完成授权".into());
         }
-        sleep(Duration::from_secs(5)).await;
+        sleep(interval).await;
     }
 }
 
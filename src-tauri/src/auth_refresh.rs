@@ -0,0 +1,100 @@
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::{argo_access, credentials, EventSink};
+
+// Authorization 失效后的处理：
+// 1. 服务端会在响应头中下发新的 Authorization，各请求路径已同步到安全存储；
+//    若存储中的值比本次请求使用的更新，直接换用新值重试，用户无感知
+// 2. 否则在后台发起 Argo 授权，此时必须由用户在浏览器中确认，
+//    才发出 auth-expired 事件（附带授权地址），授权完成后发出 auth-refreshed
+
+// 表示登录失效的提示信息
+const EXPIRED_KEYWORDS: &[&str] = &[
+    "未登录",
+    "登录失效",
+    "登录已失效",
+    "登录过期",
+    "登录已过期",
+    "token 失效",
+    "凭证失效",
+    "unauthorized",
+];
+
+static SINK: Lazy<Mutex<Option<Arc<dyn EventSink>>>> = Lazy::new(|| Mutex::new(None));
+// 同一时间只进行一次重新授权
+static REFRESHING: AtomicBool = AtomicBool::new(false);
+
+// 设置接收 auth-expired / auth-refreshed 事件的对象，未设置时只输出日志
+pub fn set_sink(sink: Arc<dyn EventSink>) {
+    *SINK.lock().unwrap() = Some(sink);
+}
+
+fn emit(event: &str, payload: serde_json::Value) {
+    match SINK.lock().unwrap().as_ref() {
+        Some(sink) => sink.emit_event(event, payload),
+        None => println!("{}: {}", event, payload),
+    }
+}
+
+pub fn is_expired_message(msg: &str) -> bool {
+    let msg = msg.to_lowercase();
+    EXPIRED_KEYWORDS.iter().any(|k| msg.contains(k))
+}
+
+// 根据状态码和响应体判断 Authorization 是否失效
+pub fn is_expired(status: u16, body: &serde_json::Value) -> bool {
+    if status == 401 || status == 403 {
+        return true;
+    }
+    body["flag"].as_bool() == Some(false) && is_expired_message(body["msg"].as_str().unwrap_or(""))
+}
+
+// account 的安全存储中比 used 更新的 Authorization，没有时返回 None
+pub fn refreshed_authorization(account: &str, used: Option<&str>) -> Option<String> {
+    credentials::get_for(account, credentials::AUTHORIZATION)
+        .filter(|stored| Some(stored.as_str()) != used)
+}
+
+// 无法自动恢复时在后台发起 Argo 授权，等待用户在浏览器中确认
+pub fn handle_expired(reason: &str) {
+    if REFRESHING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    println!("登录已失效: {}", reason);
+    let reason = reason.to_string();
+    tokio::spawn(async move {
+        let result = reauthorize(&reason).await;
+        REFRESHING.store(false, Ordering::SeqCst);
+        match result {
+            Ok(authorization) => {
+                emit(
+                    "auth-refreshed",
+                    serde_json::json!({ "authorization": authorization }),
+                );
+            }
+            Err(e) => {
+                println!("重新授权失败: {}", e);
+                emit(
+                    "auth-expired",
+                    serde_json::json!({ "reason": reason, "error": e }),
+                );
+            }
+        }
+    });
+}
+
+async fn reauthorize(reason: &str) -> Result<String, String> {
+    let login = argo_access::argo_request_login().await?;
+    emit(
+        "auth-expired",
+        serde_json::json!({
+            "reason": reason,
+            "authorization_url": login.authorization_url,
+            "request_uuid": login.request_uuid,
+        }),
+    );
+    // 授权成功后 Authorization 已保存到安全存储
+    argo_access::argo_wait_authorization(login.request_uuid).await
+}
@@ -0,0 +1,511 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{control, instance_registry, openfrp_api, tunnel_spec, ConsoleSink, FrpcProcesses};
+
+// 命令行子命令，无需打开窗口即可管理隧道
+pub enum CliCommand {
+    TunnelsList {
+        json: bool,
+    },
+    TunnelsApply {
+        file: String,
+        dry_run: bool,
+        json: bool,
+    },
+    Start {
+        id: String,
+    },
+    Stop {
+        id: String,
+    },
+    Status {
+        json: bool,
+    },
+    FrpcInstall,
+    Logs {
+        id: String,
+        follow: bool,
+    },
+    Diagnose {
+        json: bool,
+    },
+    #[cfg(feature = "selftest")]
+    SelfTest {
+        json: bool,
+        keep: bool,
+    },
+    CredentialsSet {
+        key: String,
+        value: Option<String>,
+    },
+    CredentialsClear {
+        key: String,
+    },
+    Daemon,
+    DaemonUnit {
+        install: bool,
+        user: bool,
+    },
+    Help,
+}
+
+const USAGE: &str = "用法: openfrp_cpl <命令> [参数]
+
+命令:
+  tunnels list [--json]   列出当前账户的隧道
+  tunnels apply <文件> [--dry-run] [--json]
+                          按 TOML/JSON 模板新建、修改或删除隧道，--dry-run 只显示修改计划
+  start <id>              启动隧道（前台运行，Ctrl+C 结束）
+  stop <id>               停止隧道
+  status [--json]         查看正在运行的隧道
+  frpc install            下载或更新 frpc
+  logs <id> [--follow]    查看隧道日志
+  diagnose [--json]       运行网络诊断并保存报告文件
+  credentials set <token|authorization> [值]
+                          保存当前账户的用户密钥或 Authorization，省略值时从标准输入读取
+  credentials clear <token|authorization>
+                          清除当前账户保存的凭据
+  --daemon                以无界面守护进程运行，自动启动并守护当前账户的默认隧道
+  daemon unit [--user]    输出 systemd 服务单元
+  daemon install-unit [--user]
+                          安装 systemd 服务单元
+  help                    显示本帮助
+
+守护进程或启动器运行时，start、stop 和 frpc install 通过本地控制套接字交给它执行。";
+
+// 解析命令行参数（不含程序名），返回 None 表示按界面模式启动
+pub fn parse(args: &[String]) -> Option<Result<CliCommand, String>> {
+    let first = args.first()?.as_str();
+    let rest = &args[1..];
+    let has_flag = |flag: &str| rest.iter().any(|a| a == flag);
+    let positional = || {
+        rest.iter()
+            .find(|a| !a.starts_with('-'))
+            .cloned()
+            .ok_or_else(|| format!("缺少隧道 ID\n\n{}", USAGE))
+    };
+
+    let command = match first {
+        "tunnels" => match rest.first().map(String::as_str) {
+            Some("list") => Ok(CliCommand::TunnelsList {
+                json: has_flag("--json"),
+            }),
+            Some("apply") => rest[1..]
+                .iter()
+                .find(|a| !a.starts_with('-'))
+                .cloned()
+                .ok_or_else(|| format!("缺少模板文件路径\n\n{}", USAGE))
+                .map(|file| CliCommand::TunnelsApply {
+                    file,
+                    dry_run: has_flag("--dry-run"),
+                    json: has_flag("--json"),
+                }),
+            _ => Err(format!("未知的 tunnels 子命令\n\n{}", USAGE)),
+        },
+        "start" => positional().map(|id| CliCommand::Start { id }),
+        "stop" => positional().map(|id| CliCommand::Stop { id }),
+        "status" => Ok(CliCommand::Status {
+            json: has_flag("--json"),
+        }),
+        "frpc" => match rest.first().map(String::as_str) {
+            Some("install") => Ok(CliCommand::FrpcInstall),
+            _ => Err(format!("未知的 frpc 子命令\n\n{}", USAGE)),
+        },
+        "logs" => positional().map(|id| CliCommand::Logs {
+            id,
+            follow: has_flag("--follow") || has_flag("-f"),
+        }),
+        "diagnose" => Ok(CliCommand::Diagnose {
+            json: has_flag("--json"),
+        }),
+        #[cfg(feature = "selftest")]
+        "selftest" => Ok(CliCommand::SelfTest {
+            json: has_flag("--json"),
+            keep: has_flag("--keep"),
+        }),
+        "credentials" => {
+            let key = rest
+                .get(1)
+                .cloned()
+                .ok_or_else(|| format!("缺少凭据名称\n\n{}", USAGE));
+            match rest.first().map(String::as_str) {
+                Some("set") => key.map(|key| CliCommand::CredentialsSet {
+                    key,
+                    value: rest.get(2).cloned(),
+                }),
+                Some("clear") => key.map(|key| CliCommand::CredentialsClear { key }),
+                _ => Err(format!("未知的 credentials 子命令\n\n{}", USAGE)),
+            }
+        }
+        "--daemon" => Ok(CliCommand::Daemon),
+        "daemon" => match rest.first().map(String::as_str) {
+            Some("unit") => Ok(CliCommand::DaemonUnit {
+                install: false,
+                user: has_flag("--user"),
+            }),
+            Some("install-unit") => Ok(CliCommand::DaemonUnit {
+                install: true,
+                user: has_flag("--user"),
+            }),
+            _ => Err(format!("未知的 daemon 子命令\n\n{}", USAGE)),
+        },
+        "help" | "--help" | "-h" => Ok(CliCommand::Help),
+        // 其他参数（如 --autostart、深度链接）交给界面处理
+        _ => return None,
+    };
+    Some(command)
+}
+
+// 执行命令行命令，返回进程退出码
+pub fn run(command: CliCommand) -> i32 {
+    if let CliCommand::Daemon = command {
+        return crate::daemon::run();
+    }
+    // 自检使用独立的临时程序目录，不读取用户配置
+    #[cfg(feature = "selftest")]
+    if let CliCommand::SelfTest { json, keep } = command {
+        return crate::selftest::run(json, keep);
+    }
+
+    if let Err(e) = crate::init_app_directory_headless() {
+        eprintln!("初始化程序目录失败: {}", e);
+        return 1;
+    }
+    crate::apply_config_env();
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("创建运行时失败: {}", e);
+            return 1;
+        }
+    };
+
+    match runtime.block_on(execute(command)) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+async fn execute(command: CliCommand) -> Result<(), String> {
+    match command {
+        CliCommand::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        CliCommand::TunnelsList { json } => tunnels_list(json).await,
+        CliCommand::TunnelsApply {
+            file,
+            dry_run,
+            json,
+        } => tunnels_apply(&file, dry_run, json).await,
+        CliCommand::Start { id } => {
+            let params = serde_json::json!({ "id": id });
+            match control::request("start", params).await {
+                Some(result) => result.map(|msg| {
+                    println!("{}", msg.as_str().unwrap_or("隧道已由守护进程启动"));
+                }),
+                None => start_foreground(id).await,
+            }
+        }
+        CliCommand::Stop { id } => {
+            let params = serde_json::json!({ "id": id });
+            match control::request("stop", params).await {
+                Some(result) => result.map(|_| println!("已停止隧道 {}", id)),
+                None => stop(&id),
+            }
+        }
+        CliCommand::Status { json } => status(json),
+        CliCommand::CredentialsSet { key, value } => {
+            let value = match value {
+                Some(value) => value,
+                None => read_secret(&key)?,
+            };
+            crate::credentials::set_credential(key.clone(), value, None)?;
+            println!(
+                "已保存账户 {} 的 {}",
+                crate::accounts::active_account(),
+                key
+            );
+            Ok(())
+        }
+        CliCommand::CredentialsClear { key } => {
+            crate::credentials::clear_credential(key.clone(), None)?;
+            println!(
+                "已清除账户 {} 的 {}",
+                crate::accounts::active_account(),
+                key
+            );
+            Ok(())
+        }
+        CliCommand::Daemon => Ok(()),
+        #[cfg(feature = "selftest")]
+        CliCommand::SelfTest { .. } => Ok(()),
+        CliCommand::DaemonUnit { install, user } => {
+            let output = if install {
+                crate::daemon::install_systemd_unit(user)?
+            } else {
+                crate::daemon::systemd_unit(user)?
+            };
+            print!("{}", output);
+            if install {
+                println!();
+            }
+            Ok(())
+        }
+        CliCommand::FrpcInstall => {
+            // 由正在运行的实例安装，避免替换其正在使用的 frpc
+            let result = match control::request("frpc.install", serde_json::json!({})).await {
+                Some(result) => result.map(|msg| msg.as_str().unwrap_or_default().to_string()),
+                None => crate::install_frpc(&ConsoleSink).await,
+            };
+            result.map(|msg| println!("{}", msg))
+        }
+        CliCommand::Logs { id, follow } => logs(&id, follow).await,
+        CliCommand::Diagnose { json } => diagnose(json).await,
+    }
+}
+
+// 从标准输入读取凭据，避免出现在命令行历史和进程列表中
+fn read_secret(key: &str) -> Result<String, String> {
+    eprint!("请输入 {}: ", key);
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .map_err(|e| format!("读取输入失败: {}", e))?;
+    let value = line.trim().to_string();
+    if value.is_empty() {
+        return Err("输入为空".to_string());
+    }
+    Ok(value)
+}
+
+async fn diagnose(json: bool) -> Result<(), String> {
+    if !json {
+        println!("正在进行网络诊断，请稍候...");
+    }
+    let mut report = crate::diagnostics::run().await;
+    let path = crate::diagnostics::save(&report)?;
+    report.file = Some(path.to_string_lossy().to_string());
+    if json {
+        let output = serde_json::to_string_pretty(&report)
+            .map_err(|e| format!("序列化诊断报告失败: {}", e))?;
+        println!("{}", output);
+    } else {
+        print!("{}", crate::diagnostics::render(&report));
+        println!("\n报告已保存到 {}", path.display());
+    }
+    Ok(())
+}
+
+async fn tunnels_list(json: bool) -> Result<(), String> {
+    let fetched = openfrp_api::OpenFrpClient::new()?
+        .user_proxies()
+        .await
+        .map_err(|e| format!("获取隧道列表失败: {}", e))?;
+    if let Some(saved_at) = fetched.cached_at {
+        let saved = chrono::DateTime::from_timestamp(saved_at, 0)
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        eprintln!("无法连接服务器，以下为 {} 缓存的隧道列表", saved);
+    }
+    let proxies = fetched.data;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&proxies.list).map_err(|e| e.to_string())?
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{:<10} {:<20} {:<6} {:<16} {:<22} 连接地址",
+        "ID", "名称", "类型", "节点", "本地地址"
+    );
+    for proxy in proxies.list {
+        println!(
+            "{:<10} {:<20} {:<6} {:<16} {:<22} {}",
+            proxy.id,
+            proxy.proxy_name,
+            proxy.proxy_type,
+            proxy.friendly_node,
+            format!("{}:{}", proxy.local_ip, proxy.local_port),
+            proxy.connect_address
+        );
+    }
+    Ok(())
+}
+
+async fn tunnels_apply(file: &str, dry_run: bool, json: bool) -> Result<(), String> {
+    let spec = tunnel_spec::load_spec(std::path::Path::new(file)).map_err(|e| e.to_string())?;
+    let report = tunnel_spec::apply(&spec, dry_run)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?
+        );
+    } else {
+        for item in &report.plan {
+            let action = match item.action {
+                tunnel_spec::SpecAction::Create => "新建",
+                tunnel_spec::SpecAction::Update => "修改",
+                tunnel_spec::SpecAction::Delete => "删除",
+                tunnel_spec::SpecAction::Unchanged => "不变",
+            };
+            let mut line = format!("{} {}", action, item.name);
+            if let Some(id) = item.proxy_id {
+                line.push_str(&format!(" (#{})", id));
+            }
+            if !item.changes.is_empty() {
+                line.push_str(&format!(" [{}]", item.changes.join(", ")));
+            }
+            match (item.applied, &item.message) {
+                (Some(true), Some(message)) => line.push_str(&format!(" - 完成: {}", message)),
+                (_, Some(message)) => line.push_str(&format!(" - 失败: {}", message)),
+                _ => {}
+            }
+            println!("{}", line);
+            for warning in &item.warnings {
+                println!("  警告: {}", warning);
+            }
+        }
+    }
+
+    if !report.valid {
+        return Err("模板中有隧道未通过检查，未执行任何修改".to_string());
+    }
+    if report.plan.iter().any(|item| item.applied == Some(false)) {
+        return Err("部分隧道修改失败".to_string());
+    }
+    if dry_run {
+        eprintln!("以上为修改计划，去掉 --dry-run 后执行");
+    }
+    Ok(())
+}
+
+// 前台运行隧道，frpc 退出或按下 Ctrl+C 时结束
+async fn start_foreground(id: String) -> Result<(), String> {
+    if instance_registry::list().iter().any(|r| r.id == id) {
+        return Err("该隧道已经在运行中".to_string());
+    }
+
+    let processes = FrpcProcesses::default();
+    crate::spawn_frpc(
+        &processes,
+        Arc::new(ConsoleSink),
+        id.clone(),
+        None,
+        id.clone(),
+    )?;
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {
+                println!("正在停止隧道 {}", id);
+                return crate::stop_frpc(&processes, &id);
+            }
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {
+                let mut map = processes.0.lock().map_err(|e| e.to_string())?;
+                let exited = match map.get_mut(&id) {
+                    Some(info) => !matches!(info.child.try_wait(), Ok(None)),
+                    None => true,
+                };
+                if exited {
+                    map.remove(&id);
+                    instance_registry::sync(&map);
+                    println!("frpc 已退出");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn stop(id: &str) -> Result<(), String> {
+    let record = instance_registry::list()
+        .into_iter()
+        .find(|r| r.id == id)
+        .ok_or("该隧道未在运行")?;
+    instance_registry::kill(&record)?;
+    println!("已停止隧道 {}", id);
+    Ok(())
+}
+
+fn status(json: bool) -> Result<(), String> {
+    let records = instance_registry::list();
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&records).map_err(|e| e.to_string())?
+        );
+        return Ok(());
+    }
+
+    if records.is_empty() {
+        println!("没有正在运行的隧道");
+        return Ok(());
+    }
+    println!(
+        "{:<10} {:<10} {:<8} {:<12} 启动时间",
+        "ID", "隧道", "PID", "账户"
+    );
+    for r in records {
+        let started = chrono::DateTime::from_timestamp(r.started_at, 0)
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        println!(
+            "{:<10} {:<10} {:<8} {:<12} {}",
+            r.id, r.tunnel_id, r.pid, r.account, started
+        );
+    }
+    Ok(())
+}
+
+async fn logs(id: &str, follow: bool) -> Result<(), String> {
+    let path = crate::frpc_log_path(id);
+    let mut file = std::fs::File::open(&path).map_err(|_| format!("没有隧道 {} 的日志", id))?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)
+        .map_err(|e| format!("读取日志失败: {}", e))?;
+    print!("{}", content);
+
+    if !follow {
+        return Ok(());
+    }
+
+    // 持续输出新增内容，日志被重新创建时从头读取
+    let mut position = file.stream_position().map_err(|e| e.to_string())?;
+    loop {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let len = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if len < position {
+            file = std::fs::File::open(&path).map_err(|e| e.to_string())?;
+            position = 0;
+        }
+        if len > position {
+            file.seek(SeekFrom::Start(position))
+                .map_err(|e| e.to_string())?;
+            let mut chunk = String::new();
+            file.read_to_string(&mut chunk).map_err(|e| e.to_string())?;
+            print!("{}", chunk);
+            position = file.stream_position().map_err(|e| e.to_string())?;
+        }
+    }
+}
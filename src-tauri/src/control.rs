@@ -0,0 +1,547 @@
+use once_cell::sync::Lazy;
+use rand_core::{OsRng, RngCore};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::command;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::broadcast;
+
+use crate::{EventSink, FrpcProcesses};
+
+// 本地控制接口，供部署脚本、Stream Deck 插件等本机程序管理隧道。
+// 协议为按行分隔的 JSON-RPC 2.0，连接后需先调用 auth 方法提交本机令牌：
+//   {"jsonrpc":"2.0","id":1,"method":"auth","params":{"token":"..."}}
+// 支持的方法：auth、list、status、start、stop、logs.subscribe、logs.unsubscribe。
+// 订阅日志后服务端推送通知 {"jsonrpc":"2.0","method":"log","params":{"id":"...","line":"..."}}
+
+const TOKEN_FILE: &str = "control.token";
+
+// JSON-RPC 错误码
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const OPERATION_FAILED: i64 = -32000;
+const UNAUTHORIZED: i64 = -32001;
+
+#[derive(Deserialize)]
+pub struct ControlRequest {
+    #[serde(default)]
+    pub id: Option<serde_json::Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: serde_json::Value,
+}
+
+pub struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+// 启动、停止等操作返回的错误
+impl From<String> for RpcError {
+    fn from(message: String) -> Self {
+        RpcError::new(OPERATION_FAILED, message)
+    }
+}
+
+#[derive(Clone)]
+pub struct ControlContext {
+    pub processes: FrpcProcesses,
+    pub sink: Arc<dyn EventSink>,
+    // 需要保持运行的隧道，仅守护进程会在其异常退出后重新拉起
+    pub supervised: Arc<Mutex<HashMap<String, SupervisedTunnel>>>,
+}
+
+// 重新拉起隧道时沿用的启动参数
+#[derive(Clone)]
+pub struct SupervisedTunnel {
+    pub tunnel_id: String,
+    // 通过 start 传入的用户密钥，未传入时使用安全存储中的密钥
+    pub token: Option<String>,
+}
+
+// frpc 输出的每一行日志，转发给订阅了日志的连接
+#[derive(Clone)]
+pub struct LogLine {
+    pub id: String,
+    pub line: String,
+}
+
+static LOG_CHANNEL: Lazy<broadcast::Sender<LogLine>> = Lazy::new(|| broadcast::channel(512).0);
+
+pub fn publish_log(id: &str, line: &str) {
+    // 没有订阅者时发送失败，直接忽略
+    let _ = LOG_CHANNEL.send(LogLine {
+        id: id.to_string(),
+        line: line.to_string(),
+    });
+}
+
+// 套接字放在仅所有者可访问的目录中，绑定后再修改权限之前其他用户也无法连接
+#[cfg(unix)]
+fn socket_dir() -> PathBuf {
+    crate::get_app_dir().join("control")
+}
+
+#[cfg(unix)]
+pub fn socket_path() -> PathBuf {
+    socket_dir().join("control.sock")
+}
+
+#[cfg(windows)]
+fn pipe_name() -> String {
+    format!(r"\\.\pipe\{}.control", crate::APP_IDENTIFIER)
+}
+
+// 控制接口地址，用于展示给用户
+pub fn endpoint() -> String {
+    #[cfg(unix)]
+    {
+        socket_path().to_string_lossy().to_string()
+    }
+    #[cfg(windows)]
+    {
+        pipe_name()
+    }
+}
+
+fn token_path() -> PathBuf {
+    crate::get_app_dir().join(TOKEN_FILE)
+}
+
+// 读取本机控制令牌，首次使用时随机生成并以仅所有者可读的权限保存
+pub fn control_token() -> Result<String, String> {
+    use std::io::Write;
+
+    let path = token_path();
+    if let Ok(token) = std::fs::read_to_string(&path) {
+        let token = token.trim().to_string();
+        if !token.is_empty() {
+            return Ok(token);
+        }
+    }
+
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    // 创建时即设置权限，且不覆盖其他进程同时生成的令牌
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = match options.open(&path) {
+        Ok(file) => Ok(file),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            // 另一个进程刚刚生成了令牌
+            if let Some(token) = std::fs::read_to_string(&path)
+                .ok()
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+            {
+                return Ok(token);
+            }
+            // 遗留的空文件，删除后重新创建
+            let _ = std::fs::remove_file(&path);
+            options.open(&path)
+        }
+        Err(e) => Err(e),
+    };
+    file.and_then(|mut file| file.write_all(token.as_bytes()))
+        .map_err(|e| format!("保存控制令牌失败: {}", e))?;
+    Ok(token)
+}
+
+// 逐字节比较，避免通过响应时间猜测令牌
+fn token_matches(expected: &str, provided: &str) -> bool {
+    expected.len() == provided.len()
+        && expected
+            .bytes()
+            .zip(provided.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn param_id(params: &serde_json::Value) -> Result<String, RpcError> {
+    match &params["id"] {
+        serde_json::Value::String(id) => Ok(id.clone()),
+        serde_json::Value::Number(id) => Ok(id.to_string()),
+        _ => Err(RpcError::new(INVALID_PARAMS, "缺少参数 id")),
+    }
+}
+
+// 列出正在运行的隧道，与 get_running_instances 命令返回相同的字段
+fn list_running(ctx: &ControlContext) -> serde_json::Value {
+    let mut list = Vec::new();
+    if let Ok(mut map) = ctx.processes.0.lock() {
+        map.retain(|_, info| matches!(info.child.try_wait(), Ok(None)));
+        crate::instance_registry::sync(&map);
+        for (id, info) in map.iter() {
+            list.push(serde_json::json!({
+                "id": id,
+                "tunnel_id": info.tunnel_id,
+                "pid": info.child.id(),
+                "account": info.account,
+                "started_at": info.started_at,
+            }));
+        }
+    }
+    serde_json::Value::Array(list)
+}
+
+// 单个隧道的运行状态，与 check_frpc_status 命令一致
+fn tunnel_status(ctx: &ControlContext, id: &str) -> serde_json::Value {
+    let mut pid = None;
+    if let Ok(mut map) = ctx.processes.0.lock() {
+        if let Some(info) = map.get_mut(id) {
+            if matches!(info.child.try_wait(), Ok(None)) {
+                pid = Some(info.child.id());
+            } else {
+                map.remove(id);
+                crate::instance_registry::sync(&map);
+            }
+        }
+    }
+    serde_json::json!({ "id": id, "running": pid.is_some(), "pid": pid })
+}
+
+// 每个连接各自的状态
+#[derive(Default)]
+struct Session {
+    authenticated: bool,
+    // 已订阅日志时保存接收端，以及只关注的隧道 ID
+    logs: Option<(broadcast::Receiver<LogLine>, Option<String>)>,
+}
+
+fn handle_request(
+    ctx: &ControlContext,
+    session: &mut Session,
+    request: &ControlRequest,
+) -> Result<serde_json::Value, RpcError> {
+    if request.method == "auth" {
+        let expected = control_token()?;
+        let provided = request.params["token"].as_str().unwrap_or_default();
+        if !token_matches(&expected, provided) {
+            return Err(RpcError::new(UNAUTHORIZED, "控制令牌无效"));
+        }
+        session.authenticated = true;
+        return Ok(serde_json::Value::Bool(true));
+    }
+    if !session.authenticated {
+        return Err(RpcError::new(UNAUTHORIZED, "请先调用 auth 提交控制令牌"));
+    }
+
+    match request.method.as_str() {
+        "list" => Ok(list_running(ctx)),
+        "status" => match param_id(&request.params) {
+            Ok(id) => Ok(tunnel_status(ctx, &id)),
+            Err(_) => Ok(list_running(ctx)),
+        },
+        "start" => {
+            let id = param_id(&request.params)?;
+            let tunnel_id = request.params["tunnel_id"]
+                .as_str()
+                .map(String::from)
+                .unwrap_or_else(|| id.clone());
+            let token = request.params["token"].as_str().map(String::from);
+            let message = crate::spawn_frpc(
+                &ctx.processes,
+                ctx.sink.clone(),
+                id.clone(),
+                token.clone(),
+                tunnel_id.clone(),
+            )?;
+            ctx.supervised
+                .lock()
+                .unwrap()
+                .insert(id, SupervisedTunnel { tunnel_id, token });
+            Ok(serde_json::json!(message))
+        }
+        "stop" => {
+            let id = param_id(&request.params)?;
+            ctx.supervised.lock().unwrap().remove(&id);
+            crate::stop_frpc(&ctx.processes, &id)?;
+            Ok(serde_json::Value::Null)
+        }
+        // 请求在阻塞线程池中处理，可以等待下载完成
+        "frpc.install" => tokio::runtime::Handle::current()
+            .block_on(crate::install_frpc(ctx.sink.as_ref()))
+            .map(|message| serde_json::json!(message))
+            .map_err(RpcError::from),
+        "logs.subscribe" => {
+            let filter = param_id(&request.params).ok();
+            session.logs = Some((LOG_CHANNEL.subscribe(), filter));
+            Ok(serde_json::Value::Bool(true))
+        }
+        "logs.unsubscribe" => {
+            session.logs = None;
+            Ok(serde_json::Value::Bool(true))
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("未知的方法: {}", request.method),
+        )),
+    }
+}
+
+fn response_line(id: serde_json::Value, result: Result<serde_json::Value, RpcError>) -> String {
+    let value = match result {
+        Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": error.code, "message": error.message },
+        }),
+    };
+    format!("{}\n", value)
+}
+
+// 处理一行请求，通知（不带 id 的请求）不返回响应
+fn handle_line(ctx: &ControlContext, session: &mut Session, line: &str) -> Option<String> {
+    let value: serde_json::Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) => {
+            return Some(response_line(
+                serde_json::Value::Null,
+                Err(RpcError::new(PARSE_ERROR, format!("请求格式错误: {}", e))),
+            ))
+        }
+    };
+    let request: ControlRequest = match serde_json::from_value(value) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(response_line(
+                serde_json::Value::Null,
+                Err(RpcError::new(INVALID_REQUEST, format!("无效的请求: {}", e))),
+            ))
+        }
+    };
+    let result = handle_request(ctx, session, &request);
+    request.id.map(|id| response_line(id, result))
+}
+
+// 请求会读写文件、启动或结束进程，放到阻塞线程池中执行，避免阻塞其他连接
+async fn handle_line_blocking(
+    ctx: &ControlContext,
+    session: &mut Session,
+    line: String,
+) -> Option<String> {
+    let ctx = ctx.clone();
+    let mut owned = std::mem::take(session);
+    let (owned, output) = tokio::task::spawn_blocking(move || {
+        let output = handle_line(&ctx, &mut owned, &line);
+        (owned, output)
+    })
+    .await
+    .ok()?;
+    *session = owned;
+    output
+}
+
+// 等待下一条订阅的日志，未订阅时一直挂起
+async fn next_log(logs: &mut Option<(broadcast::Receiver<LogLine>, Option<String>)>) -> LogLine {
+    let Some((receiver, filter)) = logs else {
+        return std::future::pending().await;
+    };
+    loop {
+        match receiver.recv().await {
+            Ok(entry) => {
+                if filter.as_ref().is_none_or(|id| *id == entry.id) {
+                    return entry;
+                }
+            }
+            // 客户端读取太慢时丢弃积压的日志
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return std::future::pending().await,
+        }
+    }
+}
+
+async fn handle_connection<S>(stream: S, ctx: ControlContext)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    let mut session = Session::default();
+
+    loop {
+        let output = tokio::select! {
+            line = lines.next_line() => match line {
+                Ok(Some(line)) if line.trim().is_empty() => continue,
+                Ok(Some(line)) => match handle_line_blocking(&ctx, &mut session, line).await {
+                    Some(output) => output,
+                    None => continue,
+                },
+                _ => break,
+            },
+            entry = next_log(&mut session.logs) => format!(
+                "{}\n",
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "method": "log",
+                    "params": { "id": entry.id, "line": entry.line },
+                })
+            ),
+        };
+        if writer.write_all(output.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+// 在 Unix 套接字上提供控制接口，仅当前用户可访问
+#[cfg(unix)]
+pub async fn serve(ctx: ControlContext) -> Result<(), String> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    use tokio::net::UnixListener;
+
+    control_token()?;
+    let dir = socket_dir();
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .map_err(|e| format!("创建控制套接字目录失败: {}", e))?;
+    // 目录已存在时其权限可能不同
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+        .map_err(|e| format!("设置控制套接字目录权限失败: {}", e))?;
+    let path = socket_path();
+    if path.exists() {
+        // 能连上说明守护进程或界面已经在提供控制接口
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            return Err("控制接口已由其他实例提供".to_string());
+        }
+        // 清理上次异常退出遗留的套接字文件
+        let _ = std::fs::remove_file(&path);
+    }
+    let listener = UnixListener::bind(&path).map_err(|e| format!("创建控制套接字失败: {}", e))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("设置控制套接字权限失败: {}", e))?;
+    println!("本地控制接口: {}", path.display());
+
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                println!("接受控制连接失败: {}", e);
+                continue;
+            }
+        };
+        tokio::spawn(handle_connection(stream, ctx.clone()));
+    }
+}
+
+// 在命名管道上提供控制接口，拒绝远程客户端，并依靠控制令牌区分本机程序
+#[cfg(windows)]
+pub async fn serve(ctx: ControlContext) -> Result<(), String> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    control_token()?;
+    let name = pipe_name();
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .reject_remote_clients(true)
+        .create(&name)
+        .map_err(|e| format!("创建控制管道失败（可能已由其他实例提供）: {}", e))?;
+    println!("本地控制接口: {}", name);
+
+    let next_instance = || {
+        ServerOptions::new()
+            .reject_remote_clients(true)
+            .create(&name)
+            .map_err(|e| format!("创建控制管道失败: {}", e))
+    };
+
+    loop {
+        if let Err(e) = server.connect().await {
+            // 出错的管道实例无法再使用，换成新的实例继续等待连接
+            println!("接受控制连接失败: {}", e);
+            server = next_instance()?;
+            continue;
+        }
+        let connected = std::mem::replace(&mut server, next_instance()?);
+        tokio::spawn(handle_connection(connected, ctx.clone()));
+    }
+}
+
+// 调用一次控制接口，依次发送 auth 与实际请求
+async fn call<S>(
+    stream: S,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<serde_json::Value, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let token = control_token()?;
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    let requests = [
+        ("auth", serde_json::json!({ "token": token })),
+        (method, params),
+    ];
+    let mut result = serde_json::Value::Null;
+    for (index, (method, params)) in requests.into_iter().enumerate() {
+        let line = format!(
+            "{}\n",
+            serde_json::json!({ "jsonrpc": "2.0", "id": index, "method": method, "params": params })
+        );
+        writer
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| format!("发送请求失败: {}", e))?;
+        let response = lines
+            .next_line()
+            .await
+            .map_err(|e| format!("读取响应失败: {}", e))?
+            .ok_or("控制接口已断开连接")?;
+        let value: serde_json::Value =
+            serde_json::from_str(&response).map_err(|e| format!("解析响应失败: {}", e))?;
+        if let Some(error) = value.get("error") {
+            return Err(error["message"].as_str().unwrap_or("未知错误").to_string());
+        }
+        result = value["result"].clone();
+    }
+    Ok(result)
+}
+
+// 向正在运行的守护进程或界面发送请求，返回 None 表示没有实例提供控制接口
+pub async fn request(
+    method: &str,
+    params: serde_json::Value,
+) -> Option<Result<serde_json::Value, String>> {
+    #[cfg(unix)]
+    let stream = tokio::net::UnixStream::connect(socket_path()).await.ok()?;
+    #[cfg(windows)]
+    let stream = tokio::net::windows::named_pipe::ClientOptions::new()
+        .open(pipe_name())
+        .ok()?;
+    Some(call(stream, method, params).await)
+}
+
+// 获取控制接口地址和令牌，供用户配置第三方工具
+#[command]
+pub fn get_control_info() -> Result<serde_json::Value, String> {
+    Ok(serde_json::json!({
+        "endpoint": endpoint(),
+        "token": control_token()?,
+        "token_file": token_path(),
+    }))
+}
@@ -0,0 +1,272 @@
+use once_cell::sync::Lazy;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::command;
+use xsalsa20poly1305::aead::{Aead, KeyInit};
+use xsalsa20poly1305::XSalsa20Poly1305;
+
+// 凭据安全存储：优先使用系统钥匙串（Windows 凭据管理器 / macOS Keychain / Secret Service），
+// 不可用时回退到使用机器派生密钥加密的本地文件
+
+// 用户密钥（frpc -u 参数）
+pub const TOKEN: &str = "token";
+// API 鉴权使用的 Authorization
+pub const AUTHORIZATION: &str = "authorization";
+// 网络代理的密码，不区分账户
+pub const PROXY_PASSWORD: &str = "proxy_password";
+
+const KEYRING_SERVICE: &str = crate::APP_IDENTIFIER;
+const CREDENTIALS_FILE: &str = "credentials.bin";
+const NONCE_LEN: usize = 24;
+
+// 内存缓存，避免频繁访问钥匙串
+static CACHE: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn check_key(key: &str) -> Result<(), String> {
+    match key {
+        TOKEN | AUTHORIZATION => Ok(()),
+        _ => Err(format!("不支持的凭据名称: {}", key)),
+    }
+}
+
+// ===== 系统钥匙串 =====
+
+fn keyring_entry(key: &str) -> Option<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, key).ok()
+}
+
+// 返回 Err 表示钥匙串不可用，Ok(None) 表示钥匙串中没有该凭据
+fn keyring_get(key: &str) -> Result<Option<String>, ()> {
+    let entry = keyring_entry(key).ok_or(())?;
+    match entry.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => {
+            println!("[Credentials] 读取钥匙串失败: {}", e);
+            Err(())
+        }
+    }
+}
+
+fn keyring_set(key: &str, value: &str) -> bool {
+    match keyring_entry(key).map(|entry| entry.set_password(value)) {
+        Some(Ok(())) => true,
+        Some(Err(e)) => {
+            println!("[Credentials] 写入钥匙串失败，改用加密文件: {}", e);
+            false
+        }
+        None => false,
+    }
+}
+
+fn keyring_delete(key: &str) {
+    if let Some(entry) = keyring_entry(key) {
+        let _ = entry.delete_credential();
+    }
+}
+
+// ===== 加密文件回退 =====
+
+fn credentials_path() -> PathBuf {
+    crate::get_app_dir().join(CREDENTIALS_FILE)
+}
+
+// 读取本机唯一标识，用于派生加密密钥
+fn machine_id() -> String {
+    #[cfg(target_os = "linux")]
+    {
+        for path in ["/etc/machine-id", "/var/lib/dbus/machine-id"] {
+            if let Ok(id) = fs::read_to_string(path) {
+                let id = id.trim();
+                if !id.is_empty() {
+                    return id.to_string();
+                }
+            }
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use winreg::enums::*;
+        use winreg::RegKey;
+
+        let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+        if let Ok(key) = hklm.open_subkey("SOFTWARE\\Microsoft\\Cryptography") {
+            if let Ok(guid) = key.get_value::<String, _>("MachineGuid") {
+                return guid;
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        if let Ok(output) = std::process::Command::new("ioreg")
+            .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+            .output()
+        {
+            let content = String::from_utf8_lossy(&output.stdout);
+            for line in content.lines() {
+                if line.contains("IOPlatformUUID") {
+                    if let Some(uuid) = line.split('"').nth(3) {
+                        return uuid.to_string();
+                    }
+                }
+            }
+        }
+    }
+
+    // 最后的备用方案：主目录路径
+    dirs::home_dir()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|| "openfrp-cpl".to_string())
+}
+
+fn file_cipher() -> XSalsa20Poly1305 {
+    let mut hasher = Sha256::new();
+    hasher.update(b"OpenFrp-CPL credentials v1");
+    hasher.update(machine_id().as_bytes());
+    let key = hasher.finalize();
+    XSalsa20Poly1305::new(&key)
+}
+
+fn load_file() -> HashMap<String, String> {
+    let data = match fs::read(credentials_path()) {
+        Ok(data) if data.len() > NONCE_LEN => data,
+        _ => return HashMap::new(),
+    };
+    let (nonce, cipher) = data.split_at(NONCE_LEN);
+    match file_cipher().decrypt(nonce.into(), cipher) {
+        Ok(plain) => serde_json::from_slice(&plain).unwrap_or_default(),
+        Err(_) => {
+            println!("[Credentials] 凭据文件解密失败，可能来自其他设备，已忽略");
+            HashMap::new()
+        }
+    }
+}
+
+fn save_file(map: &HashMap<String, String>) -> Result<(), String> {
+    let path = credentials_path();
+    if map.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("删除凭据文件失败: {}", e))?;
+        }
+        return Ok(());
+    }
+
+    let plain = serde_json::to_vec(map).map_err(|e| format!("序列化凭据失败: {}", e))?;
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let cipher = file_cipher()
+        .encrypt(&nonce.into(), plain.as_slice())
+        .map_err(|_| "加密凭据失败".to_string())?;
+
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(&cipher);
+    fs::write(&path, data).map_err(|e| format!("保存凭据文件失败: {}", e))?;
+
+    // 仅允许当前用户读写
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&path, fs::Permissions::from_mode(0o600));
+    }
+
+    Ok(())
+}
+
+// ===== 对外接口 =====
+
+// 各账户的凭据分开存储，默认账户沿用不带前缀的名称
+fn storage_key(account: &str, key: &str) -> String {
+    if account == crate::accounts::DEFAULT_ACCOUNT {
+        key.to_string()
+    } else {
+        format!("{}:{}", account, key)
+    }
+}
+
+pub fn get_for(account: &str, key: &str) -> Option<String> {
+    let key = storage_key(account, key);
+    if let Some(value) = CACHE.lock().unwrap().get(&key) {
+        return Some(value.clone());
+    }
+
+    let value = match keyring_get(&key) {
+        Ok(Some(value)) => Some(value),
+        // 钥匙串中没有或不可用时，再检查加密文件
+        _ => load_file().remove(&key),
+    };
+
+    if let Some(v) = &value {
+        CACHE.lock().unwrap().insert(key, v.clone());
+    }
+    value
+}
+
+pub fn set_for(account: &str, key: &str, value: &str) -> Result<(), String> {
+    if value.is_empty() {
+        return clear_for(account, key);
+    }
+
+    let key = storage_key(account, key);
+    let mut map = load_file();
+    if keyring_set(&key, value) {
+        // 已写入钥匙串，清理文件中的旧副本
+        if map.remove(&key).is_some() {
+            save_file(&map)?;
+        }
+    } else {
+        map.insert(key.clone(), value.to_string());
+        save_file(&map)?;
+    }
+
+    CACHE.lock().unwrap().insert(key, value.to_string());
+    Ok(())
+}
+
+pub fn clear_for(account: &str, key: &str) -> Result<(), String> {
+    let key = storage_key(account, key);
+    keyring_delete(&key);
+    let mut map = load_file();
+    if map.remove(&key).is_some() {
+        save_file(&map)?;
+    }
+    CACHE.lock().unwrap().remove(&key);
+    Ok(())
+}
+
+// 以下两个函数作用于当前账户
+pub fn get(key: &str) -> Option<String> {
+    get_for(&crate::accounts::active_account(), key)
+}
+
+pub fn set(key: &str, value: &str) -> Result<(), String> {
+    set_for(&crate::accounts::active_account(), key, value)
+}
+
+// account 为空时作用于当前账户
+#[command]
+pub fn set_credential(key: String, value: String, account: Option<String>) -> Result<(), String> {
+    check_key(&key)?;
+    let account = account.unwrap_or_else(crate::accounts::active_account);
+    set_for(&account, &key, &value)
+}
+
+#[command]
+pub fn clear_credential(key: String, account: Option<String>) -> Result<(), String> {
+    check_key(&key)?;
+    let account = account.unwrap_or_else(crate::accounts::active_account);
+    clear_for(&account, &key)
+}
+
+// 前端只能查询是否已保存，凭据的值只在 Rust 中读取
+#[command]
+pub fn has_credential(key: String, account: Option<String>) -> Result<bool, String> {
+    check_key(&key)?;
+    let account = account.unwrap_or_else(crate::accounts::active_account);
+    Ok(get_for(&account, &key).is_some())
+}
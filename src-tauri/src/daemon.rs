@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::control::{ControlContext, SupervisedTunnel};
+use crate::{accounts, EventSink, FrpcProcesses};
+
+// 无界面守护进程：不创建窗口和托盘，只运行隧道守护、配置与日志，
+// 通过本地控制套接字接收命令，适用于没有桌面环境的服务器
+
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(3);
+const MAX_BACKOFF_SECS: u64 = 300;
+// 运行超过该时长后退出视为正常运行过，重置失败计数
+const STABLE_SECS: i64 = 60;
+
+// 守护进程日志直接输出到标准输出，由 systemd 收集
+struct DaemonSink;
+
+impl EventSink for DaemonSink {
+    fn emit_log(&self, event: &str, message: String) {
+        let source = event.strip_prefix("frpc-log-").unwrap_or(event);
+        println!(
+            "[{}] [{}] {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            source,
+            message
+        );
+    }
+}
+
+fn log(message: String) {
+    DaemonSink.emit_log("daemon", message);
+}
+
+#[derive(Default)]
+struct RestartState {
+    failures: u32,
+    next_retry: Option<Instant>,
+}
+
+pub fn run() -> i32 {
+    if let Err(e) = crate::init_app_directory_headless() {
+        eprintln!("初始化程序目录失败: {}", e);
+        return 1;
+    }
+    crate::apply_config_env();
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("创建运行时失败: {}", e);
+            return 1;
+        }
+    };
+
+    match runtime.block_on(run_daemon()) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+async fn run_daemon() -> Result<(), String> {
+    // 登录失效需要重新授权时，授权地址输出到日志
+    crate::auth_refresh::set_sink(Arc::new(DaemonSink));
+    let ctx = ControlContext {
+        processes: FrpcProcesses::default(),
+        sink: Arc::new(DaemonSink),
+        supervised: Arc::new(Mutex::new(HashMap::new())),
+    };
+
+    // 启动当前账户的默认隧道
+    let profile = accounts::active_profile();
+    log(format!(
+        "守护进程已启动，账户: {}，默认隧道: {:?}",
+        profile.name, profile.default_tunnels
+    ));
+    ctx.supervised
+        .lock()
+        .unwrap()
+        .extend(profile.default_tunnels.iter().map(|id| {
+            let tunnel = SupervisedTunnel {
+                tunnel_id: id.clone(),
+                token: None,
+            };
+            (id.clone(), tunnel)
+        }));
+
+    tokio::spawn(async {
+        crate::endpoints::check_health().await;
+    });
+
+    crate::monitor::spawn(ctx.processes.clone(), ctx.sink.clone());
+    crate::preflight::spawn(ctx.processes.clone(), ctx.sink.clone());
+    crate::traffic::spawn(ctx.processes.clone(), ctx.sink.clone());
+
+    let server_ctx = ctx.clone();
+    tokio::spawn(async move {
+        if let Err(e) = crate::control::serve(server_ctx).await {
+            log(e);
+        }
+    });
+
+    let mut restart_states: HashMap<String, RestartState> = HashMap::new();
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    loop {
+        supervise(&ctx, &mut restart_states);
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = tokio::time::sleep(SUPERVISE_INTERVAL) => {}
+        }
+    }
+
+    log("收到退出信号，正在停止所有隧道".to_string());
+    if let Ok(mut map) = ctx.processes.0.lock() {
+        for (_, mut process_info) in map.drain() {
+            crate::kill_process(&mut process_info);
+        }
+        crate::instance_registry::sync(&map);
+    }
+    crate::monitor::flush();
+    #[cfg(unix)]
+    {
+        let _ = std::fs::remove_file(crate::control::socket_path());
+    }
+    Ok(())
+}
+
+// 检查需要保持运行的隧道，异常退出的按指数退避重新启动
+fn supervise(ctx: &ControlContext, restart_states: &mut HashMap<String, RestartState>) {
+    let supervised: Vec<(String, SupervisedTunnel)> = ctx
+        .supervised
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(id, tunnel)| (id.clone(), tunnel.clone()))
+        .collect();
+    restart_states.retain(|id, _| supervised.iter().any(|(s, _)| s == id));
+
+    for (id, tunnel) in supervised {
+        let state = restart_states.entry(id.clone()).or_default();
+
+        // 检查进程是否仍在运行
+        let mut running = false;
+        if let Ok(mut map) = ctx.processes.0.lock() {
+            if let Some(info) = map.get_mut(&id) {
+                match info.child.try_wait() {
+                    Ok(None) => running = true,
+                    _ => {
+                        let uptime = chrono::Local::now().timestamp() - info.started_at;
+                        if uptime >= STABLE_SECS {
+                            state.failures = 0;
+                        }
+                        map.remove(&id);
+                        crate::instance_registry::sync(&map);
+                        state.failures += 1;
+                        let backoff = backoff_secs(state.failures);
+                        state.next_retry = Some(Instant::now() + Duration::from_secs(backoff));
+                        log(format!("隧道 {} 已退出，{} 秒后重新启动", id, backoff));
+                    }
+                }
+            }
+        }
+        if running {
+            continue;
+        }
+
+        if state.next_retry.is_some_and(|t| Instant::now() < t) {
+            continue;
+        }
+
+        // 沿用 start 时传入的密钥，否则重启后会丢失
+        match crate::spawn_frpc(
+            &ctx.processes,
+            ctx.sink.clone(),
+            id.clone(),
+            tunnel.token,
+            tunnel.tunnel_id,
+        ) {
+            Ok(_) => log(format!("隧道 {} 已启动", id)),
+            Err(e) => {
+                state.failures += 1;
+                let backoff = backoff_secs(state.failures);
+                state.next_retry = Some(Instant::now() + Duration::from_secs(backoff));
+                log(format!("启动隧道 {} 失败: {}，{} 秒后重试", id, e, backoff));
+            }
+        }
+    }
+}
+
+fn backoff_secs(failures: u32) -> u64 {
+    (5u64 << failures.min(6)).min(MAX_BACKOFF_SECS)
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = term.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+// systemd 单元中的值：用双引号包裹，转义反斜杠和引号，% 说明符需要写两次
+fn systemd_quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%");
+    format!("\"{}\"", escaped)
+}
+
+// 系统服务的运行身份：通过 sudo 安装时为执行 sudo 的用户，否则为当前用户。
+// 返回用户名、用户组与该用户的程序数据目录
+#[cfg(unix)]
+fn service_account() -> Result<(String, String, std::path::PathBuf), String> {
+    use nix::unistd::{Group, Uid, User};
+
+    let user = match std::env::var("SUDO_USER").ok().filter(|u| !u.is_empty()) {
+        Some(name) => User::from_name(&name),
+        None => User::from_uid(Uid::current()),
+    }
+    .map_err(|e| format!("获取用户信息失败: {}", e))?
+    .ok_or("找不到运行服务的用户")?;
+    let group = Group::from_gid(user.gid)
+        .ok()
+        .flatten()
+        .map(|g| g.name)
+        .unwrap_or_else(|| user.gid.to_string());
+    // 服务中没有 XDG_DATA_HOME，程序使用 ~/.local/share 下的目录
+    let data_dir = user.dir.join(".local/share").join(crate::APP_IDENTIFIER);
+    Ok((user.name, group, data_dir))
+}
+
+#[cfg(not(unix))]
+fn service_account() -> Result<(String, String, std::path::PathBuf), String> {
+    Err("systemd 服务仅支持 Linux".to_string())
+}
+
+// 生成 systemd 服务单元，系统服务以安装者的身份运行，并限制只能写入程序数据目录
+pub fn systemd_unit(user: bool) -> Result<String, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("获取程序路径失败: {}", e))?;
+    let exec = systemd_quote(&exe.to_string_lossy()).replace('$', "$$");
+    let (wanted_by, service) = if user {
+        ("default.target", "NoNewPrivileges=true\n".to_string())
+    } else {
+        let (name, group, data_dir) = service_account()?;
+        (
+            "multi-user.target",
+            format!(
+                "User={}
+Group={}
+NoNewPrivileges=true
+ProtectSystem=strict
+PrivateTmp=true
+ReadWritePaths={}
+",
+                name,
+                group,
+                systemd_quote(&data_dir.to_string_lossy())
+            ),
+        )
+    };
+    Ok(format!(
+        "[Unit]
+Description=OpenFrp Cross Platform Launcher (daemon)
+After=network-online.target
+Wants=network-online.target
+
+[Service]
+Type=simple
+ExecStart={} --daemon
+Restart=on-failure
+RestartSec=5
+{}
+[Install]
+WantedBy={}
+",
+        exec, service, wanted_by
+    ))
+}
+
+// 写入 systemd 服务单元文件，user 为 true 时安装为用户服务
+pub fn install_systemd_unit(user: bool) -> Result<String, String> {
+    let dir = if user {
+        dirs::config_dir()
+            .ok_or("无法获取用户配置目录")?
+            .join("systemd/user")
+    } else {
+        std::path::PathBuf::from("/etc/systemd/system")
+    };
+    std::fs::create_dir_all(&dir).map_err(|e| format!("创建目录失败: {}", e))?;
+    let path = dir.join("openfrp-cpl.service");
+    std::fs::write(&path, systemd_unit(user)?)
+        .map_err(|e| format!("写入 {} 失败: {}", path.display(), e))?;
+
+    let systemctl = if user {
+        "systemctl --user"
+    } else {
+        "systemctl"
+    };
+    // 没有桌面环境的服务器上未登录过，需要先由运行服务的用户保存用户密钥
+    Ok(format!(
+        "已写入 {}\n执行以下命令启用服务:\n  {} daemon-reload\n  {} enable --now openfrp-cpl\n\
+         未在本机登录过时，先以运行服务的用户执行 {} credentials set token 保存用户密钥",
+        path.display(),
+        systemctl,
+        systemctl,
+        std::env::current_exe()
+            .map(|exe| exe.display().to_string())
+            .unwrap_or_else(|_| "openfrp_cpl".to_string())
+    ))
+}
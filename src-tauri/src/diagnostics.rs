@@ -0,0 +1,850 @@
+use reqwest::Url;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tauri::command;
+use tokio::net::TcpStream;
+
+use crate::node_recommend::node_port;
+use crate::openfrp_api::{Node, OpenFrpClient};
+use crate::ping::{self, PingOptions, PingTarget};
+use crate::{doh, endpoints, http, network_proxy};
+
+// 网络诊断：检查 DNS（系统解析与 DoH 对比）、TLS 握手、MTU 与 IPv6、代理、
+// 与服务器的时钟偏差以及节点连通性，生成可以直接发给客服的报告文件。
+// 报告中不包含用户密钥、Authorization 和代理密码
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(8);
+// 参与 TLS 检查的节点数，按延迟取最快的几个
+const MAX_TLS_NODES: usize = 5;
+// 时钟偏差阈值（秒）
+const CLOCK_SKEW_WARN: i64 = 30;
+const CLOCK_SKEW_FAIL: i64 = 300;
+// 低于该值的 MTU 可能导致大包被丢弃
+const MTU_WARN: u32 = 1400;
+// 阿里公共 DNS 的 IPv6 地址，用于检测 IPv6 连通性
+const IPV6_PROBE: &str = "[2400:3200::1]:443";
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+impl CheckStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "通过",
+            CheckStatus::Warn => "警告",
+            CheckStatus::Fail => "失败",
+            CheckStatus::Skip => "跳过",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CheckResult {
+    // dns、tls、network、proxy、clock、nodes、http
+    pub category: String,
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+    pub duration_ms: f64,
+    // 补充信息，写入报告的详情部分
+    pub details: Vec<String>,
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct Summary {
+    pub pass: usize,
+    pub warn: usize,
+    pub fail: usize,
+    pub skip: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiagnosticsReport {
+    pub generated_at: String,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub summary: Summary,
+    pub checks: Vec<CheckResult>,
+    // 保存的报告文件路径
+    pub file: Option<String>,
+}
+
+fn elapsed_ms(start: Instant) -> f64 {
+    (start.elapsed().as_secs_f64() * 10000.0).round() / 10.0
+}
+
+fn check(
+    category: &str,
+    name: String,
+    status: CheckStatus,
+    message: String,
+    start: Instant,
+    details: Vec<String>,
+) -> CheckResult {
+    CheckResult {
+        category: category.to_string(),
+        name,
+        status,
+        message,
+        duration_ms: elapsed_ms(start),
+        details,
+    }
+}
+
+fn join_ips(ips: &BTreeSet<IpAddr>) -> String {
+    if ips.is_empty() {
+        return "无".to_string();
+    }
+    ips.iter()
+        .map(IpAddr::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// 去掉地址中的认证信息
+fn redact(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.to_string().trim_end_matches('/').to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+// 启动器访问的服务主机名
+fn service_hosts() -> Vec<String> {
+    let endpoints = endpoints::current();
+    let mut hosts = Vec::new();
+    for url in endpoints
+        .api_hosts
+        .iter()
+        .chain(std::iter::once(&endpoints.access_host))
+    {
+        if let Some(host) = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+        {
+            if !hosts.contains(&host) {
+                hosts.push(host);
+            }
+        }
+    }
+    hosts
+}
+
+async fn system_lookup(host: &str) -> Result<BTreeSet<IpAddr>, String> {
+    let addrs = tokio::time::timeout(CHECK_TIMEOUT, tokio::net::lookup_host((host, 443)))
+        .await
+        .map_err(|_| "解析超时".to_string())?
+        .map_err(|e| e.to_string())?;
+    Ok(addrs.map(|a| a.ip()).collect())
+}
+
+// 依次尝试各 DoH 服务器
+async fn doh_lookup_any(host: &str) -> Result<(String, BTreeSet<IpAddr>), String> {
+    let mut errors = Vec::new();
+    for server in doh::servers(&doh::current()) {
+        match doh::query_server(&server, host).await {
+            Ok((ips, _)) => return Ok((server, ips.into_iter().collect())),
+            Err(e) => errors.push(format!("{}: {}", server, e)),
+        }
+    }
+    Err(errors.join("；"))
+}
+
+async fn check_dns(host: &str) -> CheckResult {
+    let start = Instant::now();
+    let name = format!("解析 {}", host);
+    let system = system_lookup(host).await;
+    let doh = doh_lookup_any(host).await;
+    let mut details = vec![
+        format!(
+            "系统 DNS: {}",
+            match &system {
+                Ok(ips) => join_ips(ips),
+                Err(e) => format!("失败（{}）", e),
+            }
+        ),
+        match &doh {
+            Ok((server, ips)) => format!("DoH ({}): {}", server, join_ips(ips)),
+            Err(e) => format!("DoH: 失败（{}）", e),
+        },
+    ];
+    let (status, message) = match (&system, &doh) {
+        (Ok(system), Ok((_, doh))) if system.is_empty() => {
+            if doh.is_empty() {
+                (
+                    CheckStatus::Fail,
+                    "系统 DNS 与 DoH 均未返回地址".to_string(),
+                )
+            } else {
+                (
+                    CheckStatus::Fail,
+                    "系统 DNS 未返回地址，DoH 可以解析，建议开启 DoH".to_string(),
+                )
+            }
+        }
+        (Ok(system), Ok((_, doh))) => {
+            if doh.is_empty() || !system.is_disjoint(doh) {
+                (CheckStatus::Pass, format!("解析到 {} 个地址", system.len()))
+            } else {
+                // CDN 可能按地区返回不同地址，不一致不一定是污染
+                details.push("两者结果没有交集".to_string());
+                (
+                    CheckStatus::Warn,
+                    "系统 DNS 与 DoH 结果不一致，可能存在 DNS 污染或 CDN 分区解析".to_string(),
+                )
+            }
+        }
+        (Ok(system), Err(_)) if system.is_empty() => (
+            CheckStatus::Fail,
+            "系统 DNS 未返回地址，DoH 不可用".to_string(),
+        ),
+        (Ok(_), Err(_)) => (
+            CheckStatus::Pass,
+            "系统 DNS 可以解析，DoH 不可用，无法对比".to_string(),
+        ),
+        (Err(_), Ok(_)) => (
+            CheckStatus::Fail,
+            "系统 DNS 解析失败，DoH 可以解析，建议开启 DoH".to_string(),
+        ),
+        (Err(_), Err(_)) => (
+            CheckStatus::Fail,
+            "系统 DNS 与 DoH 均解析失败，请检查网络连接".to_string(),
+        ),
+    };
+    check("dns", name, status, message, start, details)
+}
+
+// 建立 TCP 连接并完成 TLS 握手，返回 (连接耗时, 握手耗时)
+async fn tls_handshake(host: &str, port: u16, verify: bool) -> Result<(f64, f64), String> {
+    let start = Instant::now();
+    let stream = tokio::time::timeout(CHECK_TIMEOUT, TcpStream::connect((host, port)))
+        .await
+        .map_err(|_| "TCP 连接超时".to_string())?
+        .map_err(|e| format!("TCP 连接失败: {}", e))?;
+    let connect_ms = elapsed_ms(start);
+
+    let connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(!verify)
+        .danger_accept_invalid_hostnames(!verify)
+        .build()
+        .map_err(|e| format!("创建 TLS 连接器失败: {}", e))?;
+    let connector = tokio_native_tls::TlsConnector::from(connector);
+    let tls_start = Instant::now();
+    tokio::time::timeout(CHECK_TIMEOUT, connector.connect(host, stream))
+        .await
+        .map_err(|_| "TLS 握手超时".to_string())?
+        .map_err(|e| format!("TLS 握手失败: {}", e))?;
+    Ok((connect_ms, elapsed_ms(tls_start)))
+}
+
+async fn check_tls_service(host: &str) -> CheckResult {
+    let start = Instant::now();
+    let name = format!("TLS {}:443", host);
+    match tls_handshake(host, 443, true).await {
+        Ok((connect_ms, tls_ms)) => check(
+            "tls",
+            name,
+            CheckStatus::Pass,
+            format!(
+                "握手成功，证书有效（TCP {:.0}ms，TLS {:.0}ms）",
+                connect_ms, tls_ms
+            ),
+            start,
+            Vec::new(),
+        ),
+        Err(e) => {
+            // 证书校验失败通常是时间错误或存在中间人（如抓包软件、公司网关）
+            let hint = if e.contains("certificate") || e.contains("证书") {
+                vec!["证书校验失败，请检查系统时间，或是否有抓包软件、网关替换了证书".to_string()]
+            } else {
+                Vec::new()
+            };
+            check("tls", name, CheckStatus::Fail, e, start, hint)
+        }
+    }
+}
+
+// frps 在节点端口上接受 TLS 连接，启用强制 TLS 时需要握手成功
+async fn check_tls_node(node: &Node, port: u16) -> CheckResult {
+    let start = Instant::now();
+    let name = format!("节点 #{} {} TLS", node.id, node.name);
+    match tls_handshake(&node.hostname, port, false).await {
+        Ok((_, tls_ms)) => check(
+            "tls",
+            name,
+            CheckStatus::Pass,
+            format!("握手成功（{:.0}ms）", tls_ms),
+            start,
+            Vec::new(),
+        ),
+        Err(e) => check(
+            "tls",
+            name,
+            CheckStatus::Warn,
+            format!("{}，启用强制 TLS 时可能无法连接该节点", e),
+            start,
+            Vec::new(),
+        ),
+    }
+}
+
+// 默认路由所在网卡的名称与 MTU
+#[cfg(target_os = "linux")]
+fn default_route_mtu() -> Option<(String, u32)> {
+    let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+    let iface = routes.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        (fields.len() > 1 && fields[1] == "00000000").then(|| fields[0].to_string())
+    })?;
+    let mtu = std::fs::read_to_string(format!("/sys/class/net/{}/mtu", iface))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some((iface, mtu))
+}
+
+// 通过 route 找到默认路由的网卡，再从 ifconfig 的首行读取 mtu
+#[cfg(target_os = "macos")]
+fn default_route_mtu() -> Option<(String, u32)> {
+    let run = |program: &str, args: &[&str]| {
+        std::process::Command::new(program)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    let route = run("route", &["-n", "get", "default"])?;
+    let iface = route
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("interface:"))?
+        .trim()
+        .to_string();
+    // en0: flags=8863<UP,BROADCAST,RUNNING,SIMPLEX,MULTICAST> mtu 1500
+    let ifconfig = run("ifconfig", &[iface.as_str()])?;
+    let mut words = ifconfig.lines().next()?.split_whitespace();
+    words.find(|word| *word == "mtu")?;
+    let mtu = words.next()?.parse().ok()?;
+    Some((iface, mtu))
+}
+
+// 查询到公网地址的出口网卡，只查路由表不发送数据
+#[cfg(target_os = "windows")]
+fn default_route_mtu() -> Option<(String, u32)> {
+    use windows_sys::Win32::Foundation::NO_ERROR;
+    use windows_sys::Win32::NetworkManagement::IpHelper::{
+        GetBestInterface, GetIfEntry, MIB_IFROW,
+    };
+
+    // 地址按网络字节序传入
+    let probe = u32::from_ne_bytes([223, 5, 5, 5]);
+    let mut index = 0u32;
+    if unsafe { GetBestInterface(probe, &mut index) } != NO_ERROR {
+        return None;
+    }
+    let mut row: MIB_IFROW = unsafe { std::mem::zeroed() };
+    row.dwIndex = index;
+    if unsafe { GetIfEntry(&mut row) } != NO_ERROR {
+        return None;
+    }
+    let len = (row.dwDescrLen as usize).min(row.bDescr.len());
+    let name = String::from_utf8_lossy(&row.bDescr[..len])
+        .trim_end_matches('\0')
+        .to_string();
+    Some((name, row.dwMtu))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn default_route_mtu() -> Option<(String, u32)> {
+    None
+}
+
+fn check_mtu() -> CheckResult {
+    let start = Instant::now();
+    match default_route_mtu() {
+        Some((iface, mtu)) if mtu < MTU_WARN => check(
+            "network",
+            "MTU".to_string(),
+            CheckStatus::Warn,
+            format!(
+                "默认网卡 {} 的 MTU 为 {}，较小的 MTU 可能导致大包丢失或连接卡顿",
+                iface, mtu
+            ),
+            start,
+            Vec::new(),
+        ),
+        Some((iface, mtu)) => check(
+            "network",
+            "MTU".to_string(),
+            CheckStatus::Pass,
+            format!("默认网卡 {} 的 MTU 为 {}", iface, mtu),
+            start,
+            Vec::new(),
+        ),
+        None => check(
+            "network",
+            "MTU".to_string(),
+            CheckStatus::Skip,
+            "无法读取默认网卡的 MTU".to_string(),
+            start,
+            Vec::new(),
+        ),
+    }
+}
+
+async fn check_ipv6() -> CheckResult {
+    let start = Instant::now();
+    let probe: SocketAddr = IPV6_PROBE.parse().expect("IPv6 探测地址无效");
+    // UDP connect 只查询路由，不发送数据
+    let local = UdpSocket::bind("[::]:0")
+        .and_then(|socket| socket.connect(probe).and_then(|_| socket.local_addr()));
+    let local = match local {
+        Ok(addr) => addr.ip(),
+        Err(e) => {
+            return check(
+                "network",
+                "IPv6".to_string(),
+                CheckStatus::Skip,
+                format!("没有 IPv6 路由（{}），仅使用 IPv4", e),
+                start,
+                Vec::new(),
+            )
+        }
+    };
+    let details = vec![format!("本机 IPv6 出口地址: {}", local)];
+    match tokio::time::timeout(CHECK_TIMEOUT, TcpStream::connect(probe)).await {
+        Ok(Ok(_)) => check(
+            "network",
+            "IPv6".to_string(),
+            CheckStatus::Pass,
+            "IPv6 连接正常".to_string(),
+            start,
+            details,
+        ),
+        Ok(Err(e)) => check(
+            "network",
+            "IPv6".to_string(),
+            CheckStatus::Warn,
+            format!("有 IPv6 地址但无法连接外网（{}），可能导致部分连接变慢", e),
+            start,
+            details,
+        ),
+        Err(_) => check(
+            "network",
+            "IPv6".to_string(),
+            CheckStatus::Warn,
+            "有 IPv6 地址但连接超时，可能导致部分连接变慢".to_string(),
+            start,
+            details,
+        ),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn system_proxy() -> Option<String> {
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+    let key = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey(r"Software\Microsoft\Windows\CurrentVersion\Internet Settings")
+        .ok()?;
+    let enabled: u32 = key.get_value("ProxyEnable").ok()?;
+    if enabled == 0 {
+        return None;
+    }
+    key.get_value::<String, _>("ProxyServer").ok()
+}
+
+#[cfg(target_os = "macos")]
+fn system_proxy() -> Option<String> {
+    let output = std::process::Command::new("scutil")
+        .arg("--proxy")
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let value = |key: &str| {
+        stdout.lines().find_map(|line| {
+            let (k, v) = line.split_once(':')?;
+            (k.trim() == key).then(|| v.trim().to_string())
+        })
+    };
+    for (enable, host, port) in [
+        ("HTTPSEnable", "HTTPSProxy", "HTTPSPort"),
+        ("HTTPEnable", "HTTPProxy", "HTTPPort"),
+        ("SOCKSEnable", "SOCKSProxy", "SOCKSPort"),
+    ] {
+        if value(enable).as_deref() == Some("1") {
+            return Some(format!(
+                "{}:{}",
+                value(host).unwrap_or_default(),
+                value(port).unwrap_or_default()
+            ));
+        }
+    }
+    None
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn system_proxy() -> Option<String> {
+    None
+}
+
+fn check_proxy() -> CheckResult {
+    let start = Instant::now();
+    let mut details = Vec::new();
+    for key in [
+        "HTTP_PROXY",
+        "HTTPS_PROXY",
+        "ALL_PROXY",
+        "http_proxy",
+        "https_proxy",
+        "all_proxy",
+        "NO_PROXY",
+    ] {
+        if let Ok(value) = std::env::var(key) {
+            if !value.is_empty() {
+                details.push(format!("环境变量 {}={}", key, redact(&value)));
+            }
+        }
+    }
+    if let Some(proxy) = system_proxy() {
+        details.push(format!("系统代理: {}", proxy));
+    }
+    let bypass = std::env::var("BYPASS_PROXY").unwrap_or_default() == "true";
+    if bypass {
+        details.push("已开启绕过系统代理".to_string());
+    }
+
+    let (status, message) = match network_proxy::current() {
+        Some(settings) => {
+            let url = redact(&network_proxy::proxy_url(&settings, None).unwrap_or_default());
+            (CheckStatus::Pass, format!("使用手动指定的代理 {}", url))
+        }
+        None if bypass => (CheckStatus::Pass, "不使用代理".to_string()),
+        None if details.is_empty() => (CheckStatus::Pass, "未检测到代理".to_string()),
+        // 系统代理会被启动器和 frpc 使用，代理异常时所有连接都会失败
+        None => (
+            CheckStatus::Warn,
+            "检测到系统代理，启动器与 frpc 的连接会经过代理，连接失败时可尝试绕过系统代理"
+                .to_string(),
+        ),
+    };
+    check("proxy", "代理".to_string(), status, message, start, details)
+}
+
+async fn check_clock() -> CheckResult {
+    let start = Instant::now();
+    let name = "时钟偏差".to_string();
+    let client = match http::client() {
+        Ok(client) => client,
+        Err(e) => return check("clock", name, CheckStatus::Skip, e, start, Vec::new()),
+    };
+    let sent = chrono::Utc::now();
+    let response = match client
+        .head(endpoints::api_base())
+        .timeout(CHECK_TIMEOUT)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            return check(
+                "clock",
+                name,
+                CheckStatus::Skip,
+                format!("无法访问 API 服务器: {}", e),
+                start,
+                Vec::new(),
+            )
+        }
+    };
+    let received = chrono::Utc::now();
+    let server = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok());
+    let Some(server) = server else {
+        return check(
+            "clock",
+            name,
+            CheckStatus::Skip,
+            "服务器响应中没有 Date 头".to_string(),
+            start,
+            Vec::new(),
+        );
+    };
+    // 以请求往返的中点作为服务器生成响应的本地时间
+    let local = sent + (received - sent) / 2;
+    let skew = (local.timestamp_millis() - server.timestamp_millis()) / 1000;
+    let details = vec![
+        format!("本机时间: {}", local.to_rfc3339()),
+        format!("服务器时间: {}", server.to_rfc3339()),
+    ];
+    let (status, message) = match skew.abs() {
+        s if s >= CLOCK_SKEW_FAIL => (
+            CheckStatus::Fail,
+            format!(
+                "本机时间与服务器相差 {} 秒，会导致 TLS 证书校验和登录失败，请同步系统时间",
+                skew
+            ),
+        ),
+        s if s >= CLOCK_SKEW_WARN => (
+            CheckStatus::Warn,
+            format!("本机时间与服务器相差 {} 秒，建议同步系统时间", skew),
+        ),
+        _ => (CheckStatus::Pass, format!("偏差 {} 秒", skew)),
+    };
+    check("clock", name, status, message, start, details)
+}
+
+// 测试全部可用节点的 TCP 连通性，返回汇总结果和可连接的节点（按延迟排序）
+async fn check_nodes() -> (CheckResult, Vec<(Node, u16, f64)>) {
+    let start = Instant::now();
+    let name = "节点连通性".to_string();
+    let nodes = match OpenFrpClient::new() {
+        Ok(client) => client.node_list().await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let nodes: Vec<(Node, u16)> = match nodes {
+        Ok(fetched) => fetched
+            .data
+            .list
+            .into_iter()
+            .filter(|n| n.status == 200)
+            .filter_map(|n| node_port(&n).map(|port| (n, port)))
+            .collect(),
+        Err(e) => {
+            let result = check(
+                "nodes",
+                name,
+                CheckStatus::Skip,
+                format!("获取节点列表失败: {}", e),
+                start,
+                Vec::new(),
+            );
+            return (result, Vec::new());
+        }
+    };
+    if nodes.is_empty() {
+        let result = check(
+            "nodes",
+            name,
+            CheckStatus::Skip,
+            "没有可以测试的节点，请先登录".to_string(),
+            start,
+            Vec::new(),
+        );
+        return (result, Vec::new());
+    }
+
+    let targets = nodes
+        .iter()
+        .map(|(node, port)| PingTarget {
+            host: node.hostname.clone(),
+            port: *port,
+        })
+        .collect();
+    let options = PingOptions {
+        count: 2,
+        ..Default::default()
+    };
+    let reports = ping::ping_many(targets, options).await;
+
+    let mut details = Vec::new();
+    let mut reachable = Vec::new();
+    for ((node, port), report) in nodes.into_iter().zip(reports) {
+        match report.latency_ms {
+            Some(latency) if report.success => {
+                details.push(format!(
+                    "#{} {} ({}:{}): {:.0}ms",
+                    node.id, node.name, node.hostname, port, latency
+                ));
+                reachable.push((node, port, latency));
+            }
+            _ => details.push(format!(
+                "#{} {} ({}:{}): 不可达，{}",
+                node.id, node.name, node.hostname, port, report.message
+            )),
+        }
+    }
+    let total = details.len();
+    let status = match reachable.len() {
+        0 => CheckStatus::Fail,
+        n if n < total => CheckStatus::Warn,
+        _ => CheckStatus::Pass,
+    };
+    let message = format!("{}/{} 个节点可以连接", reachable.len(), total);
+    reachable.sort_by(|a, b| a.2.total_cmp(&b.2));
+    (
+        check("nodes", name, status, message, start, details),
+        reachable,
+    )
+}
+
+// 依次访问常用网站，检查 HTTP 出口是否正常
+async fn check_http() -> Vec<CheckResult> {
+    let client = match http::client() {
+        Ok(client) => client,
+        Err(e) => {
+            return vec![check(
+                "http",
+                "HTTP 连通性".to_string(),
+                CheckStatus::Skip,
+                e,
+                Instant::now(),
+                Vec::new(),
+            )]
+        }
+    };
+    crate::run_network_tests(&client)
+        .await
+        .into_iter()
+        .map(|test| {
+            let name = test["name"].as_str().unwrap_or_default();
+            let success = test["success"].as_bool().unwrap_or(false);
+            let message = if success {
+                format!("状态码 {}，耗时 {}ms", test["status"], test["latency_ms"])
+            } else {
+                test["error"].as_str().unwrap_or_default().to_string()
+            };
+            CheckResult {
+                category: "http".to_string(),
+                name: format!("访问 {}", name),
+                status: if success {
+                    CheckStatus::Pass
+                } else {
+                    CheckStatus::Fail
+                },
+                message,
+                duration_ms: test["latency_ms"].as_f64().unwrap_or_default(),
+                details: vec![redact(test["url"].as_str().unwrap_or_default())],
+            }
+        })
+        .collect()
+}
+
+pub async fn run() -> DiagnosticsReport {
+    let hosts = service_hosts();
+    let mut checks = Vec::new();
+
+    let dns = hosts.iter().map(|host| {
+        let host = host.clone();
+        tokio::spawn(async move { check_dns(&host).await })
+    });
+    let dns: Vec<_> = dns.collect();
+    let tls: Vec<_> = hosts
+        .iter()
+        .map(|host| {
+            let host = host.clone();
+            tokio::spawn(async move { check_tls_service(&host).await })
+        })
+        .collect();
+    for handle in dns.into_iter().chain(tls) {
+        if let Ok(result) = handle.await {
+            checks.push(result);
+        }
+    }
+
+    let (nodes, reachable) = check_nodes().await;
+    for (node, port, _) in reachable.iter().take(MAX_TLS_NODES) {
+        checks.push(check_tls_node(node, *port).await);
+    }
+    checks.push(check_mtu());
+    checks.push(check_ipv6().await);
+    checks.push(check_proxy());
+    checks.push(check_clock().await);
+    checks.push(nodes);
+    checks.extend(check_http().await);
+
+    let mut summary = Summary::default();
+    for c in &checks {
+        match c.status {
+            CheckStatus::Pass => summary.pass += 1,
+            CheckStatus::Warn => summary.warn += 1,
+            CheckStatus::Fail => summary.fail += 1,
+            CheckStatus::Skip => summary.skip += 1,
+        }
+    }
+    DiagnosticsReport {
+        generated_at: chrono::Local::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        summary,
+        checks,
+        file: None,
+    }
+}
+
+// 生成纯文本报告，便于粘贴到工单或聊天中
+pub fn render(report: &DiagnosticsReport) -> String {
+    let mut out = String::new();
+    out.push_str("OpenFrp 启动器网络诊断报告\n");
+    out.push_str(&format!("生成时间: {}\n", report.generated_at));
+    out.push_str(&format!(
+        "启动器版本: {}  系统: {} {}\n",
+        report.app_version, report.os, report.arch
+    ));
+    out.push_str(&format!(
+        "结果: 通过 {}，警告 {}，失败 {}，跳过 {}\n",
+        report.summary.pass, report.summary.warn, report.summary.fail, report.summary.skip
+    ));
+    let mut category = "";
+    for c in &report.checks {
+        if c.category != category {
+            category = &c.category;
+            out.push_str(&format!("\n== {} ==\n", category.to_uppercase()));
+        }
+        out.push_str(&format!(
+            "[{}] {}: {}（{:.0}ms）\n",
+            c.status.label(),
+            c.name,
+            c.message,
+            c.duration_ms
+        ));
+        for detail in &c.details {
+            out.push_str(&format!("    {}\n", detail));
+        }
+    }
+    out
+}
+
+fn report_dir() -> PathBuf {
+    crate::get_app_dir().join("diagnostics")
+}
+
+// 保存报告到程序目录，返回文件路径
+pub fn save(report: &DiagnosticsReport) -> Result<PathBuf, String> {
+    let dir = report_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("创建诊断目录失败: {}", e))?;
+    let path = dir.join(format!(
+        "diagnostics-{}.txt",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    std::fs::write(&path, render(report)).map_err(|e| format!("保存诊断报告失败: {}", e))?;
+    Ok(path)
+}
+
+// 运行全部检查并保存报告文件
+#[command]
+pub async fn run_diagnostics() -> Result<DiagnosticsReport, String> {
+    let mut report = run().await;
+    let path = save(&report)?;
+    report.file = Some(path.to_string_lossy().to_string());
+    Ok(report)
+}
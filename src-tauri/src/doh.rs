@@ -0,0 +1,541 @@
+use base64::Engine;
+use hyper::client::connect::dns::Name;
+use once_cell::sync::Lazy;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::command;
+
+use crate::{accounts, http};
+
+// DNS over HTTPS 解析：与 frpc 使用同一个 DoH 开关（账户启动参数或 USE_DOH/DOH_ADDR），
+// 开启后共享 HTTP 客户端通过这里解析域名。查询使用 RFC 8484 的 DNS 报文格式，
+// 按 TTL 缓存结果；DoH 服务器依次尝试，失败的服务器暂时排到最后，
+// 全部失败时回退到系统 DNS
+
+// 默认 DoH 服务器，使用 IP 地址避免解析 DoH 服务器自身时依赖系统 DNS
+pub const DEFAULT_SERVERS: &[&str] = &[
+    "https://223.5.5.5/dns-query",
+    "https://1.12.12.12/dns-query",
+    "https://1.1.1.1/dns-query",
+];
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+// 缓存时间按记录的 TTL，限制在该范围内
+const MIN_TTL: u32 = 30;
+const MAX_TTL: u32 = 3600;
+// 查询失败的服务器在这段时间内排到最后
+const FAILURE_COOLDOWN: Duration = Duration::from_secs(60);
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+
+pub struct DohConfig {
+    pub enabled: bool,
+    // 自定义 DoH 地址，如 223.5.5.5 或 https://dns.example.com/dns-query
+    pub addr: Option<String>,
+}
+
+// 当前生效的 DoH 设置，账户启动参数优先于全局环境变量，设置了地址即视为开启。
+// 每次 HTTP 请求都会用到，账户配置通过只读且带缓存的 read_config 获取
+pub fn current() -> DohConfig {
+    let options = accounts::active_profile().launch_options;
+    let addr = options
+        .doh_addr
+        .or_else(|| std::env::var("DOH_ADDR").ok())
+        .map(|a| a.trim().to_string())
+        .filter(|a| !a.is_empty());
+    let use_doh = options.use_doh.unwrap_or_else(|| {
+        std::env::var("USE_DOH")
+            .map(|v| v == "true")
+            .unwrap_or(false)
+    });
+    DohConfig {
+        enabled: use_doh || addr.is_some(),
+        addr,
+    }
+}
+
+// 将 frpc 形式的地址（IP 或主机名）转换为 DoH 请求地址
+pub fn server_url(addr: &str) -> String {
+    let addr = addr.trim().trim_end_matches('/');
+    if addr.starts_with("https://") || addr.starts_with("http://") {
+        addr.to_string()
+    } else {
+        format!("https://{}/dns-query", addr)
+    }
+}
+
+// 按优先级排列的 DoH 服务器，自定义地址在前
+pub fn servers(config: &DohConfig) -> Vec<String> {
+    let mut servers: Vec<String> = config.addr.iter().map(|a| server_url(a)).collect();
+    for server in DEFAULT_SERVERS {
+        if !servers.iter().any(|s| s == server) {
+            servers.push(server.to_string());
+        }
+    }
+    servers
+}
+
+struct CacheEntry {
+    ips: Vec<IpAddr>,
+    expires: Instant,
+}
+
+static CACHE: Lazy<Mutex<HashMap<String, CacheEntry>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// 服务器地址到可以重新优先使用的时间
+static FAILED: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn clear_cache() {
+    CACHE.lock().unwrap().clear();
+    FAILED.lock().unwrap().clear();
+}
+
+// 构造查询报文：ID 为 0 便于 HTTP 缓存，设置递归查询标志
+fn encode_query(host: &str, record_type: u16) -> Result<Vec<u8>, String> {
+    let mut message = vec![0, 0, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in host.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("域名格式无效: {}", host));
+        }
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+    message.push(0);
+    message.extend_from_slice(&record_type.to_be_bytes());
+    message.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(message)
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Result<u16, String> {
+    buf.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| "DNS 响应不完整".to_string())
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Result<u32, String> {
+    buf.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| "DNS 响应不完整".to_string())
+}
+
+// 跳过报文中的域名，返回其后的位置，支持压缩指针
+fn skip_name(buf: &[u8], mut pos: usize) -> Result<usize, String> {
+    loop {
+        let len = *buf.get(pos).ok_or("DNS 响应不完整")? as usize;
+        match len {
+            0 => return Ok(pos + 1),
+            l if l & 0xC0 == 0xC0 => return Ok(pos + 2),
+            l => pos += 1 + l,
+        }
+    }
+}
+
+// 解析响应中 record_type 类型的地址及最小 TTL，域名不存在时返回空列表
+fn decode_response(buf: &[u8], record_type: u16) -> Result<(Vec<IpAddr>, u32), String> {
+    let flags = read_u16(buf, 2)?;
+    if flags & 0x8000 == 0 {
+        return Err("不是 DNS 响应报文".to_string());
+    }
+    match flags & 0x000F {
+        0 => {}
+        // NXDOMAIN
+        3 => return Ok((Vec::new(), MIN_TTL)),
+        rcode => return Err(format!("DNS 服务器返回错误码 {}", rcode)),
+    }
+    let questions = read_u16(buf, 4)?;
+    let answers = read_u16(buf, 6)?;
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(buf, pos)? + 4;
+    }
+
+    let mut ips = Vec::new();
+    let mut ttl = MAX_TTL;
+    for _ in 0..answers {
+        pos = skip_name(buf, pos)?;
+        let rtype = read_u16(buf, pos)?;
+        let class = read_u16(buf, pos + 2)?;
+        let record_ttl = read_u32(buf, pos + 4)?;
+        let len = read_u16(buf, pos + 8)? as usize;
+        pos += 10;
+        let data = buf.get(pos..pos + len).ok_or("DNS 响应不完整")?;
+        pos += len;
+        // CNAME 等其他记录由服务器递归解析，只取地址记录
+        if rtype != record_type || class != CLASS_IN {
+            continue;
+        }
+        let ip = match (rtype, len) {
+            (TYPE_A, 4) => IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3])),
+            (TYPE_AAAA, 16) => {
+                let octets: [u8; 16] = data.try_into().map_err(|_| "AAAA 记录无效")?;
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => continue,
+        };
+        ips.push(ip);
+        ttl = ttl.min(record_ttl);
+    }
+    Ok((ips, ttl.clamp(MIN_TTL, MAX_TTL)))
+}
+
+// 向单个 DoH 服务器查询一种记录
+async fn query(server: &str, host: &str, record_type: u16) -> Result<(Vec<IpAddr>, u32), String> {
+    let message = encode_query(host, record_type)?;
+    let encoded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(message);
+    let response = http::doh_client()?
+        .get(server)
+        .query(&[("dns", encoded)])
+        .header("accept", "application/dns-message")
+        .timeout(QUERY_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("请求 {} 失败: {}", server, e))?;
+    if !response.status().is_success() {
+        return Err(format!("{} 返回状态码 {}", server, response.status()));
+    }
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| format!("读取 {} 的响应失败: {}", server, e))?;
+    decode_response(&body, record_type)
+}
+
+// 向单个服务器同时查询 A 与 AAAA 记录，IPv4 地址排在前面
+pub async fn query_server(server: &str, host: &str) -> Result<(Vec<IpAddr>, u32), String> {
+    let (v4, v6) = tokio::join!(query(server, host, TYPE_A), query(server, host, TYPE_AAAA));
+    match (v4, v6) {
+        (Err(e), Err(_)) => Err(e),
+        (v4, v6) => {
+            let (mut ips, mut ttl) = (Vec::new(), MAX_TTL);
+            for (found, record_ttl) in [v4, v6].into_iter().flatten() {
+                if !found.is_empty() {
+                    ttl = ttl.min(record_ttl);
+                }
+                ips.extend(found);
+            }
+            Ok((ips, ttl))
+        }
+    }
+}
+
+// 供 HTTP 客户端使用的解析器，DoH 全部失败或查不到记录时回退到系统 DNS
+#[derive(Clone)]
+pub struct DohResolver {
+    servers: Vec<String>,
+}
+
+impl DohResolver {
+    pub fn new(servers: Vec<String>) -> Self {
+        DohResolver { servers }
+    }
+
+    // 最近失败过的服务器排到最后，其余保持配置顺序
+    fn ordered_servers(&self) -> Vec<String> {
+        let now = Instant::now();
+        let failed = FAILED.lock().unwrap();
+        let (mut healthy, cooling): (Vec<String>, Vec<String>) = self
+            .servers
+            .iter()
+            .cloned()
+            .partition(|s| failed.get(s).filter(|until| **until > now).is_none());
+        healthy.extend(cooling);
+        healthy
+    }
+
+    pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        let host = host.trim_end_matches('.').to_lowercase();
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![ip]);
+        }
+        // 单标签主机名（如 localhost 或局域网主机）只能由系统解析
+        if !host.contains('.') {
+            return system_lookup(&host).await;
+        }
+        if let Some(entry) = CACHE.lock().unwrap().get(&host) {
+            if entry.expires > Instant::now() {
+                return Ok(entry.ips.clone());
+            }
+        }
+
+        let mut errors = Vec::new();
+        for server in self.ordered_servers() {
+            match query_server(&server, &host).await {
+                Ok((ips, ttl)) if !ips.is_empty() => {
+                    FAILED.lock().unwrap().remove(&server);
+                    CACHE.lock().unwrap().insert(
+                        host.clone(),
+                        CacheEntry {
+                            ips: ips.clone(),
+                            expires: Instant::now() + Duration::from_secs(u64::from(ttl)),
+                        },
+                    );
+                    return Ok(ips);
+                }
+                // 公共 DNS 查不到的域名可能是内网域名，不再询问其他 DoH 服务器
+                Ok(_) => {
+                    errors.push(format!("{} 未返回 {} 的地址", server, host));
+                    break;
+                }
+                Err(e) => {
+                    println!("DoH 查询失败: {}", e);
+                    FAILED
+                        .lock()
+                        .unwrap()
+                        .insert(server.clone(), Instant::now() + FAILURE_COOLDOWN);
+                    errors.push(e);
+                }
+            }
+        }
+        system_lookup(&host)
+            .await
+            .map_err(|e| format!("{}（DoH: {}）", e, errors.join("；")))
+    }
+}
+
+async fn system_lookup(host: &str) -> Result<Vec<IpAddr>, String> {
+    tokio::net::lookup_host((host, 0))
+        .await
+        .map(|addrs| addrs.map(|a| a.ip()).collect())
+        .map_err(|e| format!("解析 {} 失败: {}", host, e))
+}
+
+impl Resolve for DohResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let ips = resolver.lookup(&host).await?;
+            // 端口由连接器按请求地址替换
+            let addrs: Addrs = Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+// 测试 DoH 服务器，server 为空时依次测试当前配置的全部服务器
+#[command]
+pub async fn test_doh(
+    server: Option<String>,
+    host: Option<String>,
+) -> Result<Vec<serde_json::Value>, String> {
+    let host = host.unwrap_or_else(|| "api.openfrp.net".to_string());
+    let servers = match server.filter(|s| !s.trim().is_empty()) {
+        Some(server) => vec![server_url(&server)],
+        None => servers(&current()),
+    };
+    let mut results = Vec::new();
+    for server in servers {
+        let start = Instant::now();
+        let result = query_server(&server, &host).await;
+        let latency_ms = start.elapsed().as_millis() as u64;
+        results.push(match result {
+            Ok((ips, ttl)) => serde_json::json!({
+                "server": server,
+                "success": !ips.is_empty(),
+                "addresses": ips,
+                "ttl": ttl,
+                "latency_ms": latency_ms,
+            }),
+            Err(e) => serde_json::json!({
+                "server": server,
+                "success": false,
+                "error": e,
+                "latency_ms": latency_ms,
+            }),
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // 构造响应报文：复制查询的问题部分，answers 为 (类型, TTL, RDATA)，名称使用指向问题的压缩指针
+    fn response(query: &[u8], rcode: u8, answers: &[(u16, u32, Vec<u8>)]) -> Vec<u8> {
+        let mut buf = vec![query[0], query[1], 0x81, 0x80 | rcode, 0, 1];
+        buf.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&[0, 0, 0, 0]);
+        buf.extend_from_slice(&query[12..]);
+        for (rtype, ttl, data) in answers {
+            buf.extend_from_slice(&[0xC0, 0x0C]);
+            buf.extend_from_slice(&rtype.to_be_bytes());
+            buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+            buf.extend_from_slice(&ttl.to_be_bytes());
+            buf.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            buf.extend_from_slice(data);
+        }
+        buf
+    }
+
+    // CNAME 的 RDATA：一个普通标签加指向问题名称的压缩指针
+    fn cname_data() -> Vec<u8> {
+        let mut data = vec![3];
+        data.extend_from_slice(b"cdn");
+        data.extend_from_slice(&[0xC0, 0x0C]);
+        data
+    }
+
+    #[test]
+    fn encode_query_layout() {
+        let query = encode_query("api.openfrp.net.", TYPE_AAAA).unwrap();
+        assert_eq!(&query[..12], &[0, 0, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            &query[12..],
+            b"\x03api\x07openfrp\x03net\x00\x00\x1c\x00\x01"
+        );
+        assert!(encode_query("a..b", TYPE_A).is_err());
+        assert!(encode_query(&"a".repeat(64), TYPE_A).is_err());
+    }
+
+    #[test]
+    fn decode_answer_with_compression_pointer() {
+        let query = encode_query("example.com", TYPE_A).unwrap();
+        let buf = response(&query, 0, &[(TYPE_A, 120, vec![93, 184, 216, 34])]);
+        let (ips, ttl) = decode_response(&buf, TYPE_A).unwrap();
+        assert_eq!(ips, vec![IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34))]);
+        assert_eq!(ttl, 120);
+    }
+
+    #[test]
+    fn decode_skips_cname_before_address() {
+        let query = encode_query("www.example.com", TYPE_A).unwrap();
+        let buf = response(
+            &query,
+            0,
+            &[
+                (5, 10, cname_data()),
+                (TYPE_A, 7200, vec![10, 0, 0, 1]),
+                (TYPE_A, 600, vec![10, 0, 0, 2]),
+            ],
+        );
+        let (ips, ttl) = decode_response(&buf, TYPE_A).unwrap();
+        assert_eq!(
+            ips,
+            vec![
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            ]
+        );
+        // CNAME 的 TTL 不参与计算
+        assert_eq!(ttl, 600);
+    }
+
+    #[test]
+    fn decode_aaaa_and_ttl_clamp() {
+        let query = encode_query("example.com", TYPE_AAAA).unwrap();
+        let addr: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let buf = response(&query, 0, &[(TYPE_AAAA, 1, addr.octets().to_vec())]);
+        let (ips, ttl) = decode_response(&buf, TYPE_AAAA).unwrap();
+        assert_eq!(ips, vec![IpAddr::V6(addr)]);
+        assert_eq!(ttl, MIN_TTL);
+        // 查询 AAAA 时忽略 A 记录
+        let buf = response(&query, 0, &[(TYPE_A, 60, vec![10, 0, 0, 1])]);
+        assert_eq!(decode_response(&buf, TYPE_AAAA).unwrap(), (vec![], MAX_TTL));
+    }
+
+    #[test]
+    fn decode_nxdomain_and_server_error() {
+        let query = encode_query("missing.example", TYPE_A).unwrap();
+        let buf = response(&query, 3, &[]);
+        assert_eq!(decode_response(&buf, TYPE_A).unwrap(), (vec![], MIN_TTL));
+        let buf = response(&query, 2, &[]);
+        assert!(decode_response(&buf, TYPE_A)
+            .unwrap_err()
+            .contains("错误码 2"));
+    }
+
+    #[test]
+    fn decode_rejects_truncated_rdata() {
+        let query = encode_query("example.com", TYPE_A).unwrap();
+        let mut buf = response(&query, 0, &[(TYPE_A, 60, vec![10, 0, 0, 1])]);
+        buf.truncate(buf.len() - 2);
+        assert!(decode_response(&buf, TYPE_A).is_err());
+        // 报文头不完整
+        assert!(decode_response(&[0, 0, 0x81], TYPE_A).is_err());
+    }
+
+    #[test]
+    fn decode_rejects_query_message() {
+        let query = encode_query("example.com", TYPE_A).unwrap();
+        assert!(decode_response(&query, TYPE_A)
+            .unwrap_err()
+            .contains("不是 DNS 响应"));
+    }
+
+    // 本地 DoH 服务：resolver.test 返回 10.1.2.3，其余域名返回 NXDOMAIN
+    fn serve_doh(hits: Arc<AtomicUsize>) -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/dns-query", server.server_addr());
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                hits.fetch_add(1, Ordering::SeqCst);
+                let encoded = request
+                    .url()
+                    .split_once("dns=")
+                    .map(|(_, v)| v.to_string())
+                    .unwrap_or_default();
+                let query = base64::engine::general_purpose::URL_SAFE_NO_PAD
+                    .decode(encoded)
+                    .unwrap();
+                let qtype = u16::from_be_bytes([query[query.len() - 4], query[query.len() - 3]]);
+                let found = query.windows(8).any(|w| w == b"resolver");
+                let body = match (found, qtype) {
+                    (true, TYPE_A) => response(&query, 0, &[(TYPE_A, 300, vec![10, 1, 2, 3])]),
+                    (true, _) => response(&query, 0, &[]),
+                    (false, _) => response(&query, 3, &[]),
+                };
+                let header =
+                    tiny_http::Header::from_bytes("Content-Type", "application/dns-message")
+                        .unwrap();
+                let _ = request.respond(tiny_http::Response::from_data(body).with_header(header));
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn resolver_cache_cooldown_and_fallback() {
+        std::env::set_var("BYPASS_PROXY", "true");
+        let hits = Arc::new(AtomicUsize::new(0));
+        let good = serve_doh(hits.clone());
+        // 拒绝连接的服务器
+        let bad = "http://127.0.0.1:1/dns-query".to_string();
+        let resolver = DohResolver::new(vec![bad.clone(), good.clone()]);
+
+        let ips = resolver.lookup("resolver.test").await.unwrap();
+        assert_eq!(ips, vec![IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))]);
+        // A 与 AAAA 各查询一次
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        // 失败的服务器在冷却期内排到最后
+        assert_eq!(resolver.ordered_servers(), vec![good.clone(), bad.clone()]);
+
+        // 第二次命中缓存，不再请求服务器
+        let ips = resolver.lookup("RESOLVER.test.").await.unwrap();
+        assert_eq!(ips, vec![IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))]);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        // IP 地址不经过 DoH
+        assert_eq!(
+            resolver.lookup("127.0.0.1").await.unwrap(),
+            vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]
+        );
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        // DoH 查不到时回退到系统 DNS，系统解析失败的错误中附带 DoH 的结果
+        let error = resolver.lookup("nothing.invalid").await.unwrap_err();
+        assert!(error.contains("DoH"), "{}", error);
+        assert!(error.contains("未返回 nothing.invalid"), "{}", error);
+        // 单标签主机名直接由系统解析
+        let before = hits.load(Ordering::SeqCst);
+        assert!(resolver.lookup("localhost").await.is_ok());
+        assert_eq!(hits.load(Ordering::SeqCst), before);
+    }
+}
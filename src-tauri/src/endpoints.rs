@@ -0,0 +1,213 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::command;
+
+use crate::{load_config, read_config, save_config};
+
+// 集中管理启动器访问的服务地址，按环境配置保存在 config.json 中。
+// API 地址可以配置多个，请求失败时自动切换到下一个可用地址。
+
+const PRODUCTION_API_HOSTS: [&str; 3] = [
+    "https://of-dev-api.bfsea.com",
+    "https://of-dev-api.bfsea.xyz",
+    "https://api.openfrp.net",
+];
+const PRODUCTION_ACCESS_HOST: &str = "https://access.openfrp.net";
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvironmentProfile {
+    #[default]
+    Production,
+    Staging,
+    Custom,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct Endpoints {
+    // API 地址（含协议，不含路径），按优先级排列
+    pub api_hosts: Vec<String>,
+    // Argo 授权登录服务地址
+    pub access_host: String,
+}
+
+impl Endpoints {
+    fn production() -> Self {
+        Endpoints {
+            api_hosts: PRODUCTION_API_HOSTS.iter().map(|h| h.to_string()).collect(),
+            access_host: PRODUCTION_ACCESS_HOST.to_string(),
+        }
+    }
+
+    fn normalized(mut self) -> Self {
+        self.api_hosts = self
+            .api_hosts
+            .iter()
+            .map(|h| h.trim().trim_end_matches('/').to_string())
+            .filter(|h| !h.is_empty())
+            .collect();
+        self.access_host = self.access_host.trim().trim_end_matches('/').to_string();
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct EndpointSettings {
+    #[serde(default)]
+    pub profile: EnvironmentProfile,
+    // 测试环境与自定义环境的地址，未设置的部分沿用正式环境
+    pub staging: Option<Endpoints>,
+    pub custom: Option<Endpoints>,
+}
+
+// 当前优先使用的 API 地址，由健康检查和请求失败时的切换更新
+static PREFERRED_HOST: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+// 每次请求都会用到，只读取不写回；配置文件未变化时 read_config 直接返回缓存
+fn settings() -> EndpointSettings {
+    read_config()
+        .ok()
+        .and_then(|cfg| cfg.endpoints)
+        .unwrap_or_default()
+}
+
+// 当前环境的服务地址
+pub fn current() -> Endpoints {
+    let settings = settings();
+    let configured = match settings.profile {
+        EnvironmentProfile::Production => None,
+        EnvironmentProfile::Staging => settings.staging,
+        EnvironmentProfile::Custom => settings.custom,
+    };
+    let production = Endpoints::production();
+    let mut endpoints = configured.map(Endpoints::normalized).unwrap_or_default();
+    if endpoints.api_hosts.is_empty() {
+        endpoints.api_hosts = production.api_hosts;
+    }
+    if endpoints.access_host.is_empty() {
+        endpoints.access_host = production.access_host;
+    }
+    endpoints
+}
+
+// 按尝试顺序排列的 API 地址，优先使用上次可用的地址
+pub fn api_hosts() -> Vec<String> {
+    let mut hosts = current().api_hosts;
+    if let Some(preferred) = PREFERRED_HOST.lock().unwrap().as_ref() {
+        if let Some(index) = hosts.iter().position(|h| h == preferred) {
+            hosts.rotate_left(index);
+        }
+    }
+    hosts
+}
+
+// 当前优先使用的 API 地址
+pub fn api_base() -> String {
+    api_hosts().into_iter().next().unwrap_or_default()
+}
+
+pub fn access_host() -> String {
+    current().access_host
+}
+
+// 请求成功后记住该地址
+pub fn mark_healthy(host: &str) {
+    *PREFERRED_HOST.lock().unwrap() = Some(host.to_string());
+}
+
+// 请求因网络原因失败时切换到下一个地址
+pub fn mark_failed(host: &str) {
+    let hosts = api_hosts();
+    if hosts.first().map(String::as_str) != Some(host) {
+        return;
+    }
+    if let Some(next) = hosts.get(1) {
+        println!("API 地址 {} 不可用，切换到 {}", host, next);
+        *PREFERRED_HOST.lock().unwrap() = Some(next.clone());
+    }
+}
+
+// 检查当前环境下所有 API 地址的可用性，并选择第一个可用的地址
+pub async fn check_health() -> Vec<serde_json::Value> {
+    let client = match crate::http::client() {
+        Ok(client) => client,
+        Err(_) => return Vec::new(),
+    };
+
+    // 并发检查各个地址，结果按配置顺序排列
+    let handles: Vec<_> = current()
+        .api_hosts
+        .into_iter()
+        .map(|host| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                let start = Instant::now();
+                let result = client
+                    .get(format!("{}/commonQuery/get?key=software", host))
+                    .timeout(HEALTH_CHECK_TIMEOUT)
+                    .send()
+                    .await;
+                let latency_ms = start.elapsed().as_millis();
+                match result {
+                    Ok(response) => serde_json::json!({
+                        "host": host,
+                        "healthy": response.status().is_success(),
+                        "status": response.status().as_u16(),
+                        "latency_ms": latency_ms,
+                    }),
+                    Err(e) => serde_json::json!({
+                        "host": host,
+                        "healthy": false,
+                        "error": e.to_string(),
+                        "latency_ms": latency_ms,
+                    }),
+                }
+            })
+        })
+        .collect();
+    let mut results = Vec::new();
+    for handle in handles {
+        if let Ok(result) = handle.await {
+            results.push(result);
+        }
+    }
+
+    if let Some(healthy) = results
+        .iter()
+        .find(|r| r["healthy"].as_bool().unwrap_or(false))
+    {
+        mark_healthy(healthy["host"].as_str().unwrap_or_default());
+    }
+    results
+}
+
+#[command]
+pub fn get_endpoint_settings() -> Result<serde_json::Value, String> {
+    Ok(serde_json::json!({
+        "settings": settings(),
+        "current": current(),
+        "active_api_host": api_base(),
+    }))
+}
+
+#[command]
+pub fn set_endpoint_settings(settings: EndpointSettings) -> Result<(), String> {
+    let mut cfg = load_config()?;
+    cfg.endpoints = Some(settings);
+    save_config(&cfg)?;
+    // 切换环境后重新从第一个地址开始
+    *PREFERRED_HOST.lock().unwrap() = None;
+    Ok(())
+}
+
+#[command]
+pub async fn check_api_health() -> Result<serde_json::Value, String> {
+    let results = check_health().await;
+    Ok(serde_json::json!({
+        "results": results,
+        "active_api_host": api_base(),
+    }))
+}
@@ -0,0 +1,233 @@
+use once_cell::sync::Lazy;
+use rand_core::{OsRng, RngCore};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{doh, rate_limit};
+
+// 共享的 HTTP 客户端：复用连接池，统一 User-Agent、超时与代理设置
+
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// 下载 frpc 等大文件时单次请求的超时
+pub const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BASE_MS: u64 = 300;
+
+// 影响客户端构建方式的设置，变化时重新创建客户端
+#[derive(Clone, PartialEq)]
+struct ClientSettings {
+    bypass_proxy: bool,
+    // 手动指定的代理地址（含认证信息）
+    proxy_url: Option<String>,
+    // 开启 DoH 时按顺序使用的 DoH 服务器
+    doh_servers: Option<Vec<String>>,
+}
+
+impl ClientSettings {
+    fn current() -> Self {
+        let doh = doh::current();
+        ClientSettings {
+            doh_servers: doh.enabled.then(|| doh::servers(&doh)),
+            ..Self::without_doh()
+        }
+    }
+
+    // 只包含代理设置，DoH 查询自身不需要读取 DoH 设置
+    fn without_doh() -> Self {
+        ClientSettings {
+            bypass_proxy: std::env::var("BYPASS_PROXY").unwrap_or_default() == "true",
+            proxy_url: crate::network_proxy::current_url(),
+            doh_servers: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    // 服务器返回 429 或本地限流，retry_after 后可以重试
+    RateLimited { retry_after: Duration },
+    Request(reqwest::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::RateLimited { retry_after } => write!(
+                f,
+                "请求过于频繁，请 {} 秒后重试",
+                retry_after.as_secs().max(1)
+            ),
+            Error::Request(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    // 能否换用同一服务的其他地址重新发送。非幂等请求出错时可能已送达服务器，
+    // 只有连接阶段失败（请求尚未发出）时才能重发，避免重复新建或删除隧道
+    pub fn can_fail_over(&self, method: &Method) -> bool {
+        match self {
+            Error::RateLimited { .. } => false,
+            Error::Request(e) => is_idempotent(method) || e.is_connect(),
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Error::Request(error)
+    }
+}
+
+static CLIENT: Lazy<Mutex<Option<(ClientSettings, Client)>>> = Lazy::new(|| Mutex::new(None));
+// 发送 DoH 查询的客户端，使用系统 DNS，避免解析 DoH 服务器时递归
+static DOH_CLIENT: Lazy<Mutex<Option<(ClientSettings, Client)>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn user_agent() -> String {
+    format!(
+        "OpenFrp-CPL/{}-{}",
+        std::env::consts::OS,
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+fn build(settings: &ClientSettings) -> Result<Client, String> {
+    let mut builder = Client::builder()
+        .user_agent(user_agent())
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .pool_idle_timeout(Duration::from_secs(90));
+    if let Some(url) = &settings.proxy_url {
+        // 手动指定的代理优先于系统代理
+        let proxy = reqwest::Proxy::all(url).map_err(|e| format!("代理地址无效: {}", e))?;
+        builder = builder.proxy(proxy);
+    } else if settings.bypass_proxy {
+        // 绕过系统代理
+        builder = builder.no_proxy();
+    }
+    if let Some(servers) = &settings.doh_servers {
+        builder = builder.dns_resolver(Arc::new(doh::DohResolver::new(servers.clone())));
+    }
+    builder
+        .build()
+        .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))
+}
+
+fn cached_client(
+    cache: &Mutex<Option<(ClientSettings, Client)>>,
+    settings: ClientSettings,
+) -> Result<Client, String> {
+    let mut cached = cache.lock().unwrap();
+    if let Some((cached_settings, client)) = cached.as_ref() {
+        if *cached_settings == settings {
+            return Ok(client.clone());
+        }
+    }
+    let client = build(&settings)?;
+    *cached = Some((settings, client.clone()));
+    Ok(client)
+}
+
+// 获取共享客户端，代理或 DoH 设置变化后自动重建
+pub fn client() -> Result<Client, String> {
+    let settings = ClientSettings::current();
+    let doh_changed = CLIENT
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|(cached, _)| cached.doh_servers != settings.doh_servers);
+    if doh_changed {
+        // DoH 服务器变化后之前的解析结果不再可信
+        doh::clear_cache();
+    }
+    cached_client(&CLIENT, settings)
+}
+
+// 发送 DoH 查询的客户端，与共享客户端使用相同的代理设置
+pub fn doh_client() -> Result<Client, String> {
+    cached_client(&DOH_CLIENT, ClientSettings::without_doh())
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    [
+        Method::GET,
+        Method::HEAD,
+        Method::PUT,
+        Method::DELETE,
+        Method::OPTIONS,
+    ]
+    .contains(method)
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+// 指数退避，并加入随机抖动避免多个请求同时重试
+fn backoff(attempt: u32) -> Duration {
+    let base = RETRY_BASE_MS << (attempt - 1);
+    Duration::from_millis(base + OsRng.next_u64() % base)
+}
+
+// 发送请求，幂等请求在连接失败、超时、网关错误或短时间限流时重试。
+// 请求按主机限流，服务器返回 429 且无法在短时间内重试时返回 Error::RateLimited
+pub async fn send(builder: RequestBuilder) -> Result<Response, Error> {
+    let (client, request) = builder.build_split();
+    let request = request?;
+    let host = rate_limit::host_key(request.url());
+    let idempotent = is_idempotent(request.method());
+
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        rate_limit::acquire(&host)
+            .await
+            .map_err(|retry_after| Error::RateLimited { retry_after })?;
+        // 非幂等请求只发送一次；请求体为流时无法复制，也只发送一次
+        let current = match request.try_clone() {
+            Some(current) if idempotent && attempt < MAX_ATTEMPTS => current,
+            _ => return check_rate_limited(&host, client.execute(request).await?),
+        };
+        match client.execute(current).await {
+            Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                let retry_after = rate_limit::retry_after(response.headers());
+                rate_limit::record_rate_limited(&host, retry_after);
+                if retry_after > rate_limit::MAX_WAIT {
+                    return Err(Error::RateLimited { retry_after });
+                }
+                // 下一次 acquire 会等待到 Retry-After 结束
+                continue;
+            }
+            Ok(response) if is_retryable_status(response.status()) => {
+                println!(
+                    "请求 {} 返回 {}，准备重试",
+                    request.url(),
+                    response.status()
+                );
+            }
+            Err(e) if e.is_connect() || e.is_timeout() => {
+                println!("请求 {} 失败: {}，准备重试", request.url(), e);
+            }
+            result => return Ok(result?),
+        }
+        tokio::time::sleep(backoff(attempt)).await;
+    }
+}
+
+// 最后一次请求仍返回 429 时记录限流并转换为错误
+fn check_rate_limited(host: &str, response: Response) -> Result<Response, Error> {
+    if response.status() != StatusCode::TOO_MANY_REQUESTS {
+        return Ok(response);
+    }
+    let retry_after = rate_limit::retry_after(response.headers());
+    rate_limit::record_rate_limited(host, retry_after);
+    Err(Error::RateLimited { retry_after })
+}
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::ProcessInfo;
+
+// 跨进程共享的运行中隧道记录，界面、命令行等多个进程各自维护自己启动的条目，
+// 供命令行查询状态和结束其他进程启动的隧道
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InstanceRecord {
+    pub id: String,
+    pub tunnel_id: String,
+    pub pid: u32,
+    pub account: String,
+    // 持有该 frpc 进程的启动器进程
+    pub owner_pid: u32,
+    pub started_at: i64,
+    // 登记时 frpc 进程的启动时间标识，结束进程前用于确认 pid 未被其他进程复用
+    #[serde(default)]
+    pub process_start: Option<String>,
+}
+
+fn registry_path() -> PathBuf {
+    crate::get_app_dir().join("instances.json")
+}
+
+fn read_all() -> Vec<InstanceRecord> {
+    fs::read_to_string(registry_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// 用当前进程的隧道列表更新记录，同时清理已退出的进程。
+// 读取、合并和写入在文件锁内完成，避免多个进程同时更新时互相覆盖
+pub fn sync(map: &HashMap<String, ProcessInfo>) {
+    let path = registry_path();
+    let result = crate::with_file_lock(&path, || {
+        let own_pid = std::process::id();
+        let mut records: Vec<InstanceRecord> = read_all()
+            .into_iter()
+            .filter(|r| r.owner_pid != own_pid && is_running(r))
+            .collect();
+
+        for (id, info) in map.iter() {
+            let pid = info.child.id();
+            records.push(InstanceRecord {
+                id: id.clone(),
+                tunnel_id: info.tunnel_id.clone(),
+                pid,
+                account: info.account.clone(),
+                owner_pid: own_pid,
+                started_at: info.started_at,
+                process_start: process_start_time(pid),
+            });
+        }
+
+        let content = serde_json::to_string_pretty(&records)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        crate::write_atomic(&path, content.as_bytes())
+    });
+    if let Err(e) = result.and_then(|r| r) {
+        println!("写入隧道记录失败: {}", e);
+    }
+}
+
+// 获取所有仍在运行的隧道
+pub fn list() -> Vec<InstanceRecord> {
+    read_all().into_iter().filter(is_running).collect()
+}
+
+// 进程仍存在，且启动时间与登记时一致（旧记录没有启动时间时只检查进程是否存在）
+fn is_running(record: &InstanceRecord) -> bool {
+    is_alive(record.pid)
+        && match &record.process_start {
+            Some(start) => process_start_time(record.pid).as_ref() == Some(start),
+            None => true,
+        }
+}
+
+pub fn is_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::kill;
+        use nix::unistd::Pid;
+        kill(Pid::from_raw(pid as i32), None).is_ok()
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+            .creation_flags(crate::CREATE_NO_WINDOW)
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).contains(&pid.to_string()))
+            .unwrap_or(false)
+    }
+}
+
+// 进程启动时间标识：Linux 为 /proc/<pid>/stat 的第 22 个字段，
+// Windows 为进程创建时间，macOS 为 ps 输出的启动时间
+pub fn process_start_time(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        // 进程名可能包含空格和括号，从最后一个右括号之后开始数，其后第 1 个字段为第 3 个字段
+        let rest = &stat[stat.rfind(')')? + 1..];
+        rest.split_whitespace().nth(19).map(String::from)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("ps")
+            .args(["-o", "lstart=", "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        let start = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !start.is_empty()).then_some(start)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::{CloseHandle, FILETIME};
+        use windows_sys::Win32::System::Threading::{
+            GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+        };
+        let empty = || FILETIME {
+            dwLowDateTime: 0,
+            dwHighDateTime: 0,
+        };
+        let (mut created, mut exited, mut kernel, mut user) = (empty(), empty(), empty(), empty());
+        unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if handle.is_null() {
+                return None;
+            }
+            let ok = GetProcessTimes(handle, &mut created, &mut exited, &mut kernel, &mut user);
+            CloseHandle(handle);
+            if ok == 0 {
+                return None;
+            }
+        }
+        Some(
+            ((u64::from(created.dwHighDateTime) << 32) | u64::from(created.dwLowDateTime))
+                .to_string(),
+        )
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+// 结束其他进程启动的 frpc，先确认 pid 仍属于登记时的进程，无法确认时拒绝结束
+pub fn kill(record: &InstanceRecord) -> Result<(), String> {
+    let recorded = record
+        .process_start
+        .as_ref()
+        .ok_or("该隧道记录缺少进程启动时间，无法确认进程，请通过启动它的界面或守护进程停止")?;
+    match process_start_time(record.pid) {
+        Some(current) if current == *recorded => {}
+        Some(_) => {
+            return Err(format!(
+                "进程 {} 已不是该隧道的 frpc（pid 已被复用），请通过启动它的界面或守护进程停止",
+                record.pid
+            ))
+        }
+        None => {
+            return Err(format!(
+                "无法读取进程 {} 的信息，frpc 可能已退出",
+                record.pid
+            ))
+        }
+    }
+    kill_pid(record.pid)
+}
+
+fn kill_pid(pid: u32) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+        kill(Pid::from_raw(pid as i32), Signal::SIGTERM).map_err(|e| format!("终止进程失败: {}", e))
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        std::process::Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .creation_flags(crate::CREATE_NO_WINDOW)
+            .output()
+            .map(|_| ())
+            .map_err(|e| format!("终止进程失败: {}", e))
+    }
+}
@@ -2,7 +2,6 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use flate2::read::GzDecoder;
-use reqwest;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::collections::HashMap;
@@ -14,14 +13,18 @@ use std::io::Write;
 // use std::io::{BufRead, BufReader};
 //use std::path::Path;
 // use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use tar::Archive;
 use tauri::menu::{Menu, MenuItem};
 use tauri::tray::{TrayIcon, TrayIconBuilder};
 use tauri::Manager;
+mod accounts;
+mod api_cache;
 mod argo_access;
+mod auth_refresh;
 use tauri::{command, Emitter, Runtime, State};
 use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
 
@@ -32,6 +35,29 @@ use crate::update::UpdateInfo;
 use tauri::Listener;
 use tauri_plugin_updater;
 mod api_proxy;
+mod cli;
+mod control;
+mod credentials;
+mod daemon;
+mod diagnostics;
+mod doh;
+mod endpoints;
+mod http;
+mod instance_registry;
+mod monitor;
+mod network_proxy;
+mod node_recommend;
+mod openfrp_api;
+mod ping;
+mod port_analysis;
+mod ports;
+mod preflight;
+mod rate_limit;
+#[cfg(feature = "selftest")]
+mod selftest;
+mod traffic;
+mod tunnel_spec;
+mod tunnels;
 mod update;
 use std::net::TcpListener;
 use std::thread;
@@ -44,12 +70,19 @@ use std::os::windows::process::CommandExt;
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
-// 定义进程组结构
-#[derive(Default)]
-struct FrpcProcesses(Mutex<HashMap<String, ProcessInfo>>);
+// 应用标识，与 tauri.conf.json 中的 identifier 保持一致
+const APP_IDENTIFIER: &str = "com.of-cpl.app";
+
+// 定义进程组结构，可在界面、命令行等运行模式间共享
+#[derive(Default, Clone)]
+struct FrpcProcesses(Arc<Mutex<HashMap<String, ProcessInfo>>>);
 
 struct ProcessInfo {
     child: Child,
+    tunnel_id: String,
+    // 启动该隧道的账户
+    account: String,
+    started_at: i64,
     #[cfg(target_os = "windows")]
     group_id: u32,
 }
@@ -77,10 +110,50 @@ struct LogPayload {
     message: String,
 }
 
+// 日志输出目标：界面模式下发送给前端，命令行模式下打印到终端
+trait EventSink: Send + Sync {
+    fn emit_log(&self, event: &str, message: String);
+
+    // 发送结构化事件，默认以日志形式输出
+    fn emit_event(&self, event: &str, payload: serde_json::Value) {
+        self.emit_log(event, payload.to_string());
+    }
+
+    // 发送桌面通知，默认以日志形式输出
+    fn notify(&self, title: &str, body: &str) {
+        self.emit_log("notification", format!("{}: {}", title, body));
+    }
+}
+
+impl<R: Runtime> EventSink for tauri::AppHandle<R> {
+    fn emit_log(&self, event: &str, message: String) {
+        let _ = self.emit(event, LogPayload { message });
+    }
+
+    fn emit_event(&self, event: &str, payload: serde_json::Value) {
+        let _ = self.emit(event, payload);
+    }
+
+    fn notify(&self, title: &str, body: &str) {
+        use tauri_plugin_notification::NotificationExt;
+        if let Err(e) = self.notification().builder().title(title).body(body).show() {
+            println!("发送通知失败: {}", e);
+        }
+    }
+}
+
+struct ConsoleSink;
+
+impl EventSink for ConsoleSink {
+    fn emit_log(&self, _event: &str, message: String) {
+        println!("{}", message);
+    }
+}
+
 // 配置文件版本号，用于管理配置文件升级
 const CONFIG_VERSION: u32 = 2;
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 struct Config {
     // 环境变量持久化
     env_vars: Option<HashMap<String, String>>,
@@ -88,6 +161,19 @@ struct Config {
     frpc_version: Option<String>,
     frpc_filename: Option<String>,
     cpl_version: Option<String>,
+    // 多账户配置
+    accounts: Option<Vec<accounts::AccountProfile>>,
+    active_account: Option<String>,
+    // 服务地址环境配置
+    endpoints: Option<endpoints::EndpointSettings>,
+    // 手动指定的网络代理
+    network_proxy: Option<network_proxy::ProxySettings>,
+    // 隧道可用性监控
+    monitor: Option<monitor::MonitorSettings>,
+    // 启动前的本地服务检查
+    preflight: Option<preflight::PreflightSettings>,
+    // 流量统计与额度提醒
+    traffic: Option<traffic::TrafficSettings>,
 }
 
 impl Config {
@@ -96,6 +182,21 @@ impl Config {
         if self.env_vars.is_none() {
             self.env_vars = Some(HashMap::new());
         }
+        if self.accounts.is_none() {
+            self.accounts = Some(Vec::new());
+        }
+        if self.endpoints.is_none() {
+            self.endpoints = Some(endpoints::EndpointSettings::default());
+        }
+        if self.monitor.is_none() {
+            self.monitor = Some(monitor::MonitorSettings::default());
+        }
+        if self.preflight.is_none() {
+            self.preflight = Some(preflight::PreflightSettings::default());
+        }
+        if self.traffic.is_none() {
+            self.traffic = Some(traffic::TrafficSettings::default());
+        }
         let current_version = self.config_version.unwrap_or(0);
 
         if current_version < 1 {
@@ -142,14 +243,31 @@ fn init_app_directory(app: &tauri::App) -> Result<PathBuf, Box<dyn std::error::E
         .app_local_data_dir()
         .map_err(|e| Box::<dyn std::error::Error>::from(e.to_string()))?;
 
+    prepare_app_directory(app_local_data_dir)
+}
+
+// 无界面运行时没有 tauri::App，按 Tauri 的规则推导出相同的目录
+fn init_app_directory_headless() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let app_local_data_dir = dirs::data_local_dir()
+        .ok_or("无法获取本地数据目录")?
+        .join(APP_IDENTIFIER);
+
+    prepare_app_directory(app_local_data_dir)
+}
+
+fn prepare_app_directory(
+    app_local_data_dir: PathBuf,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
     // 确保目录存在
     fs::create_dir_all(&app_local_data_dir)?;
 
     // 创建子目录
     let frpc_dir = app_local_data_dir.join("frpc");
     let config_dir = app_local_data_dir.join("config");
+    let logs_dir = app_local_data_dir.join("logs");
     fs::create_dir_all(&frpc_dir)?;
     fs::create_dir_all(&config_dir)?;
+    fs::create_dir_all(&logs_dir)?;
 
     // 保存目录路径
     *APP_DIR.lock().unwrap() = Some(app_local_data_dir.clone());
@@ -166,15 +284,48 @@ fn get_app_dir() -> PathBuf {
         .expect("应用程序目录未初始化")
 }
 
+// 加载持久化环境变量并注入当前进程
+fn apply_config_env() {
+    if let Ok(cfg) = load_config() {
+        if let Some(map) = cfg.env_vars {
+            for (k, v) in map {
+                if !v.is_empty() {
+                    std::env::set_var(k, v);
+                }
+            }
+        }
+    }
+}
+
+// frpc 输出日志文件，供命令行查看
+fn frpc_log_path(id: &str) -> PathBuf {
+    get_app_dir().join("logs").join(format!("frpc-{}.log", id))
+}
+
 // 获取配置文件路径
 fn get_config_path() -> Result<PathBuf, String> {
     let app_dir = get_app_dir();
     Ok(app_dir.join("config.json"))
 }
 
-// 加载配置
-fn load_config() -> Result<Config, String> {
+// 最近一次读取的配置，以文件路径、修改时间和大小判断是否仍然有效，
+// 其他进程（界面、守护进程、命令行）修改配置后会重新读取
+static CONFIG_CACHE: Mutex<Option<(PathBuf, std::time::SystemTime, u64, Config)>> =
+    Mutex::new(None);
+
+// 读取配置，只在内存中补全和升级，不写回文件。查询设置时使用，避免并发写入覆盖其他修改
+fn read_config() -> Result<Config, String> {
     let config_path = get_config_path()?;
+    let stamp = fs::metadata(&config_path)
+        .ok()
+        .and_then(|m| Some((m.modified().ok()?, m.len())));
+    if let (Some((modified, len)), Some((path, cached_modified, cached_len, config))) =
+        (stamp, CONFIG_CACHE.lock().unwrap().as_ref())
+    {
+        if *path == config_path && *cached_modified == modified && *cached_len == len {
+            return Ok(config.clone());
+        }
+    }
     let current_version = env!("CARGO_PKG_VERSION").to_string();
 
     let mut config = if config_path.exists() {
@@ -197,12 +348,57 @@ fn load_config() -> Result<Config, String> {
     // 强制更新版本号为当前编译版本
     config.cpl_version = Some(current_version);
 
+    if let Some((modified, len)) = stamp {
+        *CONFIG_CACHE.lock().unwrap() = Some((config_path, modified, len, config.clone()));
+    }
+    Ok(config)
+}
+
+// 加载配置并保存升级后的结构，用于启动时和修改配置前
+fn load_config() -> Result<Config, String> {
+    let config = read_config()?;
+
     // 保存可能升级后的配置
     save_config(&config)?;
 
     Ok(config)
 }
 
+// 先写入同目录下的临时文件再重命名，读取方不会读到写了一半的文件
+fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        name,
+        std::process::id(),
+        SEQ.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&temp, content)?;
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+// 多个进程（界面、守护进程、命令行）共享的文件在锁内读取、修改并写回，
+// 锁文件为同目录下的 .{name}.lock，进程退出时由系统释放
+fn with_file_lock<T>(path: &Path, f: impl FnOnce() -> T) -> std::io::Result<T> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let lock = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(path.with_file_name(format!(".{}.lock", name)))?;
+    lock.lock()?;
+    Ok(f())
+}
+
 // 保存配置
 fn save_config(config: &Config) -> Result<(), String> {
     let config_path = get_config_path()?;
@@ -215,22 +411,22 @@ fn save_config(config: &Config) -> Result<(), String> {
     let content =
         serde_json::to_string_pretty(config).map_err(|e| format!("序列化配置失败: {}", e))?;
 
-    fs::write(&config_path, content).map_err(|e| format!("保存配置文件失败: {}", e))?;
+    write_atomic(&config_path, content.as_bytes())
+        .map_err(|e| format!("保存配置文件失败: {}", e))?;
 
     Ok(())
 }
 
 #[command]
 async fn download_frpc<R: Runtime>(app: tauri::AppHandle<R>) -> Result<String, String> {
+    install_frpc(&app).await
+}
+
+// 下载并安装最新版本的 frpc
+async fn install_frpc(sink: &dyn EventSink) -> Result<String, String> {
     let os = std::env::consts::OS;
 
-    let os_name = os;
-    let version = env!("CARGO_PKG_VERSION");
-    let user_agent = format!("OpenFrp-CPL/{}-{}", os_name, version);
-    let client = reqwest::Client::builder()
-        .user_agent(&user_agent)
-        .build()
-        .map_err(|e| e.to_string())?;
+    let client = http::client()?;
 
     let os_name = match os {
         "windows" => "windows",
@@ -254,19 +450,14 @@ async fn download_frpc<R: Runtime>(app: tauri::AppHandle<R>) -> Result<String, S
         format!("frpc_{}_{}", os_name, arch_name)
     };
 
-    app.emit(
-        "log",
-        LogPayload {
-            message: "开始获取最新版本信息...".into(),
-        },
-    )
-    .map_err(|e| e.to_string())?;
+    sink.emit_log("log", "开始获取最新版本信息...".into());
 
-    let response = client
-        .get("https://of-dev-api.bfsea.com/commonQuery/get?key=software")
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
+    let response = http::send(client.get(format!(
+        "{}/commonQuery/get?key=software",
+        endpoints::api_base()
+    )))
+    .await
+    .map_err(|e| e.to_string())?;
 
     let software_info: SoftwareInfo = response.json().await.map_err(|e| e.to_string())?;
 
@@ -280,38 +471,23 @@ async fn download_frpc<R: Runtime>(app: tauri::AppHandle<R>) -> Result<String, S
 
     // 先检查文件是否存在
     if target_path.exists() {
-        app.emit(
+        sink.emit_log(
             "log",
-            LogPayload {
-                message: format!(
-                    "当前版本: {}, 最新版本: {}",
-                    config.frpc_version.as_ref().map_or("", |s| s.as_str()),
-                    latest_version
-                ),
-            },
-        )
-        .map_err(|e| e.to_string())?;
+            format!(
+                "当前版本: {}, 最新版本: {}",
+                config.frpc_version.as_ref().map_or("", |s| s.as_str()),
+                latest_version
+            ),
+        );
 
         if let Some(current_version) = config.frpc_version.as_ref() {
             if current_version.as_str() == latest_version {
-                app.emit(
-                    "log",
-                    LogPayload {
-                        message: "已经是最新版本".into(),
-                    },
-                )
-                .map_err(|e| e.to_string())?;
+                sink.emit_log("log", "已经是最新版本".into());
                 return Ok("已经是最新版本".to_string());
             }
         }
     } else {
-        app.emit(
-            "log",
-            LogPayload {
-                message: "未检测到 frpc 文件，开始下载...".into(),
-            },
-        )
-        .map_err(|e| e.to_string())?;
+        sink.emit_log("log", "未检测到 frpc 文件，开始下载...".into());
     }
 
     let app_dir = get_app_dir();
@@ -332,61 +508,29 @@ async fn download_frpc<R: Runtime>(app: tauri::AppHandle<R>) -> Result<String, S
         software_info.data.source[0].value, software_info.data.latest, os_name, arch_name, file_ext
     );
 
-    app.emit(
-        "log",
-        LogPayload {
-            message: format!("开始下载: {}", download_url),
-        },
-    )
-    .map_err(|e| e.to_string())?;
+    sink.emit_log("log", format!("开始下载: {}", download_url));
 
-    let response = client
-        .get(&download_url)
-        .send()
+    let response = http::send(client.get(&download_url).timeout(http::DOWNLOAD_TIMEOUT))
         .await
         .map_err(|e| e.to_string())?;
 
     let total_size = response.content_length().unwrap_or(0);
-    app.emit(
-        "log",
-        LogPayload {
-            message: format!("文件大小: {} bytes", total_size),
-        },
-    )
-    .map_err(|e| e.to_string())?;
+    sink.emit_log("log", format!("文件大小: {} bytes", total_size));
 
     let bytes = response.bytes().await.map_err(|e| e.to_string())?;
 
-    app.emit(
-        "log",
-        LogPayload {
-            message: format!("已下载: {} bytes", bytes.len()),
-        },
-    )
-    .map_err(|e| e.to_string())?;
+    sink.emit_log("log", format!("已下载: {} bytes", bytes.len()));
 
     if os == "windows" {
         let temp_path = app_dir.join("frpc.zip");
-        app.emit(
-            "log",
-            LogPayload {
-                message: format!("临时文件路径: {}", temp_path.display()),
-            },
-        )
-        .map_err(|e| e.to_string())?;
+        sink.emit_log("log", format!("临时文件路径: {}", temp_path.display()));
 
         let mut file = File::create(&temp_path).map_err(|e| e.to_string())?;
         file.write_all(&bytes).map_err(|e| e.to_string())?;
         file.flush().map_err(|e| e.to_string())?;
         drop(file);
 
-        app.emit(
-            "log",
-            LogPayload {
-                message: "开始解压文件...".into(),
-            },
-        )
-        .map_err(|e| e.to_string())?;
+        sink.emit_log("log", "开始解压文件...".into());
 
         let file = File::open(&temp_path).map_err(|e| format!("无法打开下载的文件: {}", e))?;
 
@@ -398,13 +542,7 @@ async fn download_frpc<R: Runtime>(app: tauri::AppHandle<R>) -> Result<String, S
             .map_err(|e| format!("解压失败: {}", e))?;
 
         if let Err(e) = std::fs::remove_file(&temp_path) {
-            app.emit(
-                "log",
-                LogPayload {
-                    message: format!("警告：无法删除临时文件: {}", e),
-                },
-            )
-            .map_err(|e| e.to_string())?;
+            sink.emit_log("log", format!("警告：无法删除临时文件: {}", e));
         }
 
         // 移动文件到最终位置
@@ -420,13 +558,7 @@ async fn download_frpc<R: Runtime>(app: tauri::AppHandle<R>) -> Result<String, S
             fs::remove_dir_all(extract_dir).map_err(|e| format!("无法清理解压目录: {}", e))?;
         }
     } else {
-        app.emit(
-            "log",
-            LogPayload {
-                message: "正在解压文件...".into(),
-            },
-        )
-        .map_err(|e| e.to_string())?;
+        sink.emit_log("log", "正在解压文件...".into());
 
         let cursor = Cursor::new(bytes);
         let gz_decoder = GzDecoder::new(cursor);
@@ -462,42 +594,18 @@ async fn download_frpc<R: Runtime>(app: tauri::AppHandle<R>) -> Result<String, S
                 let mut perms = metadata.permissions();
                 perms.set_mode(0o755); // 设置可执行权限
                 if let Err(e) = fs::set_permissions(&target_path, perms) {
-                    app.emit(
-                        "log",
-                        LogPayload {
-                            message: format!("警告：无法设置文件权限: {}", e),
-                        },
-                    )
-                    .map_err(|e| e.to_string())?;
+                    sink.emit_log("log", format!("警告：无法设置文件权限: {}", e));
                 } else {
-                    app.emit(
-                        "log",
-                        LogPayload {
-                            message: "已设置文件可执行权限".into(),
-                        },
-                    )
-                    .map_err(|e| e.to_string())?;
+                    sink.emit_log("log", "已设置文件可执行权限".into());
                 }
             }
             Err(e) => {
-                app.emit(
-                    "log",
-                    LogPayload {
-                        message: format!("警告：无法获取文件元数据: {}", e),
-                    },
-                )
-                .map_err(|e| e.to_string())?;
+                sink.emit_log("log", format!("警告：无法获取文件元数据: {}", e));
             }
         }
     }
 
-    app.emit(
-        "log",
-        LogPayload {
-            message: "下载和安装完成".into(),
-        },
-    )
-    .map_err(|e| e.to_string())?;
+    sink.emit_log("log", "下载和安装完成".into());
 
     Ok("下载完成".to_string())
 }
@@ -507,7 +615,24 @@ async fn start_frpc_instance<R: Runtime>(
     app: tauri::AppHandle<R>,
     processes: State<'_, FrpcProcesses>,
     id: String,
-    token: String,
+    token: Option<String>,
+    tunnel_id: String,
+) -> Result<preflight::StartResult, preflight::StartError> {
+    if processes.0.lock().is_ok_and(|map| map.contains_key(&id)) {
+        return Err("该隧道已经在运行中".to_string().into());
+    }
+    // 启动前检查本地服务，按设置提示或阻止启动
+    let warnings = preflight::run(&tunnel_id).await?;
+    let message = spawn_frpc(&processes, Arc::new(app), id, token, tunnel_id)?;
+    Ok(preflight::StartResult { message, warnings })
+}
+
+// 启动 frpc 进程，输出同时发送到 sink 和日志文件
+fn spawn_frpc(
+    processes: &FrpcProcesses,
+    sink: Arc<dyn EventSink>,
+    id: String,
+    token: Option<String>,
     tunnel_id: String,
 ) -> Result<String, String> {
     if let Ok(map) = processes.0.lock() {
@@ -516,8 +641,14 @@ async fn start_frpc_instance<R: Runtime>(
         }
     }
 
+    // 未传入用户密钥时使用安全存储中的密钥
+    let token = match token.filter(|t| !t.is_empty()) {
+        Some(token) => token,
+        None => credentials::get(credentials::TOKEN).ok_or("未找到用户密钥，请先登录")?,
+    };
+
     let app_dir = get_app_dir();
-    let config = load_config()?;
+    let config = read_config()?;
     let frpc_path = app_dir.join(&config.frpc_filename.as_ref().unwrap());
 
     if !frpc_path.exists() {
@@ -532,42 +663,34 @@ async fn start_frpc_instance<R: Runtime>(
     }
 
     cmd.args(&["-u", &token, "-p", &tunnel_id]);
-    // 如果启用了 DoH，则添加启动参数
-    let use_doh = std::env::var("USE_DOH").unwrap_or_else(|_| "false".to_string());
-    if use_doh == "true" {
+
+    // 账户配置的启动参数优先于全局环境变量
+    let options = accounts::active_profile().launch_options;
+    let env_flag = |key: &str| std::env::var(key).map(|v| v == "true").unwrap_or(false);
+
+    // 如果启用了 DoH，则添加启动参数，启动器自身的请求使用相同的 DoH 设置
+    let doh = doh::current();
+    if doh.enabled {
         cmd.arg("--use-doh");
     }
+    // 如果指定了自定义 DoH 地址
+    if let Some(addr) = &doh.addr {
+        cmd.args(["--doh-addr", addr]);
+    }
 
     // 如果需要输出 debug 日志
-    let debug_enabled = std::env::var("FRPC_DEBUG").unwrap_or_else(|_| "false".to_string());
-    if debug_enabled == "true" {
+    if options.debug.unwrap_or_else(|| env_flag("FRPC_DEBUG")) {
         cmd.arg("--debug");
     }
 
     // 强制 TLS
-    let force_tls = std::env::var("FRPC_FORCE_TLS").unwrap_or_else(|_| "false".to_string());
-    if force_tls == "true" {
+    if options
+        .force_tls
+        .unwrap_or_else(|| env_flag("FRPC_FORCE_TLS"))
+    {
         cmd.arg("--force-tls");
     }
 
-    // 如果指定了自定义 DoH 地址
-    // 如果需要输出 debug 日志
-    let debug_enabled = std::env::var("FRPC_DEBUG").unwrap_or_else(|_| "false".to_string());
-    let force_tls = std::env::var("FRPC_FORCE_TLS").unwrap_or_else(|_| "false".to_string());
-    if debug_enabled == "true" {
-        cmd.arg("--debug");
-    }
-
-    if let Ok(doh_addr) = std::env::var("DOH_ADDR") {
-        if !doh_addr.is_empty() {
-            // 确保也启用了 DoH
-            if use_doh != "true" {
-                cmd.arg("--use-doh");
-            }
-            cmd.args(&["--doh-addr", &doh_addr]);
-        }
-    }
-
     cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
     println!("{:?}", cmd);
 
@@ -590,62 +713,88 @@ async fn start_frpc_instance<R: Runtime>(
             cmd.env("WINHTTP_NO_PROXY", "*");
         }
     }
+    // 手动指定的代理优先于系统代理
+    network_proxy::apply_to_command(&mut cmd);
 
     let mut child = cmd.spawn().map_err(|e| e.to_string())?;
 
+    // 每次启动时重新创建日志文件
+    let log_file = File::create(frpc_log_path(&id))
+        .map(|f| Arc::new(Mutex::new(f)))
+        .ok();
+
     // 处理标准输出
     if let Some(stdout) = child.stdout.take() {
-        let event_name = format!("frpc-log-{}", id);
-        let app_handle = app.clone();
-
-        std::thread::spawn(move || {
-            use std::io::{BufRead, BufReader};
-            let reader = BufReader::new(stdout);
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    let _ = app_handle.emit(&event_name, LogPayload { message: line });
-                }
-            }
-        });
+        pipe_frpc_output(stdout, sink.clone(), &id, log_file.clone(), "");
     }
 
     // 处理标准错误
     if let Some(stderr) = child.stderr.take() {
-        let event_name = format!("frpc-log-{}", id);
-        let app_handle = app.clone();
-
-        std::thread::spawn(move || {
-            use std::io::{BufRead, BufReader};
-            let reader = BufReader::new(stderr);
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    let _ = app_handle.emit(
-                        &event_name,
-                        LogPayload {
-                            message: format!("错误: {}", line),
-                        },
-                    );
-                }
-            }
-        });
+        pipe_frpc_output(stderr, sink.clone(), &id, log_file.clone(), "错误: ");
     }
 
     // 存储进程信息
+    let account = accounts::active_account();
+    let started_at = chrono::Local::now().timestamp();
     if let Ok(mut map) = processes.0.lock() {
         #[cfg(target_os = "windows")]
         {
             let group_id = child.id();
-            map.insert(id.clone(), ProcessInfo { child, group_id });
+            map.insert(
+                id.clone(),
+                ProcessInfo {
+                    child,
+                    tunnel_id,
+                    account,
+                    started_at,
+                    group_id,
+                },
+            );
         }
         #[cfg(not(target_os = "windows"))]
         {
-            map.insert(id.clone(), ProcessInfo { child });
+            map.insert(
+                id.clone(),
+                ProcessInfo {
+                    child,
+                    tunnel_id,
+                    account,
+                    started_at,
+                },
+            );
         }
+        instance_registry::sync(&map);
     }
 
     Ok("启动成功".to_string())
 }
 
+// 逐行转发 frpc 输出
+fn pipe_frpc_output<T: std::io::Read + Send + 'static>(
+    output: T,
+    sink: Arc<dyn EventSink>,
+    id: &str,
+    log_file: Option<Arc<Mutex<File>>>,
+    prefix: &'static str,
+) {
+    let event_name = format!("frpc-log-{}", id);
+    let id = id.to_string();
+    std::thread::spawn(move || {
+        use std::io::{BufRead, BufReader};
+        let reader = BufReader::new(output);
+        for line in reader.lines().map_while(Result::ok) {
+            let message = format!("{}{}", prefix, line);
+            if let Some(file) = &log_file {
+                if let Ok(mut f) = file.lock() {
+                    let _ = writeln!(f, "{}", message);
+                }
+            }
+            control::publish_log(&id, &message);
+            sink.emit_log(&event_name, message);
+        }
+    });
+}
+
 #[command]
 fn get_system_info() -> String {
     let arch = std::env::consts::ARCH;
@@ -1009,135 +1158,68 @@ fn get_build_info() -> String {
 }
 
 #[command]
-async fn tcp_ping(host: String, port: u16) -> Result<serde_json::Value, String> {
-    use std::net::{TcpStream, ToSocketAddrs};
-    use std::time::{Duration, Instant};
-    println!("tcp_ping called with host: {}, port: {}", host, port);
-
-    // 解析主机名和端口为SocketAddr
-    let addrs = match (host.as_str(), port).to_socket_addrs() {
-        Ok(addrs) => addrs,
-        Err(e) => {
-            return Ok(serde_json::json!({
-                "success": false,
-                "latency_ms": null,
-                "message": format!("地址解析失败: {}", e)
-            }));
-        }
-    };
-
-    // 取第一个地址进行连接测试
-    let addr = match addrs.into_iter().next() {
-        Some(addr) => {
-            println!("Using address: {:?}", addr);
-            addr
-        }
-        None => {
-            println!("No valid address found");
-            return Ok(serde_json::json!({
-                "success": false,
-                "latency_ms": null,
-                "message": "未能解析到有效的地址"
-            }));
-        }
-    };
-
-    let mut latencies: Vec<u128> = Vec::new();
-    let start_time = Instant::now();
-    let max_duration = Duration::from_secs(2); // 最多测试2秒钟
+async fn stop_frpc_instance<R: Runtime>(
+    _app: tauri::AppHandle<R>,
+    processes: State<'_, FrpcProcesses>,
+    id: String,
+) -> Result<(), String> {
+    stop_frpc(&processes, &id)
+}
 
-    // 在1秒钟内进行多次测试
-    while start_time.elapsed() < max_duration {
-        let start = Instant::now();
-        match TcpStream::connect_timeout(&addr, Duration::from_secs(5)) {
-            Ok(_) => {
-                let duration = start.elapsed();
-                latencies.push(duration.as_millis());
-                // 短暂休眠以避免过于频繁的连接尝试
-                tokio::time::sleep(Duration::from_millis(100)).await;
-            }
-            Err(e) => {
-                println!("Connection failed: {}", e);
-                // 即使连接失败也记录时间，但用0表示失败
-                latencies.push(0);
-                // 短暂休眠
-                tokio::time::sleep(Duration::from_millis(100)).await;
-            }
+fn stop_frpc(processes: &FrpcProcesses, id: &str) -> Result<(), String> {
+    if let Ok(mut map) = processes.0.lock() {
+        if let Some(mut process_info) = map.remove(id) {
+            kill_process(&mut process_info);
+            instance_registry::sync(&map);
+            return Ok(());
         }
     }
+    Err("进程不存在".to_string())
+}
 
-    // 计算统计数据
-    if latencies.is_empty() {
-        return Ok(serde_json::json!({
-            "success": false,
-            "latency_ms": null,
-            "message": "未能执行任何测试"
-        }));
+// 结束 frpc 进程（Windows 下连同子进程一起结束）
+fn kill_process(process_info: &mut ProcessInfo) {
+    #[cfg(target_os = "windows")]
+    {
+        let mut cmd = Command::new("taskkill");
+        cmd.creation_flags(CREATE_NO_WINDOW);
+        let _ = cmd
+            .args(["/F", "/T", "/PID"])
+            .arg(process_info.group_id.to_string())
+            .output();
     }
-
-    // 过滤掉失败的连接(延迟为0的)
-    let successful_latencies: Vec<u128> = latencies.iter().filter(|&&x| x > 0).cloned().collect();
-
-    if successful_latencies.is_empty() {
-        return Ok(serde_json::json!({
-            "success": false,
-            "latency_ms": 0,
-            "message": "所有连接测试均失败",
-            "total_tests": latencies.len(),
-            "successful_tests": 0
-        }));
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = process_info.child.kill();
     }
-
-    let total_tests = latencies.len();
-    let successful_tests = successful_latencies.len();
-    let avg_latency = successful_latencies.iter().sum::<u128>() / successful_tests as u128;
-
-    // 计算最小和最大延迟
-    let min_latency = *successful_latencies.iter().min().unwrap();
-    let max_latency = *successful_latencies.iter().max().unwrap();
-
-    println!("Connection successful, average latency: {}ms", avg_latency);
-    Ok(serde_json::json!({
-        "success": true,
-        "latency_ms": avg_latency,
-        "message": format!("测试完成，平均延迟 {}ms (最小: {}ms, 最大: {}ms)", avg_latency, min_latency, max_latency),
-        "total_tests": total_tests,
-        "successful_tests": successful_tests,
-        "min_latency": min_latency,
-        "max_latency": max_latency
-    }))
 }
 
+// 获取正在运行的隧道及启动它们的账户
 #[command]
-async fn stop_frpc_instance<R: Runtime>(
-    _app: tauri::AppHandle<R>,
+async fn get_running_instances(
     processes: State<'_, FrpcProcesses>,
-    id: String,
-) -> Result<(), String> {
+) -> Result<serde_json::Value, String> {
+    let mut list = Vec::new();
     if let Ok(mut map) = processes.0.lock() {
-        if let Some(mut process_info) = map.remove(&id) {
-            #[cfg(target_os = "windows")]
-            {
-                let mut cmd = Command::new("taskkill");
-                cmd.creation_flags(CREATE_NO_WINDOW);
-                let _ = cmd
-                    .args(&["/F", "/T", "/PID"])
-                    .arg(process_info.group_id.to_string())
-                    .output();
-            }
-            #[cfg(not(target_os = "windows"))]
-            {
-                let _ = process_info.child.kill();
-            }
-            return Ok(());
+        // 顺便清理已退出的进程
+        map.retain(|_, info| matches!(info.child.try_wait(), Ok(None)));
+        instance_registry::sync(&map);
+        for (id, info) in map.iter() {
+            list.push(serde_json::json!({
+                "id": id,
+                "tunnel_id": info.tunnel_id,
+                "pid": info.child.id(),
+                "account": info.account,
+                "started_at": info.started_at,
+            }));
         }
     }
-    Err("进程不存在".to_string())
+    Ok(serde_json::Value::Array(list))
 }
 
 #[command]
 async fn get_frpc_version() -> Result<String, String> {
-    let config = load_config()?;
+    let config = read_config()?;
     Ok(config
         .frpc_version
         .as_ref()
@@ -1149,7 +1231,7 @@ async fn get_frpc_version() -> Result<String, String> {
 #[command]
 async fn check_frpc<R: Runtime>(app: tauri::AppHandle<R>) -> Result<bool, String> {
     let app_dir = get_app_dir();
-    let config = load_config()?;
+    let config = read_config()?;
 
     if config.frpc_filename.is_none() {
         app.emit(
@@ -1348,6 +1430,7 @@ async fn check_frpc_status(
             match process_info.child.try_wait() {
                 Ok(Some(_)) => {
                     // 进程已结束
+                    instance_registry::sync(&map);
                     return Ok(false);
                 }
                 Ok(None) => {
@@ -1425,9 +1508,20 @@ async fn exit_app(app_handle: tauri::AppHandle) -> Result<(), String> {
 
 // 在 main 函数前添加这个函数
 fn create_tray_menu(app: &tauri::App) -> Result<TrayIcon, Box<dyn std::error::Error>> {
+    // 显示当前账户，切换账户时更新文字
+    let account_item = MenuItem::with_id(
+        app,
+        "account",
+        accounts::tray_label(&accounts::active_account()),
+        false,
+        None::<&str>,
+    )?;
+    app.manage(accounts::AccountMenuItem(account_item.clone()));
+
     let menu = Menu::with_items(
         app,
         &[
+            &account_item,
             &MenuItem::with_id(app, "show", "显示主窗口", true, None::<&str>)?,
             &MenuItem::with_id(app, "separator", "", false, None::<&str>)?,
             &MenuItem::with_id(
@@ -1461,18 +1555,9 @@ fn create_tray_menu(app: &tauri::App) -> Result<TrayIcon, Box<dyn std::error::Er
                 if let Some(processes) = app.try_state::<FrpcProcesses>() {
                     if let Ok(mut map) = processes.0.lock() {
                         for (_, mut process_info) in map.drain() {
-                            #[cfg(target_os = "windows")]
-                            {
-                                let _ = Command::new("taskkill")
-                                    .args(&["/F", "/T", "/PID"])
-                                    .arg(process_info.group_id.to_string())
-                                    .output();
-                            }
-                            #[cfg(not(target_os = "windows"))]
-                            {
-                                let _ = process_info.child.kill();
-                            }
+                            kill_process(&mut process_info);
                         }
+                        instance_registry::sync(&map);
                     }
                 }
                 app.exit(0);
@@ -1509,7 +1594,7 @@ fn create_tray_menu(app: &tauri::App) -> Result<TrayIcon, Box<dyn std::error::Er
 
 #[command]
 fn get_cpl_version() -> Result<String, String> {
-    let config = load_config()?;
+    let config = read_config()?;
     Ok(config.cpl_version.unwrap_or_else(|| "0.8.1".to_string()))
 }
 
@@ -1726,7 +1811,7 @@ fn get_env(key: String) -> Result<Option<String>, String> {
         return Ok(Some(val));
     }
     // 再检查配置文件中的持久化变量
-    let cfg = load_config()?;
+    let cfg = read_config()?;
     if let Some(map) = cfg.env_vars {
         if let Some(v) = map.get(&key) {
             return Ok(Some(v.clone()));
@@ -1747,67 +1832,62 @@ fn check_proxy_bypass() -> Result<bool, String> {
 async fn test_network_connection() -> Result<serde_json::Value, String> {
     let bypass_proxy = std::env::var("BYPASS_PROXY").unwrap_or_else(|_| "false".to_string());
 
-    let client_builder = reqwest::Client::builder().timeout(std::time::Duration::from_secs(10));
+    let client = http::client()?;
 
-    let client = if bypass_proxy == "true" {
-        client_builder
-            .no_proxy()
-            .build()
-            .map_err(|e| e.to_string())?
-    } else {
-        client_builder.build().map_err(|e| e.to_string())?
-    };
-
-    let mut results = serde_json::json!({
+    Ok(serde_json::json!({
         "bypass_proxy": bypass_proxy == "true",
-        "tests": []
-    });
+        "proxy": network_proxy::current().is_some(),
+        "tests": run_network_tests(&client).await,
+    }))
+}
+
+// 用指定的客户端依次访问测试地址
+async fn run_network_tests(client: &reqwest::Client) -> Vec<serde_json::Value> {
+    let mut tests = Vec::new();
 
     // 测试多个端点
+    let api_url = format!("{}/frp/api/getUserInfo", endpoints::api_base());
     let test_urls = vec![
-        (
-            "OpenFrp API",
-            "https://of-dev-api.bfsea.com/frp/api/getUserInfo",
-        ),
+        ("OpenFrp API", api_url.as_str()),
         ("Baidu", "https://www.baidu.com"),
         ("ZGIT API", "https://api.zyghit.cn"),
         ("GitHub", "https://api.github.com"),
-        ("Google DNS", "https://8.8.8.8"),
+        // 直接访问 IP 的 HTTPS 证书校验必然失败，改用域名
+        ("AliDNS", "https://dns.alidns.com"),
     ];
 
     for (name, url) in test_urls {
         let start = std::time::Instant::now();
-        match client.get(url).send().await {
+        match client
+            .get(url)
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await
+        {
             Ok(response) => {
                 let duration = start.elapsed();
-                results["tests"]
-                    .as_array_mut()
-                    .unwrap()
-                    .push(serde_json::json!({
-                        "name": name,
-                        "url": url,
-                        "success": true,
-                        "status": response.status().as_u16(),
-                        "latency_ms": duration.as_millis()
-                    }));
+                tests.push(serde_json::json!({
+                    "name": name,
+                    "url": url,
+                    "success": true,
+                    "status": response.status().as_u16(),
+                    "latency_ms": duration.as_millis()
+                }));
             }
             Err(e) => {
                 let duration = start.elapsed();
-                results["tests"]
-                    .as_array_mut()
-                    .unwrap()
-                    .push(serde_json::json!({
-                        "name": name,
-                        "url": url,
-                        "success": false,
-                        "error": e.to_string(),
-                        "latency_ms": duration.as_millis()
-                    }));
+                tests.push(serde_json::json!({
+                    "name": name,
+                    "url": url,
+                    "success": false,
+                    "error": e.to_string(),
+                    "latency_ms": duration.as_millis()
+                }));
             }
         }
     }
 
-    Ok(results)
+    tests
 }
 
 // 添加状态结构体
@@ -1827,7 +1907,7 @@ async fn oauth_callback(
     code: String,
     redirect_url: Option<String>,
 ) -> Result<OAuthResponse, String> {
-    let client = reqwest::Client::new();
+    let client = http::client()?;
     let mut form = std::collections::HashMap::new();
     form.insert("code", code);
     // 使用与获取登录URL时相同的 redirect_url，确保服务端校验通过
@@ -1837,7 +1917,7 @@ async fn oauth_callback(
     );
 
     let res = client
-        .post("https://of-dev-api.bfsea.com/oauth2/callback")
+        .post(format!("{}/oauth2/callback", endpoints::api_base()))
         .form(&form)
         .send()
         .await
@@ -1856,9 +1936,14 @@ async fn oauth_callback(
         .await
         .map_err(|e| e.to_string())?;
 
+    let flag = json["flag"].as_bool().unwrap_or(false);
+    if flag {
+        credentials::set(credentials::AUTHORIZATION, &auth)?;
+    }
+
     Ok(OAuthResponse {
         authorization: auth,
-        flag: json["flag"].as_bool().unwrap_or(false),
+        flag,
         msg: json["msg"].as_str().unwrap_or("").to_string(),
         data: json["data"].as_str().unwrap_or("").to_string(),
     })
@@ -1992,126 +2077,29 @@ fn open_app_data_dir() -> Result<(), String> {
     Ok(())
 }
 
-#[tauri::command]
-async fn get_local_ports() -> Result<serde_json::Value, String> {
-    #[cfg(target_os = "windows")]
-    {
-        use std::collections::HashMap;
-        let output = std::process::Command::new("netstat")
-            .args(["-ano"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output()
-            .map_err(|e| e.to_string())?;
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut tcp_ports = Vec::new();
-        let mut udp_ports = Vec::new();
-        let mut pid_map: HashMap<u32, String> = HashMap::new();
-        // 获取进程名
-        if let Ok(tasklist) = std::process::Command::new("tasklist")
-            .creation_flags(CREATE_NO_WINDOW)
-            .output()
-        {
-            let taskout = String::from_utf8_lossy(&tasklist.stdout);
-            for line in taskout.lines().skip(3) {
-                let parts: Vec<_> = line.split_whitespace().collect();
-                if parts.len() >= 2 {
-                    if let Ok(pid) = parts[1].parse::<u32>() {
-                        pid_map.insert(pid, parts[0].to_string());
-                    }
-                }
-            }
-        }
-        for line in stdout.lines() {
-            let line = line.trim();
-            let parts: Vec<_> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                let proto = parts[0].to_uppercase();
-                let addr = parts[1];
-                let pid = parts.last().and_then(|s| s.parse::<u32>().ok());
-                if let Some(idx) = addr.rfind(':') {
-                    if let Ok(port) = addr[idx + 1..].parse::<u16>() {
-                        let process = pid.and_then(|p| pid_map.get(&p)).cloned();
-                        let entry = serde_json::json!({
-                            "port": port,
-                            "pid": pid,
-                            "process": process,
-                        });
-                        if proto.starts_with("TCP") {
-                            tcp_ports.push(entry);
-                        } else if proto.starts_with("UDP") {
-                            udp_ports.push(entry);
-                        }
-                    }
-                }
-            }
-        }
-        return Ok(serde_json::json!({ "tcp": tcp_ports, "udp": udp_ports }));
+// 修改 main 函数
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    // 本地自检安装的 frpc 转接程序，只存在于启用 selftest 特性的构建中
+    #[cfg(feature = "selftest")]
+    if selftest::shim_requested(&args) {
+        std::process::exit(selftest::run_frpc_shim(&args));
     }
-    #[cfg(not(target_os = "windows"))]
-    {
-        use std::collections::HashMap;
-        let output = std::process::Command::new("netstat")
-            .args(["-tunlp"])
-            .output()
-            .map_err(|e| e.to_string())?;
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut tcp_ports = Vec::new();
-        let mut udp_ports = Vec::new();
-        let mut pid_map: HashMap<u32, String> = HashMap::new();
-        // 解析 /proc 获取进程名
-        if let Ok(entries) = std::fs::read_dir("/proc") {
-            for entry in entries.flatten() {
-                if let Ok(pid) = entry.file_name().to_string_lossy().parse::<u32>() {
-                    if let Ok(cmdline) = std::fs::read_to_string(format!("/proc/{}/cmdline", pid)) {
-                        let name = cmdline
-                            .split('\0')
-                            .next()
-                            .unwrap_or("")
-                            .split('/')
-                            .last()
-                            .unwrap_or("")
-                            .to_string();
-                        pid_map.insert(pid, name);
-                    }
-                }
-            }
-        }
-        for line in stdout.lines() {
-            let line = line.trim();
-            let parts: Vec<_> = line.split_whitespace().collect();
-            if parts.len() >= 6 {
-                let proto = parts[0].to_lowercase();
-                let addr = parts[3];
-                let pid_info = parts[6];
-                let pid = pid_info
-                    .split('/')
-                    .next()
-                    .and_then(|s| s.parse::<u32>().ok());
-                if let Some(idx) = addr.rfind(':') {
-                    if let Ok(port) = addr[idx + 1..].parse::<u16>() {
-                        let process = pid.and_then(|p| pid_map.get(&p)).cloned();
-                        let entry = serde_json::json!({
-                            "port": port,
-                            "pid": pid,
-                            "process": process,
-                        });
-                        if proto.starts_with("tcp") {
-                            tcp_ports.push(entry);
-                        } else if proto.starts_with("udp") {
-                            udp_ports.push(entry);
-                        }
-                    }
-                }
+
+    // 带子命令时按命令行模式运行，不创建窗口
+    if let Some(command) = cli::parse(&args[1..]) {
+        let code = match command {
+            Ok(command) => cli::run(command),
+            Err(e) => {
+                eprintln!("{}", e);
+                2
             }
-        }
-        return Ok(serde_json::json!({ "tcp": tcp_ports, "udp": udp_ports }));
+        };
+        std::process::exit(code);
     }
-    #[allow(unreachable_code)]
-    Ok(serde_json::json!({ "tcp": [], "udp": [] }))
-}
 
-// 修改 main 函数
-fn main() {
+    let context = tauri::generate_context!();
+    let processes = FrpcProcesses::default();
     let app = tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
@@ -2132,15 +2120,7 @@ fn main() {
         .setup(|app| {
             let app_dir = init_app_directory(app)?;
             // 加载持久化环境变量并注入当前进程
-            if let Ok(cfg) = load_config() {
-                if let Some(map) = cfg.env_vars {
-                    for (k, v) in map {
-                        if !v.is_empty() {
-                            std::env::set_var(k, v);
-                        }
-                    }
-                }
-            }
+            apply_config_env();
             println!("应用程序目录: {:?}", app_dir);
 
             #[cfg(any(windows, target_os = "linux"))]
@@ -2151,6 +2131,29 @@ fn main() {
 
             let _tray = create_tray_menu(app)?;
 
+            // 启动时检查 API 地址可用性，选择可用的地址
+            tauri::async_runtime::spawn(async {
+                endpoints::check_health().await;
+            });
+
+            auth_refresh::set_sink(Arc::new(app.handle().clone()));
+
+            // 本地控制接口，与界面共用同一份进程列表
+            let ctx = control::ControlContext {
+                processes: app.state::<FrpcProcesses>().inner().clone(),
+                sink: Arc::new(app.handle().clone()),
+                supervised: Default::default(),
+            };
+            // 定期检测运行中隧道的可用性
+            monitor::spawn(ctx.processes.clone(), ctx.sink.clone());
+            preflight::spawn(ctx.processes.clone(), ctx.sink.clone());
+            traffic::spawn(ctx.processes.clone(), ctx.sink.clone());
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = control::serve(ctx).await {
+                    println!("本地控制接口启动失败: {}", e);
+                }
+            });
+
             #[cfg(target_os = "windows")]
             {
                 if let Err(e) = register_app_for_notifications() {
@@ -2168,7 +2171,7 @@ fn main() {
 
             Ok(())
         })
-        .manage(FrpcProcesses::default())
+        .manage(processes)
         .invoke_handler(tauri::generate_handler![
             check_frpc_status,
             download_frpc,
@@ -2189,6 +2192,11 @@ fn main() {
             get_env,
             check_proxy_bypass,
             test_network_connection,
+            diagnostics::run_diagnostics,
+            doh::test_doh,
+            network_proxy::get_network_proxy,
+            network_proxy::set_network_proxy,
+            network_proxy::test_network_proxy,
             oauth_callback,
             check_update,
             install_update,
@@ -2196,11 +2204,49 @@ fn main() {
             get_system_info,
             get_detailed_system_info,
             api_proxy::proxy_api,
+            endpoints::get_endpoint_settings,
+            endpoints::set_endpoint_settings,
+            endpoints::check_api_health,
+            openfrp_api::get_user_info,
+            openfrp_api::get_user_proxies,
+            openfrp_api::get_node_list,
+            openfrp_api::new_proxy,
+            openfrp_api::edit_proxy,
+            openfrp_api::remove_proxy,
+            openfrp_api::user_sign,
+            openfrp_api::get_all_proxies,
+            credentials::set_credential,
+            credentials::clear_credential,
+            credentials::has_credential,
+            accounts::list_accounts,
+            accounts::save_account,
+            accounts::delete_account,
+            accounts::switch_account,
+            get_running_instances,
+            control::get_control_info,
             get_app_data_dir,
             open_app_data_dir,
-            get_local_ports, // 新增端口扫描命令
+            ports::get_local_ports,
+            port_analysis::analyze_tunnel_ports,
+            tunnels::create_tunnel,
+            tunnels::edit_tunnel,
+            tunnels::delete_tunnel,
+            tunnels::batch_delete_tunnels,
+            tunnel_spec::apply_tunnel_spec,
+            node_recommend::recommend_nodes,
             download_and_install_update,
-            tcp_ping,
+            ping::tcp_ping,
+            ping::tcp_ping_batch,
+            monitor::get_tunnel_health,
+            monitor::get_tunnel_health_history,
+            monitor::get_monitor_settings,
+            monitor::set_monitor_settings,
+            preflight::get_local_service_health,
+            preflight::get_preflight_settings,
+            preflight::set_preflight_settings,
+            traffic::get_traffic_stats,
+            traffic::get_traffic_settings,
+            traffic::set_traffic_settings,
             argo_access::argo_generate_public_key,
             argo_access::argo_request_login,
             argo_access::argo_poll_login,
@@ -2213,7 +2259,7 @@ fn main() {
             // argo_wait_authorization,
             // argo_cancel_wait,
         ])
-        .build(tauri::generate_context!())
+        .build(context)
         .expect("error while running tauri application");
 
     app.run(|app_handle, event| {
@@ -2243,7 +2289,7 @@ fn main() {
             }
             tauri::RunEvent::Resumed => {}
             tauri::RunEvent::MainEventsCleared => {}
-            tauri::RunEvent::Exit => {}
+            tauri::RunEvent::Exit => monitor::flush(),
             tauri::RunEvent::ExitRequested { .. } => {}
             _ => {}
         }
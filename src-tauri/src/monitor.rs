@@ -0,0 +1,826 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::command;
+
+use crate::openfrp_api::{OpenFrpClient, Proxy};
+use crate::ping::{self, PingOptions};
+use crate::{load_config, read_config, save_config, EventSink, FrpcProcesses};
+
+// 隧道可用性监控：定期从公网侧检测运行中隧道的连接地址，
+// TCP 类隧道测试端口连通，HTTP/S 隧道发起 GET 请求并可校验状态码与响应内容。
+// 连续失败达到阈值才判定为不可用，状态变化时发出 tunnel-health 事件和桌面通知，
+// 检测记录保存到 monitor/history.json，用于计算可用率：最近 24 小时保留每次检测，
+// 更早的按小时合并，超过 7 天的删除。界面与守护进程共用该文件，保存时在文件锁内
+// 合并各自新增的记录，退出时也会保存
+
+const MIN_INTERVAL_SECS: u64 = 10;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+// 检测记录保留时长
+const HISTORY_SECS: i64 = 7 * 24 * 3600;
+// 保留每次检测的时长，更早的记录按小时合并
+const DETAIL_SECS: i64 = 24 * 3600;
+// 后台检查（启动前检查、本地服务复查、可用性监控）共用的隧道列表有效期
+pub const PROXY_LIST_MAX_AGE: Duration = Duration::from_secs(60);
+// 检测记录写入文件的间隔
+const SAVE_INTERVAL: Duration = Duration::from_secs(600);
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct HttpCheck {
+    // 请求路径，默认为 /
+    #[serde(default)]
+    pub path: Option<String>,
+    // 期望的状态码，未设置时小于 500 即视为正常
+    #[serde(default)]
+    pub expected_status: Option<u16>,
+    // 响应中应包含的内容
+    #[serde(default)]
+    pub expected_body: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MonitorSettings {
+    pub enabled: bool,
+    // 检测间隔（秒）
+    pub interval_secs: u64,
+    // 连续失败多少次判定为不可用
+    pub failure_threshold: u32,
+    // 状态变化时是否发送桌面通知
+    pub notify: bool,
+    // 按隧道 ID 配置的 HTTP 检测规则
+    pub http_checks: HashMap<String, HttpCheck>,
+}
+
+impl Default for MonitorSettings {
+    fn default() -> Self {
+        MonitorSettings {
+            enabled: true,
+            interval_secs: 60,
+            failure_threshold: 2,
+            notify: true,
+            http_checks: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthState {
+    Up,
+    Down,
+    // 尚未得出结论，或隧道类型无法从公网检测
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CheckRecord {
+    pub at: i64,
+    pub up: bool,
+    pub latency_ms: Option<f64>,
+}
+
+// 一小时内检测结果的合计
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+pub struct HourlySummary {
+    pub checks: u32,
+    pub up: u32,
+    pub latency_total_ms: f64,
+    pub latency_count: u32,
+}
+
+impl HourlySummary {
+    fn add(&mut self, record: &CheckRecord) {
+        self.checks += 1;
+        self.up += record.up as u32;
+        if let Some(latency) = record.latency_ms {
+            self.latency_total_ms += latency;
+            self.latency_count += 1;
+        }
+    }
+}
+
+// 单个隧道的检测记录
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+struct TunnelHistory {
+    // 最近 24 小时的每次检测
+    recent: VecDeque<CheckRecord>,
+    // 更早的记录，键为小时开始时间
+    hourly: BTreeMap<i64, HourlySummary>,
+}
+
+impl TunnelHistory {
+    // 将超过 24 小时的记录合并到按小时统计中，删除超过保留时长的记录
+    fn compact(&mut self, now: i64) {
+        while let Some(record) = self.recent.front() {
+            if record.at >= now - DETAIL_SECS {
+                break;
+            }
+            let hour = record.at - record.at.rem_euclid(3600);
+            self.hourly.entry(hour).or_default().add(record);
+            self.recent.pop_front();
+        }
+        self.hourly.retain(|start, _| *start >= now - HISTORY_SECS);
+    }
+
+    // 加入一次检测结果并合并过期记录
+    fn push(&mut self, check: CheckRecord, now: i64) {
+        self.recent.push_back(check);
+        self.compact(now);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.recent.is_empty() && self.hourly.is_empty()
+    }
+
+    // since 之后的可用率（百分比），没有记录时为 None
+    fn uptime(&self, since: i64) -> Option<f64> {
+        let (mut total, mut up) = self
+            .recent
+            .iter()
+            .filter(|r| r.at >= since)
+            .fold((0u32, 0u32), |(total, up), r| (total + 1, up + r.up as u32));
+        for summary in self
+            .hourly
+            .range(since - since.rem_euclid(3600)..)
+            .map(|(_, s)| s)
+        {
+            total += summary.checks;
+            up += summary.up;
+        }
+        (total > 0).then(|| (up as f64 * 1000.0 / total as f64).round() / 10.0)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct HourlyPoint {
+    pub start: i64,
+    pub checks: u32,
+    pub up: u32,
+    pub avg_latency_ms: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct HealthHistory {
+    // 最近 24 小时的每次检测
+    pub recent: Vec<CheckRecord>,
+    // 更早的按小时合计
+    pub hourly: Vec<HourlyPoint>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct TunnelHealth {
+    pub tunnel_id: String,
+    pub name: String,
+    // 检测的地址，HTTP/S 隧道为完整 URL
+    pub target: String,
+    pub state: HealthState,
+    // 进入当前状态的时间
+    pub since: i64,
+    pub last_checked: Option<i64>,
+    pub latency_ms: Option<f64>,
+    pub message: String,
+    pub consecutive_failures: u32,
+    // 最近 24 小时与 7 天的可用率（百分比），没有记录时为 None
+    pub uptime_24h: Option<f64>,
+    pub uptime_7d: Option<f64>,
+}
+
+impl TunnelHealth {
+    fn new(tunnel_id: String, now: i64) -> Self {
+        TunnelHealth {
+            tunnel_id,
+            name: String::new(),
+            target: String::new(),
+            state: HealthState::Unknown,
+            since: now,
+            last_checked: None,
+            latency_ms: None,
+            message: String::new(),
+            consecutive_failures: 0,
+            uptime_24h: None,
+            uptime_7d: None,
+        }
+    }
+
+    // 根据检测结果更新状态：成功立即恢复可用，连续失败达到阈值才判定为不可用。
+    // 状态发生变化时返回变化前的状态
+    fn update_state(&mut self, up: bool, threshold: u32, now: i64) -> Option<HealthState> {
+        self.consecutive_failures = if up { 0 } else { self.consecutive_failures + 1 };
+        let state = if up {
+            HealthState::Up
+        } else if self.consecutive_failures >= threshold.max(1) {
+            HealthState::Down
+        } else {
+            self.state
+        };
+        let previous = (state != self.state).then_some(self.state);
+        if previous.is_some() {
+            self.since = now;
+        }
+        self.state = state;
+        self.last_checked = Some(now);
+        previous
+    }
+}
+
+struct Probe {
+    up: bool,
+    latency_ms: Option<f64>,
+    message: String,
+}
+
+// 运行中隧道的当前状态
+static HEALTH: Lazy<Mutex<HashMap<String, TunnelHealth>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+// 各隧道的检测记录，首次使用时从文件加载，保存时换成合并后的记录
+static HISTORY: Lazy<Mutex<HashMap<String, TunnelHistory>>> =
+    Lazy::new(|| Mutex::new(load_history()));
+// 本进程上次保存之后新增的检测记录
+static PENDING: Lazy<Mutex<HashMap<String, Vec<CheckRecord>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn history_path() -> PathBuf {
+    crate::get_app_dir().join("monitor").join("history.json")
+}
+
+fn load_history() -> HashMap<String, TunnelHistory> {
+    std::fs::read_to_string(history_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// 合并并删除过期记录，已删除的隧道不再产生新记录，其记录也会随时间清除
+fn compact_history(history: &mut HashMap<String, TunnelHistory>, now: i64) {
+    for entry in history.values_mut() {
+        entry.compact(now);
+    }
+    history.retain(|_, entry| !entry.is_empty());
+}
+
+fn save_history(history: &HashMap<String, TunnelHistory>) {
+    match serde_json::to_string(history) {
+        Ok(content) => {
+            if let Err(e) = crate::write_atomic(&history_path(), content.as_bytes()) {
+                println!("保存监控记录失败: {}", e);
+            }
+        }
+        Err(e) => println!("序列化监控记录失败: {}", e),
+    }
+}
+
+// 重新读取文件，加入本进程新增的记录后写回，避免覆盖另一个进程保存的记录
+pub fn flush() {
+    let path = history_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let result = crate::with_file_lock(&path, || {
+        let mut history = load_history();
+        let pending = std::mem::take(&mut *PENDING.lock().unwrap());
+        for (id, records) in pending {
+            let entry = history.entry(id).or_default();
+            entry.recent.extend(records);
+            entry.recent.make_contiguous().sort_by_key(|r| r.at);
+        }
+        compact_history(&mut history, chrono::Utc::now().timestamp());
+        save_history(&history);
+        *HISTORY.lock().unwrap() = history;
+    });
+    if let Err(e) = result {
+        println!("锁定监控记录失败: {}", e);
+    }
+}
+
+pub fn settings() -> MonitorSettings {
+    read_config()
+        .ok()
+        .and_then(|cfg| cfg.monitor)
+        .unwrap_or_default()
+}
+
+// 隧道的域名列表
+fn domains(proxy: &Proxy) -> Vec<String> {
+    proxy
+        .domain
+        .as_deref()
+        .and_then(|d| serde_json::from_str(d).ok())
+        .unwrap_or_default()
+}
+
+// 根据隧道类型确定检测地址，无法从公网检测时返回 None
+fn target(proxy: &Proxy, check: Option<&HttpCheck>) -> Option<String> {
+    let proxy_type = proxy.proxy_type.to_lowercase();
+    match proxy_type.as_str() {
+        "http" | "https" => {
+            let domain = domains(proxy).into_iter().next()?;
+            let path = check.and_then(|c| c.path.as_deref()).unwrap_or("/");
+            let path = if path.starts_with('/') {
+                path.to_string()
+            } else {
+                format!("/{}", path)
+            };
+            Some(format!("{}://{}{}", proxy_type, domain, path))
+        }
+        "tcp" => {
+            let address = proxy.connect_address.trim();
+            if address.rsplit_once(':').is_some() {
+                Some(address.to_string())
+            } else {
+                proxy
+                    .remote_port
+                    .map(|port| format!("{}:{}", address, port))
+            }
+        }
+        // UDP 无法通过连接判断，STCP/XTCP 需要访问端，不从公网检测
+        _ => None,
+    }
+}
+
+async fn probe_tcp(address: &str) -> Probe {
+    let Some((host, port)) = address
+        .rsplit_once(':')
+        .and_then(|(host, port)| port.parse::<u16>().ok().map(|port| (host, port)))
+    else {
+        return Probe {
+            up: false,
+            latency_ms: None,
+            message: format!("连接地址无效: {}", address),
+        };
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let options = PingOptions {
+        count: 2,
+        timeout_ms: PROBE_TIMEOUT.as_millis() as u64,
+        interval_ms: 200,
+        ..Default::default()
+    };
+    let report = ping::ping(host, port, &options).await;
+    Probe {
+        up: report.success,
+        latency_ms: report.latency_ms,
+        message: report.message,
+    }
+}
+
+async fn probe_http(url: &str, check: Option<&HttpCheck>) -> Probe {
+    let failed = |message: String| Probe {
+        up: false,
+        latency_ms: None,
+        message,
+    };
+    let client = match crate::http::client() {
+        Ok(client) => client,
+        Err(e) => return failed(e),
+    };
+    let start = Instant::now();
+    let response = match client.get(url).timeout(PROBE_TIMEOUT).send().await {
+        Ok(response) => response,
+        Err(e) => return failed(format!("请求失败: {}", e)),
+    };
+    let latency_ms = Some(start.elapsed().as_secs_f64() * 1000.0);
+    let status = response.status().as_u16();
+    let expected_status = check.and_then(|c| c.expected_status);
+    let status_ok = match expected_status {
+        Some(expected) => status == expected,
+        None => status < 500,
+    };
+    if !status_ok {
+        return Probe {
+            up: false,
+            latency_ms,
+            message: match expected_status {
+                Some(expected) => format!("状态码 {}，期望 {}", status, expected),
+                None => format!("状态码 {}", status),
+            },
+        };
+    }
+    if let Some(expected) = check
+        .and_then(|c| c.expected_body.as_deref())
+        .filter(|b| !b.is_empty())
+    {
+        let body = match response.text().await {
+            Ok(body) => body,
+            Err(e) => return failed(format!("读取响应失败: {}", e)),
+        };
+        if !body.contains(expected) {
+            return Probe {
+                up: false,
+                latency_ms,
+                message: format!("响应中未包含 {:?}", expected),
+            };
+        }
+    }
+    Probe {
+        up: true,
+        latency_ms,
+        message: format!("状态码 {}", status),
+    }
+}
+
+fn state_label(state: HealthState) -> &'static str {
+    match state {
+        HealthState::Up => "可用",
+        HealthState::Down => "不可用",
+        HealthState::Unknown => "未知",
+    }
+}
+
+// 记录一次检测结果，状态发生变化时返回变化前的状态
+fn record(
+    proxy: &Proxy,
+    target: String,
+    probe: Probe,
+    threshold: u32,
+    now: i64,
+) -> (TunnelHealth, Option<HealthState>) {
+    let tunnel_id = proxy.id.to_string();
+    let (uptime_24h, uptime_7d) = {
+        let mut history = HISTORY.lock().unwrap();
+        let entry = history.entry(tunnel_id.clone()).or_default();
+        let check = CheckRecord {
+            at: now,
+            up: probe.up,
+            latency_ms: probe.latency_ms,
+        };
+        PENDING
+            .lock()
+            .unwrap()
+            .entry(tunnel_id.clone())
+            .or_default()
+            .push(check.clone());
+        entry.push(check, now);
+        (
+            entry.uptime(now - DETAIL_SECS),
+            entry.uptime(now - HISTORY_SECS),
+        )
+    };
+
+    let mut health = HEALTH.lock().unwrap();
+    let entry = health
+        .entry(tunnel_id.clone())
+        .or_insert_with(|| TunnelHealth::new(tunnel_id, now));
+    let previous = entry.update_state(probe.up, threshold, now);
+    entry.name = proxy.proxy_name.clone();
+    entry.target = target;
+    entry.latency_ms = probe.latency_ms;
+    entry.message = probe.message;
+    entry.uptime_24h = uptime_24h;
+    entry.uptime_7d = uptime_7d;
+    (entry.clone(), previous)
+}
+
+fn mark_unknown(proxy: &Proxy, message: &str, now: i64) {
+    let mut health = HEALTH.lock().unwrap();
+    health.insert(
+        proxy.id.to_string(),
+        TunnelHealth {
+            tunnel_id: proxy.id.to_string(),
+            name: proxy.proxy_name.clone(),
+            target: proxy.connect_address.clone(),
+            state: HealthState::Unknown,
+            since: now,
+            last_checked: Some(now),
+            latency_ms: None,
+            message: message.to_string(),
+            consecutive_failures: 0,
+            uptime_24h: None,
+            uptime_7d: None,
+        },
+    );
+}
+
+fn notify_change(
+    sink: &Arc<dyn EventSink>,
+    health: &TunnelHealth,
+    previous: HealthState,
+    notify: bool,
+) {
+    sink.emit_event(
+        "tunnel-health",
+        serde_json::json!({
+            "tunnel_id": health.tunnel_id,
+            "name": health.name,
+            "state": health.state,
+            "previous": previous,
+            "health": health,
+        }),
+    );
+    // 首次检测成功不打扰用户
+    let first_up = previous == HealthState::Unknown && health.state == HealthState::Up;
+    if notify && !first_up {
+        let body = match health.state {
+            HealthState::Down => format!("{} 无法访问: {}", health.target, health.message),
+            _ => format!("{} 已恢复访问", health.target),
+        };
+        sink.notify(
+            &format!("隧道 {} {}", health.name, state_label(health.state)),
+            &body,
+        );
+    }
+}
+
+// 运行中的隧道 ID，frpc 的 -p 参数可包含多个以逗号分隔的隧道
+fn running_tunnels(processes: &FrpcProcesses) -> Vec<String> {
+    let map = processes.0.lock().unwrap();
+    map.values()
+        .flat_map(|info| info.tunnel_id.split(','))
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+// 运行中隧道的详细信息，短时间内重复调用时共用一次请求，接口不可用时使用缓存
+pub async fn running_proxies(processes: &FrpcProcesses) -> Result<Vec<Proxy>, String> {
+    let running = running_tunnels(processes);
+    if running.is_empty() {
+        return Ok(Vec::new());
+    }
+    let client = OpenFrpClient::new().map_err(|e| format!("获取隧道列表失败: {}", e))?;
+    let proxies = client
+        .user_proxies_within(PROXY_LIST_MAX_AGE)
+        .await
+        .map_err(|e| format!("获取隧道列表失败: {}", e))?;
+    Ok(proxies
+        .data
+        .list
+        .into_iter()
+        .filter(|p| running.contains(&p.id.to_string()))
+        .collect())
+}
+
+// 检测一轮所有运行中的隧道
+async fn check_all(
+    processes: &FrpcProcesses,
+    sink: &Arc<dyn EventSink>,
+    settings: &MonitorSettings,
+) {
+    // 已停止的隧道不再显示状态，检测记录保留
+    let running = running_tunnels(processes);
+    HEALTH.lock().unwrap().retain(|id, _| running.contains(id));
+    let proxies = match running_proxies(processes).await {
+        Ok(proxies) => proxies,
+        Err(e) => {
+            println!("监控{}", e);
+            return;
+        }
+    };
+    if proxies.is_empty() {
+        return;
+    }
+
+    let mut handles = Vec::new();
+    for proxy in proxies {
+        let check = settings.http_checks.get(&proxy.id.to_string()).cloned();
+        let Some(target) = target(&proxy, check.as_ref()) else {
+            mark_unknown(
+                &proxy,
+                "该类型隧道无法从公网检测",
+                chrono::Utc::now().timestamp(),
+            );
+            continue;
+        };
+        handles.push(tokio::spawn(async move {
+            let probe = if target.starts_with("http") {
+                probe_http(&target, check.as_ref()).await
+            } else {
+                probe_tcp(&target).await
+            };
+            (proxy, target, probe)
+        }));
+    }
+
+    for handle in handles {
+        let Ok((proxy, target, probe)) = handle.await else {
+            continue;
+        };
+        let now = chrono::Utc::now().timestamp();
+        let (health, previous) = record(&proxy, target, probe, settings.failure_threshold, now);
+        if let Some(previous) = previous {
+            notify_change(sink, &health, previous, settings.notify);
+        }
+    }
+}
+
+// 在后台持续检测，界面与守护进程各启动一次
+pub fn spawn(processes: FrpcProcesses, sink: Arc<dyn EventSink>) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_save = Instant::now();
+        loop {
+            let settings = settings();
+            if settings.enabled {
+                check_all(&processes, &sink, &settings).await;
+            }
+            if last_save.elapsed() >= SAVE_INTERVAL {
+                flush();
+                last_save = Instant::now();
+            }
+            let interval = settings.interval_secs.max(MIN_INTERVAL_SECS);
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+    });
+}
+
+// 运行中隧道的当前状态，tunnel_id 为空时返回全部
+#[command]
+pub fn get_tunnel_health(tunnel_id: Option<String>) -> Vec<TunnelHealth> {
+    let health = HEALTH.lock().unwrap();
+    let mut list: Vec<TunnelHealth> = health
+        .values()
+        .filter(|h| tunnel_id.is_none() || tunnel_id.as_ref() == Some(&h.tunnel_id))
+        .cloned()
+        .collect();
+    list.sort_by(|a, b| a.tunnel_id.cmp(&b.tunnel_id));
+    list
+}
+
+// 隧道的检测记录，since 为 Unix 时间戳（秒）
+#[command]
+pub fn get_tunnel_health_history(tunnel_id: String, since: Option<i64>) -> HealthHistory {
+    let since = since.unwrap_or(i64::MIN);
+    let history = HISTORY.lock().unwrap();
+    let Some(entry) = history.get(&tunnel_id) else {
+        return HealthHistory::default();
+    };
+    HealthHistory {
+        recent: entry
+            .recent
+            .iter()
+            .filter(|r| r.at >= since)
+            .cloned()
+            .collect(),
+        hourly: entry
+            .hourly
+            .iter()
+            .filter(|(start, _)| **start + 3600 > since)
+            .map(|(start, s)| HourlyPoint {
+                start: *start,
+                checks: s.checks,
+                up: s.up,
+                avg_latency_ms: (s.latency_count > 0)
+                    .then(|| s.latency_total_ms / s.latency_count as f64),
+            })
+            .collect(),
+    }
+}
+
+#[command]
+pub fn get_monitor_settings() -> Result<MonitorSettings, String> {
+    Ok(settings())
+}
+
+#[command]
+pub fn set_monitor_settings(settings: MonitorSettings) -> Result<(), String> {
+    let mut cfg = load_config()?;
+    cfg.monitor = Some(settings);
+    save_config(&cfg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 3600;
+    // 取整点，便于计算小时边界
+    const NOW: i64 = 1_700_000_000 - 1_700_000_000 % HOUR;
+
+    fn check(at: i64, up: bool) -> CheckRecord {
+        CheckRecord {
+            at,
+            up,
+            latency_ms: up.then_some(10.0),
+        }
+    }
+
+    fn summary(checks: u32, up: u32) -> HourlySummary {
+        HourlySummary {
+            checks,
+            up,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn compact_buckets_by_hour() {
+        let mut history = TunnelHistory::default();
+        history.recent.extend([
+            check(NOW - 26 * HOUR + 10, true),
+            check(NOW - 25 * HOUR + 5, true),
+            check(NOW - 25 * HOUR + 1800, false),
+            check(NOW - HOUR, true),
+        ]);
+        history.push(check(NOW, false), NOW);
+        // 24 小时内的保留每次检测
+        let recent: Vec<i64> = history.recent.iter().map(|r| r.at).collect();
+        assert_eq!(recent, vec![NOW - HOUR, NOW]);
+        // 更早的按所在小时合并
+        assert_eq!(history.hourly.len(), 2);
+        let merged = history.hourly[&(NOW - 25 * HOUR)];
+        assert_eq!((merged.checks, merged.up, merged.latency_count), (2, 1, 1));
+        assert_eq!(merged.latency_total_ms, 10.0);
+        assert_eq!(history.hourly[&(NOW - 26 * HOUR)].checks, 1);
+    }
+
+    #[test]
+    fn compact_drops_after_seven_days() {
+        let mut history = TunnelHistory::default();
+        history.hourly.insert(NOW - 8 * 24 * HOUR, summary(1, 1));
+        history.hourly.insert(NOW - 7 * 24 * HOUR, summary(1, 1));
+        history.hourly.insert(NOW - 6 * 24 * HOUR, summary(1, 0));
+        history
+            .recent
+            .push_back(check(NOW - 7 * 24 * HOUR - HOUR, true));
+        history.compact(NOW);
+        assert!(history.recent.is_empty());
+        let kept: Vec<i64> = history.hourly.keys().copied().collect();
+        assert_eq!(kept, vec![NOW - 7 * 24 * HOUR, NOW - 6 * 24 * HOUR]);
+        // 全部超过保留时长后为空，保存时会删除该隧道
+        history.compact(NOW + 7 * 24 * HOUR);
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn uptime_combines_recent_and_hourly() {
+        let mut history = TunnelHistory::default();
+        assert_eq!(history.uptime(NOW - DETAIL_SECS), None);
+        history.hourly.insert(NOW - 48 * HOUR, summary(4, 1));
+        history.recent.extend([
+            check(NOW - 2 * HOUR, true),
+            check(NOW - HOUR, true),
+            check(NOW, false),
+        ]);
+        // 24 小时内 3 次检测成功 2 次
+        assert_eq!(history.uptime(NOW - DETAIL_SECS), Some(66.7));
+        // 7 天内 7 次检测成功 3 次
+        assert_eq!(history.uptime(NOW - HISTORY_SECS), Some(42.9));
+        // 起始时间落在某个小时中间时包含该小时的合计
+        assert_eq!(history.uptime(NOW - 48 * HOUR + 1800), Some(42.9));
+        assert_eq!(history.uptime(NOW - 47 * HOUR), Some(66.7));
+        assert_eq!(history.uptime(NOW + 1), None);
+    }
+
+    #[test]
+    fn state_changes_after_threshold() {
+        let mut health = TunnelHealth::new("1".to_string(), NOW);
+        // 首次检测成功：未知 → 可用
+        assert_eq!(
+            health.update_state(true, 2, NOW),
+            Some(HealthState::Unknown)
+        );
+        assert_eq!(health.state, HealthState::Up);
+        // 失败次数未达到阈值时保持原状态
+        assert_eq!(health.update_state(false, 2, NOW + 60), None);
+        assert_eq!(
+            (health.state, health.consecutive_failures),
+            (HealthState::Up, 1)
+        );
+        // 连续失败达到阈值判定为不可用
+        assert_eq!(
+            health.update_state(false, 2, NOW + 120),
+            Some(HealthState::Up)
+        );
+        assert_eq!((health.state, health.since), (HealthState::Down, NOW + 120));
+        // 持续不可用不再重复报告
+        assert_eq!(health.update_state(false, 2, NOW + 180), None);
+        assert_eq!(health.since, NOW + 120);
+        assert_eq!(health.last_checked, Some(NOW + 180));
+        // 一次成功即恢复
+        assert_eq!(
+            health.update_state(true, 2, NOW + 240),
+            Some(HealthState::Down)
+        );
+        assert_eq!(health.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn failure_resets_on_success() {
+        let mut health = TunnelHealth::new("1".to_string(), NOW);
+        health.update_state(true, 3, NOW);
+        health.update_state(false, 3, NOW + 60);
+        health.update_state(false, 3, NOW + 120);
+        // 中间一次成功，重新计算连续失败次数
+        assert_eq!(health.update_state(true, 3, NOW + 180), None);
+        assert_eq!(health.update_state(false, 3, NOW + 240), None);
+        assert_eq!(health.update_state(false, 3, NOW + 300), None);
+        assert_eq!(health.state, HealthState::Up);
+        assert_eq!(
+            health.update_state(false, 3, NOW + 360),
+            Some(HealthState::Up)
+        );
+    }
+
+    #[test]
+    fn zero_threshold_counts_as_one() {
+        let mut health = TunnelHealth::new("1".to_string(), NOW);
+        assert_eq!(
+            health.update_state(false, 0, NOW),
+            Some(HealthState::Unknown)
+        );
+        assert_eq!(health.state, HealthState::Down);
+    }
+}
@@ -0,0 +1,188 @@
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::command;
+use tokio::sync::Semaphore;
+
+use crate::openfrp_api::{ApiError, Node, OpenFrpClient};
+use crate::ping::{self, PingOptions, PingStats};
+use crate::tunnels::ProxyType;
+
+// 节点推荐：筛选当前用户可用的节点，并发测试连接延迟与丢包，
+// 结合带宽与在线倍率打分，返回排序后的节点及评分依据
+
+const PROBE_ATTEMPTS: u32 = 4;
+const PROBE_TIMEOUT_MS: u64 = 2000;
+// 同时测试的节点数
+const MAX_CONCURRENT_PROBES: usize = 16;
+
+// 评分权重，合计为 100
+const WEIGHT_LATENCY: f64 = 45.0;
+const WEIGHT_LOSS: f64 = 25.0;
+const WEIGHT_BANDWIDTH: f64 = 20.0;
+const WEIGHT_LOAD: f64 = 10.0;
+// 延迟低于该值得满分，高于上限不得分
+const LATENCY_BEST_MS: f64 = 30.0;
+const LATENCY_WORST_MS: f64 = 300.0;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct NodeRecommendation {
+    pub node_id: i64,
+    pub name: String,
+    pub classify: i64,
+    // 0-100，越高越推荐
+    pub score: f64,
+    pub probe: PingStats,
+    // 实际可用带宽（Mbps），已乘以带宽倍率
+    pub bandwidth: f64,
+    pub reasons: Vec<String>,
+}
+
+// 节点端口，无权查询时为提示文字
+pub fn node_port(node: &Node) -> Option<u16> {
+    match &node.port {
+        serde_json::Value::Number(n) => n.as_u64().and_then(|p| u16::try_from(p).ok()),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn allows_group(node: &Node, group: &str) -> bool {
+    node.group.split(';').any(|g| g.trim() == group)
+}
+
+// 测试节点的全部地址，取表现最好的地址
+async fn probe(host: String, port: u16) -> PingStats {
+    let options = PingOptions {
+        count: PROBE_ATTEMPTS,
+        timeout_ms: PROBE_TIMEOUT_MS,
+        ..Default::default()
+    };
+    let report = ping::ping(&host, port, &options).await;
+    match report.best() {
+        Some(best) => best.stats.clone(),
+        None => PingStats {
+            attempts: PROBE_ATTEMPTS,
+            loss_percent: 100.0,
+            ..Default::default()
+        },
+    }
+}
+
+fn score(node: &Node, probe: PingStats, max_bandwidth: f64) -> NodeRecommendation {
+    let bandwidth = node.bandwidth as f64 * node.bandwidth_magnification.max(0.0);
+    let mut reasons = Vec::new();
+
+    let loss = probe.loss_percent / 100.0;
+    let score = match probe.avg_ms {
+        None => {
+            reasons.push("连接测试全部失败，节点可能不可达".to_string());
+            0.0
+        }
+        Some(latency) => {
+            let latency_score = ((LATENCY_WORST_MS - latency)
+                / (LATENCY_WORST_MS - LATENCY_BEST_MS))
+                .clamp(0.0, 1.0);
+            reasons.push(format!("平均延迟 {:.0}ms", latency));
+            if let Some(jitter) = probe.jitter_ms {
+                reasons.push(format!("抖动 {:.1}ms", jitter));
+            }
+            if loss > 0.0 {
+                reasons.push(format!("丢包 {:.0}%", probe.loss_percent));
+            } else {
+                reasons.push("无丢包".to_string());
+            }
+
+            let bandwidth_score = if max_bandwidth > 0.0 {
+                bandwidth / max_bandwidth
+            } else {
+                0.0
+            };
+            reasons.push(format!(
+                "带宽 {}Mbps × {}",
+                node.bandwidth, node.bandwidth_magnification
+            ));
+
+            // 在线倍率越低说明节点越拥挤
+            let load_score = node.max_online_magnification.clamp(0.0, 1.0);
+            if node.max_online_magnification < 1.0 {
+                reasons.push(format!(
+                    "在线倍率 {}，节点较拥挤",
+                    node.max_online_magnification
+                ));
+            }
+
+            latency_score * WEIGHT_LATENCY
+                + (1.0 - loss) * WEIGHT_LOSS
+                + bandwidth_score * WEIGHT_BANDWIDTH
+                + load_score * WEIGHT_LOAD
+        }
+    };
+
+    NodeRecommendation {
+        node_id: node.id,
+        name: node.name.clone(),
+        classify: node.classify,
+        score: (score * 10.0).round() / 10.0,
+        probe,
+        bandwidth,
+        reasons,
+    }
+}
+
+// needs_realname 为 None 时按当前用户是否已实名筛选；classify 限定区域；limit 限制返回数量
+#[command]
+pub async fn recommend_nodes(
+    proxy_type: ProxyType,
+    needs_realname: Option<bool>,
+    classify: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<NodeRecommendation>, ApiError> {
+    let client = OpenFrpClient::new()?;
+    let user = client.user_info().await?.data;
+    let nodes = client.node_list().await?.data.list;
+    let allow_realname = needs_realname.unwrap_or(user.realname);
+
+    let candidates: Vec<(Node, u16)> = nodes
+        .into_iter()
+        .filter(|n| n.status == 200 && !n.fully_loaded)
+        .filter(|n| allows_group(n, &user.group))
+        .filter(|n| proxy_type.supported_by(&n.protocol_support))
+        .filter(|n| allow_realname || !n.need_realname)
+        .filter(|n| classify.is_none() || classify == Some(n.classify))
+        .filter_map(|n| node_port(&n).map(|port| (n, port)))
+        .collect();
+
+    // 并发测试，限制同时进行的连接数
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_PROBES));
+    let handles: Vec<_> = candidates
+        .iter()
+        .map(|(node, port)| {
+            let semaphore = semaphore.clone();
+            let host = node.hostname.clone();
+            let port = *port;
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                probe(host, port).await
+            })
+        })
+        .collect();
+    let mut probes = Vec::new();
+    for handle in handles {
+        probes.push(handle.await.unwrap_or_default());
+    }
+
+    let max_bandwidth = candidates
+        .iter()
+        .map(|(n, _)| n.bandwidth as f64 * n.bandwidth_magnification.max(0.0))
+        .fold(0.0, f64::max);
+    let mut ranked: Vec<NodeRecommendation> = candidates
+        .iter()
+        .zip(probes)
+        .map(|((node, _), probe)| score(node, probe, max_bandwidth))
+        .collect();
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    if let Some(limit) = limit {
+        ranked.truncate(limit);
+    }
+    Ok(ranked)
+}
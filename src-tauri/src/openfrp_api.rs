@@ -0,0 +1,584 @@
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::command;
+
+use crate::{accounts, api_cache, auth_refresh, credentials, endpoints, http};
+
+// OpenFrp API 的类型化客户端，接口说明见仓库根目录的 OFAPI.md
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ApiError {
+    // 本地没有保存 Authorization
+    NotLoggedIn,
+    // Authorization 无效或已过期
+    Unauthorized { message: String },
+    Network { message: String },
+    Timeout { message: String },
+    // 请求过于频繁，retry_after 秒后可以重试
+    RateLimited { message: String, retry_after: u64 },
+    Http { status: u16, message: String },
+    Parse { message: String },
+    // 接口返回 flag 为 false
+    Api { message: String },
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::NotLoggedIn => write!(f, "未登录，请先登录"),
+            ApiError::Unauthorized { message } => write!(f, "登录已失效: {}", message),
+            ApiError::Network { message } => write!(f, "网络请求失败: {}", message),
+            ApiError::Timeout { message } => write!(f, "请求超时: {}", message),
+            ApiError::RateLimited { message, .. } => write!(f, "{}", message),
+            ApiError::Http { status, message } => write!(f, "服务器返回 {}: {}", status, message),
+            ApiError::Parse { message } => write!(f, "解析响应失败: {}", message),
+            ApiError::Api { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            ApiError::Timeout {
+                message: error.to_string(),
+            }
+        } else {
+            ApiError::Network {
+                message: error.to_string(),
+            }
+        }
+    }
+}
+
+impl From<http::Error> for ApiError {
+    fn from(error: http::Error) -> Self {
+        match error {
+            http::Error::RateLimited { retry_after } => ApiError::RateLimited {
+                message: error.to_string(),
+                retry_after: retry_after.as_secs(),
+            },
+            http::Error::Request(e) => e.into(),
+        }
+    }
+}
+
+impl ApiError {
+    // 接口暂时不可用（网络错误、超时、限流或服务器错误），可以使用缓存数据
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            ApiError::Network { .. } | ApiError::Timeout { .. } | ApiError::RateLimited { .. } => {
+                true
+            }
+            ApiError::Http { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl From<ApiError> for String {
+    fn from(error: ApiError) -> Self {
+        error.to_string()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UserInfo {
+    pub id: i64,
+    pub username: String,
+    pub email: String,
+    // 用户密钥，启动 frpc 时使用
+    pub token: String,
+    pub realname: bool,
+    pub reg_time: String,
+    // 用户组标识，如 normal
+    pub group: String,
+    pub friendly_group: String,
+    // 可用隧道总数与已用隧道数
+    pub proxies: i64,
+    pub used: i64,
+    // 带宽（Kbps）
+    pub in_limit: i64,
+    pub out_limit: i64,
+    // 剩余流量（MiB）
+    pub traffic: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Proxy {
+    pub id: i64,
+    pub uid: i64,
+    // 节点 ID
+    pub nid: i64,
+    pub proxy_name: String,
+    pub proxy_type: String,
+    pub local_ip: String,
+    pub local_port: i64,
+    pub remote_port: Option<i64>,
+    pub connect_address: String,
+    pub friendly_node: String,
+    // 绑定的域名，为 JSON 数组字符串，仅 HTTP/S 隧道
+    pub domain: Option<String>,
+    pub custom: Option<String>,
+    pub auto_tls: Option<String>,
+    pub force_https: bool,
+    pub proxy_protocol_version: bool,
+    pub use_encryption: bool,
+    pub use_compression: bool,
+    // 是否启用
+    pub status: bool,
+    pub online: bool,
+    pub last_login: Option<i64>,
+    pub last_update: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ProtocolSupport {
+    pub tcp: bool,
+    pub udp: bool,
+    pub xtcp: bool,
+    pub stcp: bool,
+    pub http: bool,
+    pub https: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Node {
+    pub id: i64,
+    pub name: String,
+    // 无权查询时为提示文字
+    pub hostname: String,
+    // 节点端口，无权查询时为提示文字
+    pub port: serde_json::Value,
+    pub description: String,
+    pub comments: String,
+    // 允许使用的用户组，以 ; 分隔
+    pub group: String,
+    // 1 中国大陆，2 港澳台，3 海外
+    pub classify: i64,
+    // 200 为正常
+    pub status: i64,
+    pub bandwidth: i64,
+    pub bandwidth_magnification: f64,
+    pub max_online_magnification: f64,
+    pub need_realname: bool,
+    pub enable_default_tls: bool,
+    pub fully_loaded: bool,
+    pub allow_ec: bool,
+    pub unitcost_ec: f64,
+    pub protocol_support: ProtocolSupport,
+    // 远程端口范围，如 "(50000,60000)"，为空则不限制
+    pub allow_port: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Page<T> {
+    pub total: i64,
+    pub list: Vec<T>,
+}
+
+// 新建、编辑隧道提交的内容，字段名与接口一致
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ProxyConfig {
+    // 编辑隧道时必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_id: Option<i64>,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub proxy_type: String,
+    pub node_id: i64,
+    pub local_addr: String,
+    pub local_port: String,
+    pub remote_port: Option<i64>,
+    #[serde(default)]
+    pub domain_bind: String,
+    #[serde(rename = "autoTls", default)]
+    pub auto_tls: String,
+    #[serde(rename = "forceHttps", default)]
+    pub force_https: bool,
+    #[serde(rename = "proxyProtocolVersion", default)]
+    pub proxy_protocol_version: bool,
+    #[serde(rename = "dataEncrypt", default)]
+    pub data_encrypt: bool,
+    #[serde(rename = "dataGzip", default)]
+    pub data_gzip: bool,
+    #[serde(default)]
+    pub custom: String,
+}
+
+// 通过用户密钥获取的隧道列表，按节点分组
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct NodeProxies {
+    pub node: String,
+    pub proxies: Vec<SimpleProxy>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SimpleProxy {
+    pub id: i64,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub proxy_type: String,
+    // 连接地址
+    pub remote: String,
+    pub local: String,
+}
+
+// 接口统一的返回格式
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    flag: bool,
+    #[serde(default)]
+    msg: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+// 可能来自本地缓存的数据，接口不可用时 stale 为 true
+#[derive(Serialize, Clone, Debug)]
+pub struct Fetched<T> {
+    #[serde(flatten)]
+    pub data: T,
+    pub stale: bool,
+    // 缓存保存时间（Unix 时间戳，秒）
+    pub cached_at: Option<i64>,
+}
+
+pub struct OpenFrpClient {
+    client: reqwest::Client,
+    // 创建时的当前账户，Authorization 与用户密钥都按该账户读写
+    account: String,
+    authorization: Option<String>,
+}
+
+impl OpenFrpClient {
+    // 使用安全存储中当前账户的 Authorization
+    pub fn new() -> Result<Self, ApiError> {
+        let client = http::client().map_err(|message| ApiError::Network { message })?;
+        let account = accounts::active_account();
+        Ok(OpenFrpClient {
+            client,
+            authorization: credentials::get_for(&account, credentials::AUTHORIZATION),
+            account,
+        })
+    }
+
+    // 使用指定的 Authorization，不读取安全存储
+    #[cfg(feature = "selftest")]
+    pub fn with_authorization(authorization: &str) -> Result<Self, ApiError> {
+        let client = http::client().map_err(|message| ApiError::Network { message })?;
+        Ok(OpenFrpClient {
+            client,
+            account: accounts::active_account(),
+            authorization: Some(authorization.to_string()),
+        })
+    }
+
+    // 依次尝试当前环境的各个 API 地址，网络错误时切换到下一个，
+    // 非幂等请求只在连接失败时切换
+    async fn send(
+        &self,
+        method: &Method,
+        build: impl Fn(&str) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, ApiError> {
+        let mut last_error = ApiError::Network {
+            message: "未配置 API 地址".to_string(),
+        };
+        for host in endpoints::api_hosts() {
+            match http::send(build(&host)).await {
+                Ok(response) => {
+                    endpoints::mark_healthy(&host);
+                    return Ok(response);
+                }
+                // 各地址是同一服务，限流时不再切换地址
+                Err(e @ http::Error::RateLimited { .. }) => return Err(e.into()),
+                Err(e) => {
+                    endpoints::mark_failed(&host);
+                    if !e.can_fail_over(method) {
+                        return Err(e.into());
+                    }
+                    last_error = e.into();
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    // 登录失效时换用安全存储中更新的 Authorization 重试一次，仍然失效时发起重新授权
+    async fn call(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<Envelope, ApiError> {
+        let authorization = self.authorization.as_deref().ok_or(ApiError::NotLoggedIn)?;
+        let result = self
+            .call_with(method.clone(), path, body.clone(), authorization)
+            .await;
+        let result = match result {
+            Err(ApiError::Unauthorized { message }) => {
+                match auth_refresh::refreshed_authorization(&self.account, Some(authorization)) {
+                    Some(fresh) => self.call_with(method, path, body, &fresh).await,
+                    None => Err(ApiError::Unauthorized { message }),
+                }
+            }
+            result => result,
+        };
+        if let Err(ApiError::Unauthorized { message }) = &result {
+            auth_refresh::handle_expired(message);
+        }
+        if let Some(kind) = api_cache::CacheKind::modified_by(path) {
+            api_cache::invalidate(kind);
+        }
+        result
+    }
+
+    async fn call_with(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+        authorization: &str,
+    ) -> Result<Envelope, ApiError> {
+        let response = self
+            .send(&method, |host| {
+                let mut request = self
+                    .client
+                    .request(method.clone(), format!("{}/frp/api/{}", host, path))
+                    .header("Authorization", authorization);
+                if let Some(body) = &body {
+                    request = request.json(body);
+                }
+                request
+            })
+            .await?;
+
+        // 服务端可能下发新的 Authorization，同步到安全存储
+        if let Some(auth) = response
+            .headers()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty())
+        {
+            let _ = credentials::set_for(&self.account, credentials::AUTHORIZATION, auth);
+        }
+
+        let status = response.status();
+        let text = response.text().await?;
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Err(ApiError::Unauthorized { message: text });
+        }
+        if !status.is_success() {
+            return Err(ApiError::Http {
+                status: status.as_u16(),
+                message: text,
+            });
+        }
+
+        let envelope: Envelope = serde_json::from_str(&text).map_err(|e| ApiError::Parse {
+            message: e.to_string(),
+        })?;
+        if !envelope.flag && auth_refresh::is_expired_message(&envelope.msg) {
+            return Err(ApiError::Unauthorized {
+                message: envelope.msg,
+            });
+        }
+        if !envelope.flag {
+            return Err(ApiError::Api {
+                message: envelope.msg,
+            });
+        }
+        Ok(envelope)
+    }
+
+    // 请求成功时更新本地缓存，接口不可用时返回缓存数据
+    async fn call_cached<T: DeserializeOwned>(&self, path: &str) -> Result<Fetched<T>, ApiError> {
+        let decode = |data: serde_json::Value| {
+            serde_json::from_value(data).map_err(|e| ApiError::Parse {
+                message: e.to_string(),
+            })
+        };
+        let kind = api_cache::CacheKind::from_path(path);
+
+        match self.call(Method::POST, path, None).await {
+            Ok(envelope) => {
+                if let Some(kind) = kind {
+                    if let Ok(body) = serde_json::to_value(&envelope) {
+                        api_cache::store(kind, &body);
+                    }
+                }
+                Ok(Fetched {
+                    data: decode(envelope.data)?,
+                    stale: false,
+                    cached_at: None,
+                })
+            }
+            Err(e) if e.is_unavailable() => {
+                let entry = kind.and_then(api_cache::load).ok_or(e)?;
+                Ok(Fetched {
+                    data: decode(entry.body["data"].clone())?,
+                    stale: true,
+                    cached_at: Some(entry.saved_at),
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn user_info(&self) -> Result<Fetched<UserInfo>, ApiError> {
+        let info: Fetched<UserInfo> = self.call_cached("getUserInfo").await?;
+        // 保存用户密钥，启动隧道时无需再由前端传入；请求期间切换了账户时不保存
+        if !info.data.token.is_empty() && self.account == accounts::active_account() {
+            let _ = credentials::set_for(&self.account, credentials::TOKEN, &info.data.token);
+        }
+        Ok(info)
+    }
+
+    pub async fn user_proxies(&self) -> Result<Fetched<Page<Proxy>>, ApiError> {
+        self.call_cached("getUserProxies").await
+    }
+
+    // 本进程在 max_age 内获取过隧道列表时直接使用，供启动检查、监控等后台任务共享一次请求
+    pub async fn user_proxies_within(
+        &self,
+        max_age: Duration,
+    ) -> Result<Fetched<Page<Proxy>>, ApiError> {
+        let cached =
+            api_cache::load_fresh(api_cache::CacheKind::UserProxies, max_age).and_then(|entry| {
+                let data = serde_json::from_value(entry.body["data"].clone()).ok()?;
+                Some(Fetched {
+                    data,
+                    stale: false,
+                    cached_at: Some(entry.saved_at),
+                })
+            });
+        match cached {
+            Some(fetched) => Ok(fetched),
+            None => self.user_proxies().await,
+        }
+    }
+
+    pub async fn node_list(&self) -> Result<Fetched<Page<Node>>, ApiError> {
+        self.call_cached("getNodeList").await
+    }
+
+    // 新建、编辑、删除隧道返回服务端的提示信息
+    pub async fn new_proxy(&self, config: &ProxyConfig) -> Result<String, ApiError> {
+        let body = serde_json::to_value(config).map_err(|e| ApiError::Parse {
+            message: e.to_string(),
+        })?;
+        Ok(self.call(Method::POST, "newProxy", Some(body)).await?.msg)
+    }
+
+    pub async fn edit_proxy(&self, config: &ProxyConfig) -> Result<String, ApiError> {
+        if config.proxy_id.is_none() {
+            return Err(ApiError::Api {
+                message: "编辑隧道需要提供 proxy_id".to_string(),
+            });
+        }
+        let body = serde_json::to_value(config).map_err(|e| ApiError::Parse {
+            message: e.to_string(),
+        })?;
+        Ok(self.call(Method::POST, "editProxy", Some(body)).await?.msg)
+    }
+
+    pub async fn remove_proxy(&self, proxy_id: i64) -> Result<String, ApiError> {
+        let body = serde_json::json!({ "proxy_id": proxy_id });
+        Ok(self
+            .call(Method::POST, "removeProxy", Some(body))
+            .await?
+            .msg)
+    }
+
+    // 签到需要前端完成人机验证后把验证参数一并提交
+    pub async fn user_sign(&self, params: serde_json::Value) -> Result<String, ApiError> {
+        let envelope = self.call(Method::POST, "userSign", Some(params)).await?;
+        Ok(envelope.data.as_str().unwrap_or(&envelope.msg).to_string())
+    }
+
+    // 仅凭用户密钥获取隧道列表，无需 Authorization
+    pub async fn all_proxies(&self, token: &str) -> Result<Vec<NodeProxies>, ApiError> {
+        let response = self
+            .send(&Method::GET, |host| {
+                self.client
+                    .get(format!("{}/api", host))
+                    .query(&[("action", "getallproxies"), ("user", token)])
+            })
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ApiError::Http {
+                status: status.as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+        let body: serde_json::Value = response.json().await.map_err(|e| ApiError::Parse {
+            message: e.to_string(),
+        })?;
+        if !body["success"].as_bool().unwrap_or(false) {
+            return Err(ApiError::Api {
+                message: body["message"].as_str().unwrap_or("未知错误").to_string(),
+            });
+        }
+        serde_json::from_value(body["data"].clone()).map_err(|e| ApiError::Parse {
+            message: e.to_string(),
+        })
+    }
+}
+
+#[command]
+pub async fn get_user_info() -> Result<Fetched<UserInfo>, ApiError> {
+    OpenFrpClient::new()?.user_info().await
+}
+
+#[command]
+pub async fn get_user_proxies() -> Result<Fetched<Page<Proxy>>, ApiError> {
+    OpenFrpClient::new()?.user_proxies().await
+}
+
+#[command]
+pub async fn get_node_list() -> Result<Fetched<Page<Node>>, ApiError> {
+    OpenFrpClient::new()?.node_list().await
+}
+
+#[command]
+pub async fn new_proxy(config: ProxyConfig) -> Result<String, ApiError> {
+    OpenFrpClient::new()?.new_proxy(&config).await
+}
+
+#[command]
+pub async fn edit_proxy(config: ProxyConfig) -> Result<String, ApiError> {
+    OpenFrpClient::new()?.edit_proxy(&config).await
+}
+
+#[command]
+pub async fn remove_proxy(proxy_id: i64) -> Result<String, ApiError> {
+    OpenFrpClient::new()?.remove_proxy(proxy_id).await
+}
+
+#[command]
+pub async fn user_sign(params: Option<serde_json::Value>) -> Result<String, ApiError> {
+    OpenFrpClient::new()?
+        .user_sign(params.unwrap_or_else(|| serde_json::json!({})))
+        .await
+}
+
+// 未传入用户密钥时使用安全存储中的密钥
+#[command]
+pub async fn get_all_proxies(token: Option<String>) -> Result<Vec<NodeProxies>, ApiError> {
+    let token = token
+        .or_else(|| credentials::get(credentials::TOKEN))
+        .ok_or(ApiError::NotLoggedIn)?;
+    OpenFrpClient::new()?.all_proxies(&token).await
+}
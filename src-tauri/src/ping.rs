@@ -0,0 +1,372 @@
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::command;
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+
+// TCP 连接测试：解析目标的全部地址并分别测试，统计延迟分布、抖动与丢包，
+// DNS 解析耗时单独统计。连接全部在 tokio 上异步进行，不会阻塞运行时线程
+
+// 批量测试时同时进行的目标数
+const MAX_CONCURRENT_TARGETS: usize = 16;
+const MAX_COUNT: u32 = 50;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    #[default]
+    Any,
+    Ipv4,
+    Ipv6,
+}
+
+impl AddressFamily {
+    fn matches(self, addr: &SocketAddr) -> bool {
+        match self {
+            AddressFamily::Any => true,
+            AddressFamily::Ipv4 => addr.is_ipv4(),
+            AddressFamily::Ipv6 => addr.is_ipv6(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PingOptions {
+    // 每个地址的连接次数
+    pub count: u32,
+    // 单次连接的超时
+    pub timeout_ms: u64,
+    // 两次连接之间的间隔
+    pub interval_ms: u64,
+    pub family: AddressFamily,
+}
+
+impl Default for PingOptions {
+    fn default() -> Self {
+        PingOptions {
+            count: 5,
+            timeout_ms: 2000,
+            interval_ms: 100,
+            family: AddressFamily::Any,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PingTarget {
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct PingStats {
+    pub attempts: u32,
+    pub successes: u32,
+    pub loss_percent: f64,
+    // 以下延迟只统计成功的连接，全部失败时为 None
+    pub min_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    // 相邻两次成功连接延迟之差的平均值
+    pub jitter_ms: Option<f64>,
+}
+
+impl PingStats {
+    fn from_samples(attempts: u32, samples: &[f64]) -> Self {
+        let successes = samples.len() as u32;
+        let loss_percent = if attempts == 0 {
+            0.0
+        } else {
+            (attempts - successes) as f64 * 100.0 / attempts as f64
+        };
+        if samples.is_empty() {
+            return PingStats {
+                attempts,
+                successes,
+                loss_percent,
+                ..Default::default()
+            };
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        // 最近秩法计算百分位
+        let percentile = |p: f64| {
+            let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        let jitter = (samples.len() > 1).then(|| {
+            samples.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>()
+                / (samples.len() - 1) as f64
+        });
+        PingStats {
+            attempts,
+            successes,
+            loss_percent,
+            min_ms: sorted.first().copied(),
+            max_ms: sorted.last().copied(),
+            avg_ms: Some(samples.iter().sum::<f64>() / samples.len() as f64),
+            p50_ms: Some(percentile(50.0)),
+            p95_ms: Some(percentile(95.0)),
+            jitter_ms: jitter,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AddressResult {
+    pub address: String,
+    pub ipv6: bool,
+    pub stats: PingStats,
+    // 最后一次连接失败的原因
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PingReport {
+    pub host: String,
+    pub port: u16,
+    pub success: bool,
+    pub dns_ms: f64,
+    pub addresses: Vec<AddressResult>,
+    // 表现最好的地址，按丢包率、平均延迟排序
+    pub best_address: Option<String>,
+    // 最好地址的平均延迟
+    pub latency_ms: Option<f64>,
+    pub message: String,
+}
+
+impl PingReport {
+    // 表现最好的地址的统计
+    pub fn best(&self) -> Option<&AddressResult> {
+        self.addresses
+            .iter()
+            .filter(|a| a.stats.successes > 0)
+            .min_by(|a, b| {
+                a.stats.loss_percent.total_cmp(&b.stats.loss_percent).then(
+                    a.stats
+                        .avg_ms
+                        .unwrap_or(f64::MAX)
+                        .total_cmp(&b.stats.avg_ms.unwrap_or(f64::MAX)),
+                )
+            })
+    }
+}
+
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+async fn ping_address(addr: SocketAddr, options: PingOptions) -> AddressResult {
+    let timeout = Duration::from_millis(options.timeout_ms.max(1));
+    let mut samples = Vec::new();
+    let mut error = None;
+    for attempt in 0..options.count {
+        if attempt > 0 {
+            tokio::time::sleep(Duration::from_millis(options.interval_ms)).await;
+        }
+        let start = Instant::now();
+        match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => samples.push(elapsed_ms(start)),
+            Ok(Err(e)) => error = Some(e.to_string()),
+            Err(_) => error = Some(format!("连接超时（{}ms）", options.timeout_ms)),
+        }
+    }
+    AddressResult {
+        address: addr.to_string(),
+        ipv6: addr.is_ipv6(),
+        stats: PingStats::from_samples(options.count, &samples),
+        error,
+    }
+}
+
+fn failed(host: &str, port: u16, dns_ms: f64, message: String) -> PingReport {
+    PingReport {
+        host: host.to_string(),
+        port,
+        success: false,
+        dns_ms,
+        addresses: Vec::new(),
+        best_address: None,
+        latency_ms: None,
+        message,
+    }
+}
+
+// 测试单个目标的全部地址，各地址并发测试
+pub async fn ping(host: &str, port: u16, options: &PingOptions) -> PingReport {
+    let mut options = options.clone();
+    options.count = options.count.clamp(1, MAX_COUNT);
+
+    let dns_start = Instant::now();
+    let resolved = tokio::time::timeout(
+        Duration::from_millis(options.timeout_ms.max(1)),
+        tokio::net::lookup_host((host, port)),
+    )
+    .await;
+    let dns_ms = elapsed_ms(dns_start);
+    let mut addrs: Vec<SocketAddr> = match resolved {
+        Ok(Ok(addrs)) => addrs.filter(|a| options.family.matches(a)).collect(),
+        Ok(Err(e)) => return failed(host, port, dns_ms, format!("地址解析失败: {}", e)),
+        Err(_) => return failed(host, port, dns_ms, "地址解析超时".to_string()),
+    };
+    let mut seen = std::collections::HashSet::new();
+    addrs.retain(|addr| seen.insert(*addr));
+    if addrs.is_empty() {
+        return failed(host, port, dns_ms, "未能解析到符合要求的地址".to_string());
+    }
+
+    let handles: Vec<_> = addrs
+        .into_iter()
+        .map(|addr| tokio::spawn(ping_address(addr, options.clone())))
+        .collect();
+    let mut addresses = Vec::new();
+    for handle in handles {
+        if let Ok(result) = handle.await {
+            addresses.push(result);
+        }
+    }
+
+    let mut report = PingReport {
+        host: host.to_string(),
+        port,
+        success: false,
+        dns_ms,
+        addresses,
+        best_address: None,
+        latency_ms: None,
+        message: String::new(),
+    };
+    match report.best().cloned() {
+        Some(best) => {
+            report.success = true;
+            report.best_address = Some(best.address.clone());
+            report.latency_ms = best.stats.avg_ms;
+            report.message = format!(
+                "测试完成，{} 平均延迟 {:.0}ms（p95 {:.0}ms，抖动 {:.1}ms，丢包 {:.0}%）",
+                best.address,
+                best.stats.avg_ms.unwrap_or_default(),
+                best.stats.p95_ms.unwrap_or_default(),
+                best.stats.jitter_ms.unwrap_or_default(),
+                best.stats.loss_percent
+            );
+        }
+        None => {
+            report.message = format!(
+                "所有地址的连接测试均失败（共 {} 个地址）",
+                report.addresses.len()
+            );
+        }
+    }
+    report
+}
+
+// 并发测试多个目标，结果与 targets 顺序一致
+pub async fn ping_many(targets: Vec<PingTarget>, options: PingOptions) -> Vec<PingReport> {
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_TARGETS));
+    let handles: Vec<_> = targets
+        .into_iter()
+        .map(|target| {
+            let semaphore = semaphore.clone();
+            let options = options.clone();
+            let handle = tokio::spawn({
+                let target = target.clone();
+                async move {
+                    let _permit = semaphore.acquire_owned().await;
+                    ping(&target.host, target.port, &options).await
+                }
+            });
+            (target, handle)
+        })
+        .collect();
+    let mut reports = Vec::new();
+    for (target, handle) in handles {
+        reports.push(handle.await.unwrap_or_else(|e| {
+            failed(
+                &target.host,
+                target.port,
+                0.0,
+                format!("测试任务异常: {}", e),
+            )
+        }));
+    }
+    reports
+}
+
+#[command]
+pub async fn tcp_ping(
+    host: String,
+    port: u16,
+    options: Option<PingOptions>,
+) -> Result<PingReport, String> {
+    Ok(ping(&host, port, &options.unwrap_or_default()).await)
+}
+
+#[command]
+pub async fn tcp_ping_batch(
+    targets: Vec<PingTarget>,
+    options: Option<PingOptions>,
+) -> Result<Vec<PingReport>, String> {
+    Ok(ping_many(targets, options.unwrap_or_default()).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_without_attempts() {
+        let stats = PingStats::from_samples(0, &[]);
+        assert_eq!(stats.attempts, 0);
+        assert_eq!(stats.successes, 0);
+        assert_eq!(stats.loss_percent, 0.0);
+        assert_eq!(stats.avg_ms, None);
+        assert_eq!(stats.jitter_ms, None);
+    }
+
+    #[test]
+    fn stats_single_sample() {
+        let stats = PingStats::from_samples(1, &[12.5]);
+        assert_eq!(stats.successes, 1);
+        assert_eq!(stats.loss_percent, 0.0);
+        assert_eq!(stats.min_ms, Some(12.5));
+        assert_eq!(stats.max_ms, Some(12.5));
+        assert_eq!(stats.avg_ms, Some(12.5));
+        assert_eq!(stats.p50_ms, Some(12.5));
+        assert_eq!(stats.p95_ms, Some(12.5));
+        // 只有一次成功时没有抖动
+        assert_eq!(stats.jitter_ms, None);
+    }
+
+    #[test]
+    fn stats_all_failed() {
+        let stats = PingStats::from_samples(4, &[]);
+        assert_eq!(stats.attempts, 4);
+        assert_eq!(stats.successes, 0);
+        assert_eq!(stats.loss_percent, 100.0);
+        assert_eq!(stats.min_ms, None);
+        assert_eq!(stats.max_ms, None);
+        assert_eq!(stats.avg_ms, None);
+        assert_eq!(stats.p50_ms, None);
+        assert_eq!(stats.p95_ms, None);
+        assert_eq!(stats.jitter_ms, None);
+    }
+
+    #[test]
+    fn stats_partial_loss() {
+        let stats = PingStats::from_samples(5, &[30.0, 10.0, 20.0, 40.0]);
+        assert_eq!(stats.loss_percent, 20.0);
+        assert_eq!(stats.min_ms, Some(10.0));
+        assert_eq!(stats.max_ms, Some(40.0));
+        assert_eq!(stats.avg_ms, Some(25.0));
+        assert_eq!(stats.p50_ms, Some(20.0));
+        assert_eq!(stats.p95_ms, Some(40.0));
+        // |10-30| + |20-10| + |40-20| = 50，三个间隔
+        assert_eq!(stats.jitter_ms, Some(50.0 / 3.0));
+    }
+}
@@ -0,0 +1,444 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use tauri::command;
+
+use crate::openfrp_api::{OpenFrpClient, Proxy};
+use crate::ports::{self, Listener, Protocol};
+
+// 隧道端口分析：将每条隧道的 localIp:localPort 与本机监听端口对照，
+// 找出没有服务监听、服务只监听回环地址、多条隧道指向同一端口，
+// 以及未加密暴露敏感服务等问题
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    // 本地端口没有服务监听
+    NoListener,
+    // 服务只监听回环地址，隧道却指向局域网地址
+    LoopbackOnly,
+    // 服务监听在其他地址上
+    AddressMismatch,
+    // 多条隧道指向同一个本地端口
+    DuplicateLocalPort,
+    // 敏感服务未加密暴露到公网
+    SensitiveExposed,
+    // 目标为局域网内其他设备，无法检查
+    RemoteTarget,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PortFinding {
+    pub kind: FindingKind,
+    pub severity: Severity,
+    // 涉及的隧道，重复端口时有多条
+    pub tunnel_ids: Vec<i64>,
+    pub tunnel_names: Vec<String>,
+    pub local_addr: String,
+    pub local_port: u16,
+    pub protocol: Protocol,
+    pub message: String,
+    // 该端口上的本机监听
+    pub listeners: Vec<Listener>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PortAnalysis {
+    pub checked_tunnels: usize,
+    // 隧道列表来自缓存
+    pub stale: bool,
+    // 按严重程度从高到低排序
+    pub findings: Vec<PortFinding>,
+}
+
+// 常见的敏感服务端口及严重程度
+const SENSITIVE_PORTS: &[(u16, &str, Severity)] = &[
+    (22, "SSH", Severity::Warning),
+    (3389, "远程桌面 (RDP)", Severity::Warning),
+    (3306, "MySQL", Severity::Warning),
+    (6379, "Redis", Severity::Error),
+];
+
+fn protocol_of(proxy: &Proxy) -> Protocol {
+    if proxy.proxy_type.eq_ignore_ascii_case("udp") {
+        Protocol::Udp
+    } else {
+        Protocol::Tcp
+    }
+}
+
+// 隧道目标地址，localhost 与空地址按 127.0.0.1 处理
+fn target_ip(proxy: &Proxy) -> Option<IpAddr> {
+    match proxy.local_ip.trim() {
+        "" | "localhost" => Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        ip => ip
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse()
+            .ok(),
+    }
+}
+
+// 地址是否属于本机：能绑定说明该地址配置在本机网卡上
+fn is_local_ip(ip: IpAddr) -> bool {
+    ip.is_loopback() || ip.is_unspecified() || UdpSocket::bind((ip, 0)).is_ok()
+}
+
+// 监听是否能接受发往 target 的连接
+fn accepts(listener: &Listener, target: IpAddr) -> bool {
+    if listener.is_unspecified() {
+        // 0.0.0.0 只接受 IPv4，:: 在多数系统上同时接受两者
+        return listener.ipv6 || target.is_ipv4();
+    }
+    match listener.ip() {
+        Some(ip) => ip == target || (ip.is_loopback() && target.is_loopback()),
+        None => false,
+    }
+}
+
+fn finding(
+    kind: FindingKind,
+    severity: Severity,
+    proxy: &Proxy,
+    message: String,
+    listeners: Vec<Listener>,
+) -> PortFinding {
+    PortFinding {
+        kind,
+        severity,
+        tunnel_ids: vec![proxy.id],
+        tunnel_names: vec![proxy.proxy_name.clone()],
+        local_addr: proxy.local_ip.clone(),
+        local_port: proxy.local_port as u16,
+        protocol: protocol_of(proxy),
+        message,
+        listeners,
+    }
+}
+
+// 检查单条隧道的本地服务监听情况，is_local 判断地址是否属于本机
+fn check_listener(
+    proxy: &Proxy,
+    all: &[Listener],
+    is_local: &dyn Fn(IpAddr) -> bool,
+) -> Option<PortFinding> {
+    let protocol = protocol_of(proxy);
+    let port = proxy.local_port as u16;
+    let name = protocol_name(protocol);
+    let Some(target) = target_ip(proxy) else {
+        return Some(finding(
+            FindingKind::RemoteTarget,
+            Severity::Info,
+            proxy,
+            format!("本地地址 {} 不是 IP，无法检查监听情况", proxy.local_ip),
+            Vec::new(),
+        ));
+    };
+    if !is_local(target) {
+        return Some(finding(
+            FindingKind::RemoteTarget,
+            Severity::Info,
+            proxy,
+            format!("{} 是局域网内的其他设备，无法检查其端口", target),
+            Vec::new(),
+        ));
+    }
+
+    let on_port: Vec<Listener> = all
+        .iter()
+        .filter(|l| l.protocol == protocol && l.port == port)
+        .cloned()
+        .collect();
+    if on_port.is_empty() {
+        return Some(finding(
+            FindingKind::NoListener,
+            Severity::Error,
+            proxy,
+            format!("本机 {} 端口 {} 没有服务监听，隧道无法连接", name, port),
+            on_port,
+        ));
+    }
+    if on_port.iter().any(|l| accepts(l, target)) {
+        return None;
+    }
+    if !target.is_loopback() && on_port.iter().all(Listener::is_loopback) {
+        return Some(finding(
+            FindingKind::LoopbackOnly,
+            Severity::Error,
+            proxy,
+            format!(
+                "端口 {} 上的服务只监听 127.0.0.1，请将隧道的本地地址改为 127.0.0.1，或让服务监听 0.0.0.0",
+                port
+            ),
+            on_port,
+        ));
+    }
+    let addresses: Vec<&str> = on_port.iter().map(|l| l.address.as_str()).collect();
+    Some(finding(
+        FindingKind::AddressMismatch,
+        Severity::Error,
+        proxy,
+        format!(
+            "端口 {} 上的服务监听在 {}，不接受发往 {} 的连接",
+            port,
+            addresses.join("、"),
+            target
+        ),
+        on_port,
+    ))
+}
+
+fn protocol_name(protocol: Protocol) -> &'static str {
+    match protocol {
+        Protocol::Tcp => "TCP",
+        Protocol::Udp => "UDP",
+    }
+}
+
+// 公网可直接访问的隧道传输敏感服务且未开启加密
+fn check_sensitive(proxy: &Proxy) -> Option<PortFinding> {
+    let exposed = ["tcp", "udp"]
+        .iter()
+        .any(|t| proxy.proxy_type.eq_ignore_ascii_case(t));
+    if !exposed || proxy.use_encryption {
+        return None;
+    }
+    let (_, service, severity) = SENSITIVE_PORTS
+        .iter()
+        .find(|(port, _, _)| i64::from(*port) == proxy.local_port)?;
+    Some(finding(
+        FindingKind::SensitiveExposed,
+        *severity,
+        proxy,
+        format!(
+            "{} 服务直接暴露在公网且未开启加密，建议开启加密、改用 STCP 或设置强密码",
+            service
+        ),
+        Vec::new(),
+    ))
+}
+
+// 指向同一本地地址和端口的隧道
+fn check_duplicates(proxies: &[Proxy]) -> Vec<PortFinding> {
+    let mut groups: HashMap<(String, u16, Protocol), Vec<&Proxy>> = HashMap::new();
+    for proxy in proxies {
+        // 回环地址视为同一地址，无法解析为 IP 的按主机名比较
+        let addr = match target_ip(proxy) {
+            Some(ip) if ip.is_loopback() => Ipv4Addr::LOCALHOST.to_string(),
+            Some(ip) => ip.to_string(),
+            None => proxy.local_ip.trim().to_lowercase(),
+        };
+        groups
+            .entry((addr, proxy.local_port as u16, protocol_of(proxy)))
+            .or_default()
+            .push(proxy);
+    }
+    let mut findings: Vec<PortFinding> = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|group| {
+            let first = group[0];
+            let names: Vec<String> = group.iter().map(|p| p.proxy_name.clone()).collect();
+            PortFinding {
+                tunnel_ids: group.iter().map(|p| p.id).collect(),
+                message: format!(
+                    "{} 条隧道指向同一个本地端口 {}：{}",
+                    group.len(),
+                    first.local_port,
+                    names.join("、")
+                ),
+                tunnel_names: names,
+                ..finding(
+                    FindingKind::DuplicateLocalPort,
+                    Severity::Warning,
+                    first,
+                    String::new(),
+                    Vec::new(),
+                )
+            }
+        })
+        .collect();
+    findings.sort_by_key(|f| f.local_port);
+    findings
+}
+
+pub fn analyze(
+    proxies: &[Proxy],
+    listeners: &[Listener],
+    is_local: impl Fn(IpAddr) -> bool,
+) -> Vec<PortFinding> {
+    // 端口无效的隧道交给接口检查
+    let proxies: Vec<Proxy> = proxies
+        .iter()
+        .filter(|p| u16::try_from(p.local_port).is_ok_and(|port| port > 0))
+        .cloned()
+        .collect();
+    let mut findings = Vec::new();
+    for proxy in &proxies {
+        findings.extend(check_listener(proxy, listeners, &is_local));
+        findings.extend(check_sensitive(proxy));
+    }
+    findings.extend(check_duplicates(&proxies));
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+    findings
+}
+
+#[command]
+pub async fn analyze_tunnel_ports() -> Result<PortAnalysis, String> {
+    let client = OpenFrpClient::new().map_err(|e| format!("获取隧道列表失败: {}", e))?;
+    let proxies = client
+        .user_proxies()
+        .await
+        .map_err(|e| format!("获取隧道列表失败: {}", e))?;
+    let local = ports::local_ports().await?;
+    let listeners: Vec<Listener> = local.tcp.into_iter().chain(local.udp).collect();
+    Ok(PortAnalysis {
+        checked_tunnels: proxies.data.list.len(),
+        stale: proxies.stale,
+        findings: analyze(&proxies.data.list, &listeners, is_local_ip),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy(id: i64, proxy_type: &str, local_ip: &str, local_port: i64) -> Proxy {
+        Proxy {
+            id,
+            proxy_name: format!("tunnel{}", id),
+            proxy_type: proxy_type.to_string(),
+            local_ip: local_ip.to_string(),
+            local_port,
+            ..Default::default()
+        }
+    }
+
+    fn listener(protocol: Protocol, address: &str, port: u16) -> Listener {
+        Listener {
+            protocol,
+            address: address.to_string(),
+            port,
+            ipv6: address.contains(':'),
+            pid: None,
+            process: None,
+        }
+    }
+
+    fn kinds(findings: &[PortFinding]) -> Vec<FindingKind> {
+        findings.iter().map(|f| f.kind).collect()
+    }
+
+    // 假定本机网卡上只有回环地址和该局域网地址
+    const LAN_IP: &str = "192.168.1.10";
+
+    fn is_local(ip: IpAddr) -> bool {
+        ip.is_loopback() || ip.is_unspecified() || ip.to_string() == LAN_IP
+    }
+
+    #[test]
+    fn no_listener() {
+        let findings = analyze(
+            &[proxy(1, "tcp", "127.0.0.1", 8080)],
+            &[
+                listener(Protocol::Tcp, "0.0.0.0", 8081),
+                listener(Protocol::Udp, "0.0.0.0", 8080),
+            ],
+            is_local,
+        );
+        assert_eq!(kinds(&findings), vec![FindingKind::NoListener]);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(findings[0].tunnel_ids, vec![1]);
+    }
+
+    #[test]
+    fn loopback_only_with_lan_target() {
+        let findings = analyze(
+            &[proxy(1, "tcp", LAN_IP, 8080)],
+            &[listener(Protocol::Tcp, "127.0.0.1", 8080)],
+            is_local,
+        );
+        assert_eq!(kinds(&findings), vec![FindingKind::LoopbackOnly]);
+        assert_eq!(findings[0].listeners.len(), 1);
+
+        // 监听在该局域网地址上时可以连接
+        let findings = analyze(
+            &[proxy(1, "tcp", LAN_IP, 8080)],
+            &[listener(Protocol::Tcp, LAN_IP, 8080)],
+            is_local,
+        );
+        assert!(findings.is_empty());
+    }
+
+    #[test]
+    fn other_lan_device() {
+        let findings = analyze(
+            &[proxy(1, "tcp", "192.168.1.20", 8080)],
+            &[listener(Protocol::Tcp, "127.0.0.1", 8080)],
+            is_local,
+        );
+        assert_eq!(kinds(&findings), vec![FindingKind::RemoteTarget]);
+        assert_eq!(findings[0].severity, Severity::Info);
+    }
+
+    #[test]
+    fn duplicate_local_port() {
+        let findings = analyze(
+            &[
+                proxy(1, "tcp", "127.0.0.1", 3000),
+                proxy(2, "http", "localhost", 3000),
+                proxy(3, "udp", "127.0.0.1", 3000),
+            ],
+            &[
+                listener(Protocol::Tcp, "0.0.0.0", 3000),
+                listener(Protocol::Udp, "0.0.0.0", 3000),
+            ],
+            is_local,
+        );
+        assert_eq!(kinds(&findings), vec![FindingKind::DuplicateLocalPort]);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert_eq!(findings[0].tunnel_ids, vec![1, 2]);
+        assert_eq!(findings[0].tunnel_names, vec!["tunnel1", "tunnel2"]);
+    }
+
+    #[test]
+    fn unspecified_v6_accepts_v4() {
+        let findings = analyze(
+            &[proxy(1, "tcp", "127.0.0.1", 8080)],
+            &[listener(Protocol::Tcp, "::", 8080)],
+            is_local,
+        );
+        assert!(findings.is_empty());
+
+        // 0.0.0.0 不接受 IPv6 连接
+        let findings = analyze(
+            &[proxy(1, "tcp", "::1", 8080)],
+            &[listener(Protocol::Tcp, "0.0.0.0", 8080)],
+            is_local,
+        );
+        assert_eq!(kinds(&findings), vec![FindingKind::AddressMismatch]);
+    }
+
+    #[test]
+    fn redis_without_encryption() {
+        let listeners = [listener(Protocol::Tcp, "127.0.0.1", 6379)];
+        let findings = analyze(&[proxy(1, "tcp", "127.0.0.1", 6379)], &listeners, is_local);
+        assert_eq!(kinds(&findings), vec![FindingKind::SensitiveExposed]);
+        assert_eq!(findings[0].severity, Severity::Error);
+
+        let encrypted = Proxy {
+            use_encryption: true,
+            ..proxy(1, "tcp", "127.0.0.1", 6379)
+        };
+        assert!(analyze(&[encrypted], &listeners, is_local).is_empty());
+        assert!(analyze(&[proxy(1, "stcp", "127.0.0.1", 6379)], &listeners, is_local).is_empty());
+    }
+}
@@ -0,0 +1,502 @@
+use serde::Serialize;
+use std::net::IpAddr;
+use tauri::command;
+
+// 本机监听端口枚举：Linux 直接读取 /proc/net/{tcp,tcp6,udp,udp6}，
+// 通过 /proc/<pid>/fd 中的 socket inode 找到进程；Windows 使用 IP Helper 的
+// GetExtendedTcpTable/GetExtendedUdpTable；macOS 解析 lsof 的机器可读输出。
+// 只返回监听中的 TCP 套接字和未连接的 UDP 套接字
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Listener {
+    pub protocol: Protocol,
+    // 绑定的地址，0.0.0.0 或 :: 表示所有地址
+    pub address: String,
+    pub port: u16,
+    pub ipv6: bool,
+    // 无权查看其他用户的进程时为 None
+    pub pid: Option<u32>,
+    pub process: Option<String>,
+}
+
+impl Listener {
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.address.parse().ok()
+    }
+
+    // 是否绑定在所有地址上
+    pub fn is_unspecified(&self) -> bool {
+        self.ip().is_some_and(|ip| ip.is_unspecified())
+    }
+
+    pub fn is_loopback(&self) -> bool {
+        self.ip().is_some_and(|ip| ip.is_loopback())
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct LocalPorts {
+    pub tcp: Vec<Listener>,
+    pub udp: Vec<Listener>,
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{Listener, Protocol};
+    use std::collections::HashMap;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    // TCP 状态 0A 为 LISTEN；UDP 没有监听状态，未连接的套接字状态为 07
+    const TCP_LISTEN: &str = "0A";
+    const UDP_UNCONNECTED: &str = "07";
+
+    // /proc/net 中的地址按内存字节序输出为十六进制，每 4 字节一组
+    fn parse_ip(hex: &str) -> Option<IpAddr> {
+        let mut bytes = Vec::with_capacity(16);
+        for chunk in hex.as_bytes().chunks(8) {
+            let word = u32::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+            bytes.extend_from_slice(&word.to_ne_bytes());
+        }
+        match bytes.len() {
+            4 => Some(IpAddr::V4(Ipv4Addr::new(
+                bytes[0], bytes[1], bytes[2], bytes[3],
+            ))),
+            16 => {
+                let bytes: [u8; 16] = bytes.try_into().ok()?;
+                let ip = Ipv6Addr::from(bytes);
+                // IPv4 映射地址按 IPv4 显示
+                Some(match ip.to_ipv4_mapped() {
+                    Some(v4) => IpAddr::V4(v4),
+                    None => IpAddr::V6(ip),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_endpoint(field: &str) -> Option<(IpAddr, u16)> {
+        let (ip, port) = field.split_once(':')?;
+        Some((parse_ip(ip)?, u16::from_str_radix(port, 16).ok()?))
+    }
+
+    // 解析 /proc/net/ 下一个表的内容，返回 (地址, 端口, inode)
+    pub(super) fn parse_table(content: &str, protocol: Protocol) -> Vec<(IpAddr, u16, u64)> {
+        let wanted = match protocol {
+            Protocol::Tcp => TCP_LISTEN,
+            Protocol::Udp => UDP_UNCONNECTED,
+        };
+        content
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                // sl local_address rem_address st tx:rx tr:when retrnsmt uid timeout inode
+                if fields.len() < 10 || fields[3] != wanted {
+                    return None;
+                }
+                let (ip, port) = parse_endpoint(fields[1])?;
+                let (_, remote_port) = parse_endpoint(fields[2])?;
+                if protocol == Protocol::Udp && remote_port != 0 {
+                    return None;
+                }
+                Some((ip, port, fields[9].parse().ok()?))
+            })
+            .collect()
+    }
+
+    fn read_table(file: &str, protocol: Protocol) -> Vec<(IpAddr, u16, u64)> {
+        match std::fs::read_to_string(format!("/proc/net/{}", file)) {
+            Ok(content) => parse_table(&content, protocol),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    // socket inode 到 (pid, 进程名) 的映射，只能看到有权限访问的进程
+    fn socket_owners() -> HashMap<u64, (u32, String)> {
+        let mut owners = HashMap::new();
+        let Ok(entries) = std::fs::read_dir("/proc") else {
+            return owners;
+        };
+        for entry in entries.flatten() {
+            let Ok(pid) = entry.file_name().to_string_lossy().parse::<u32>() else {
+                continue;
+            };
+            let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+                continue;
+            };
+            let name = std::fs::read_to_string(entry.path().join("comm"))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default();
+            for fd in fds.flatten() {
+                let Ok(target) = std::fs::read_link(fd.path()) else {
+                    continue;
+                };
+                let target = target.to_string_lossy();
+                if let Some(inode) = target
+                    .strip_prefix("socket:[")
+                    .and_then(|s| s.strip_suffix(']'))
+                    .and_then(|s| s.parse::<u64>().ok())
+                {
+                    owners.entry(inode).or_insert_with(|| (pid, name.clone()));
+                }
+            }
+        }
+        owners
+    }
+
+    pub fn listeners() -> Result<Vec<Listener>, String> {
+        if !std::path::Path::new("/proc/net/tcp").exists() {
+            return Err("无法读取 /proc/net，系统未挂载 procfs".to_string());
+        }
+        let owners = socket_owners();
+        let tables = [
+            ("tcp", Protocol::Tcp, false),
+            ("tcp6", Protocol::Tcp, true),
+            ("udp", Protocol::Udp, false),
+            ("udp6", Protocol::Udp, true),
+        ];
+        let mut listeners = Vec::new();
+        // IPv4 映射地址按 IPv4 显示，是否为 IPv6 套接字以所在的表为准
+        for (file, protocol, ipv6) in tables {
+            for (ip, port, inode) in read_table(file, protocol) {
+                let owner = owners.get(&inode);
+                listeners.push(Listener {
+                    protocol,
+                    address: ip.to_string(),
+                    port,
+                    ipv6,
+                    pid: owner.map(|(pid, _)| *pid),
+                    process: owner.map(|(_, name)| name.clone()),
+                });
+            }
+        }
+        Ok(listeners)
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{Listener, Protocol};
+    use std::collections::HashMap;
+    use std::ffi::c_void;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use windows_sys::Win32::Foundation::{CloseHandle, ERROR_INSUFFICIENT_BUFFER, NO_ERROR};
+    use windows_sys::Win32::NetworkManagement::IpHelper::{
+        GetExtendedTcpTable, GetExtendedUdpTable, MIB_TCP6ROW_OWNER_PID, MIB_TCPROW_OWNER_PID,
+        MIB_UDP6ROW_OWNER_PID, MIB_UDPROW_OWNER_PID, TCP_TABLE_OWNER_PID_LISTENER,
+        UDP_TABLE_OWNER_PID,
+    };
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    const AF_INET: u32 = 2;
+    const AF_INET6: u32 = 23;
+
+    // 按所需大小分配缓冲区并读取表，表头为 4 字节的行数
+    fn fetch_table(fetch: impl Fn(*mut c_void, *mut u32) -> u32) -> Result<Vec<u32>, String> {
+        let mut size = 0u32;
+        let mut buffer: Vec<u32> = Vec::new();
+        for _ in 0..5 {
+            let ret = fetch(buffer.as_mut_ptr().cast(), &mut size);
+            match ret {
+                NO_ERROR => return Ok(buffer),
+                // 表在两次调用之间变大时重新分配
+                ERROR_INSUFFICIENT_BUFFER => buffer = vec![0u32; (size as usize).div_ceil(4)],
+                code => return Err(format!("读取端口表失败，错误码 {}", code)),
+            }
+        }
+        Err("读取端口表失败，端口表变化过快".to_string())
+    }
+
+    fn rows<T: Copy>(buffer: &[u32]) -> Vec<T> {
+        let Some(&count) = buffer.first() else {
+            return Vec::new();
+        };
+        let available = (buffer.len() - 1) * 4 / std::mem::size_of::<T>();
+        let count = (count as usize).min(available);
+        let first = buffer[1..].as_ptr().cast::<T>();
+        // 行数已按缓冲区大小截断，读取不会越界
+        (0..count)
+            .map(|i| unsafe { first.add(i).read_unaligned() })
+            .collect()
+    }
+
+    fn tcp_table(family: u32) -> Result<Vec<u32>, String> {
+        fetch_table(|table, size| unsafe {
+            GetExtendedTcpTable(table, size, 0, family, TCP_TABLE_OWNER_PID_LISTENER, 0)
+        })
+    }
+
+    fn udp_table(family: u32) -> Result<Vec<u32>, String> {
+        fetch_table(|table, size| unsafe {
+            GetExtendedUdpTable(table, size, 0, family, UDP_TABLE_OWNER_PID, 0)
+        })
+    }
+
+    // 端口以网络字节序保存在低 16 位
+    fn port(raw: u32) -> u16 {
+        u16::from_be(raw as u16)
+    }
+
+    fn process_name(pid: u32) -> Option<String> {
+        match pid {
+            0 => return Some("System Idle Process".to_string()),
+            4 => return Some("System".to_string()),
+            _ => {}
+        }
+        unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if handle.is_null() {
+                return None;
+            }
+            let mut buffer = [0u16; 1024];
+            let mut len = buffer.len() as u32;
+            let ok = QueryFullProcessImageNameW(
+                handle,
+                PROCESS_NAME_WIN32,
+                buffer.as_mut_ptr(),
+                &mut len,
+            );
+            CloseHandle(handle);
+            if ok == 0 {
+                return None;
+            }
+            let path = String::from_utf16_lossy(&buffer[..len as usize]);
+            path.rsplit('\\').next().map(str::to_string)
+        }
+    }
+
+    pub fn listeners() -> Result<Vec<Listener>, String> {
+        let mut found: Vec<(Protocol, IpAddr, u16, u32)> = Vec::new();
+        for row in rows::<MIB_TCPROW_OWNER_PID>(&tcp_table(AF_INET)?) {
+            let ip = Ipv4Addr::from(row.dwLocalAddr.to_ne_bytes());
+            found.push((
+                Protocol::Tcp,
+                ip.into(),
+                port(row.dwLocalPort),
+                row.dwOwningPid,
+            ));
+        }
+        for row in rows::<MIB_TCP6ROW_OWNER_PID>(&tcp_table(AF_INET6)?) {
+            let ip = Ipv6Addr::from(row.ucLocalAddr);
+            found.push((
+                Protocol::Tcp,
+                ip.into(),
+                port(row.dwLocalPort),
+                row.dwOwningPid,
+            ));
+        }
+        for row in rows::<MIB_UDPROW_OWNER_PID>(&udp_table(AF_INET)?) {
+            let ip = Ipv4Addr::from(row.dwLocalAddr.to_ne_bytes());
+            found.push((
+                Protocol::Udp,
+                ip.into(),
+                port(row.dwLocalPort),
+                row.dwOwningPid,
+            ));
+        }
+        for row in rows::<MIB_UDP6ROW_OWNER_PID>(&udp_table(AF_INET6)?) {
+            let ip = Ipv6Addr::from(row.ucLocalAddr);
+            found.push((
+                Protocol::Udp,
+                ip.into(),
+                port(row.dwLocalPort),
+                row.dwOwningPid,
+            ));
+        }
+
+        let mut names: HashMap<u32, Option<String>> = HashMap::new();
+        Ok(found
+            .into_iter()
+            .map(|(protocol, ip, port, pid)| Listener {
+                protocol,
+                address: ip.to_string(),
+                port,
+                ipv6: ip.is_ipv6(),
+                pid: Some(pid),
+                process: names
+                    .entry(pid)
+                    .or_insert_with(|| process_name(pid))
+                    .clone(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use super::{Listener, Protocol};
+
+    // macOS 没有公开的套接字枚举接口，使用系统自带的 lsof，
+    // -F 输出每行一个字段：p 进程号、c 进程名、t 地址类型、P 协议、n 地址
+    pub fn listeners() -> Result<Vec<Listener>, String> {
+        let output = std::process::Command::new("lsof")
+            .args(["-nP", "-iTCP", "-sTCP:LISTEN", "-iUDP", "-FpctPn"])
+            .output()
+            .map_err(|e| format!("执行 lsof 失败: {}", e))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        let mut listeners = Vec::new();
+        let (mut pid, mut process, mut ipv6, mut protocol) = (None, None, false, None);
+        for line in stdout.lines() {
+            let (tag, value) = line.split_at(line.len().min(1));
+            match tag {
+                "p" => {
+                    pid = value.parse().ok();
+                    process = None;
+                }
+                "c" => process = Some(value.to_string()),
+                "f" => protocol = None,
+                "t" => ipv6 = value == "IPv6",
+                "P" => {
+                    protocol = match value {
+                        "TCP" => Some(Protocol::Tcp),
+                        "UDP" => Some(Protocol::Udp),
+                        _ => None,
+                    }
+                }
+                "n" => {
+                    // 已连接的 UDP 套接字形如 a->b，不是监听端口
+                    let (Some(protocol), false) = (protocol, value.contains("->")) else {
+                        continue;
+                    };
+                    let Some((address, port)) = value.rsplit_once(':') else {
+                        continue;
+                    };
+                    let Ok(port) = port.parse::<u16>() else {
+                        continue;
+                    };
+                    let address = address.trim_start_matches('[').trim_end_matches(']');
+                    let address = match (address, ipv6) {
+                        ("*", true) => "::",
+                        ("*", false) => "0.0.0.0",
+                        (address, _) => address,
+                    };
+                    listeners.push(Listener {
+                        protocol,
+                        address: address.to_string(),
+                        port,
+                        ipv6,
+                        pid,
+                        process: process.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        Ok(listeners)
+    }
+}
+
+// 本机所有监听中的端口，按协议、端口、地址排序并去重
+pub fn listeners() -> Result<Vec<Listener>, String> {
+    let mut listeners = platform::listeners()?;
+    listeners.sort_by(|a, b| {
+        (a.protocol == Protocol::Udp, a.port, &a.address).cmp(&(
+            b.protocol == Protocol::Udp,
+            b.port,
+            &b.address,
+        ))
+    });
+    listeners.dedup_by(|a, b| {
+        a.protocol == b.protocol && a.port == b.port && a.address == b.address && a.pid == b.pid
+    });
+    Ok(listeners)
+}
+
+// 扫描 /proc 可能较慢，放到阻塞线程中执行
+pub async fn local_ports() -> Result<LocalPorts, String> {
+    let listeners = tokio::task::spawn_blocking(listeners)
+        .await
+        .map_err(|e| format!("获取本地端口失败: {}", e))??;
+    let (tcp, udp) = listeners
+        .into_iter()
+        .partition(|l| l.protocol == Protocol::Tcp);
+    Ok(LocalPorts { tcp, udp })
+}
+
+#[command]
+pub async fn get_local_ports() -> Result<LocalPorts, String> {
+    local_ports().await
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::platform::parse_table;
+    use super::Protocol;
+
+    // 以下内容取自 x86_64 Linux 的 /proc/net，地址按小端输出
+
+    fn parsed(content: &str, protocol: Protocol) -> Vec<(String, u16, u64)> {
+        parse_table(content, protocol)
+            .into_iter()
+            .map(|(ip, port, inode)| (ip.to_string(), port, inode))
+            .collect()
+    }
+
+    #[test]
+    fn tcp_keeps_only_listening() {
+        let content = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:0CEA 00000000:0000 0A 00000000:00000000 00:00000000 00000000   116        0 23456 1 0000000000000000 100 0 0 10 0
+   1: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 18021 1 0000000000000000 100 0 0 10 0
+   2: 0F02000A:0016 0202000A:C350 01 00000000:00000000 02:0009D1A3 00000000     0        0 41523 4 0000000000000000 20 4 31 10 -1
+";
+        assert_eq!(
+            parsed(content, Protocol::Tcp),
+            vec![
+                ("127.0.0.1".to_string(), 3306, 23456),
+                ("0.0.0.0".to_string(), 22, 18021),
+            ]
+        );
+    }
+
+    #[test]
+    fn tcp6_maps_v4_addresses() {
+        let content = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:1F90 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 34567 1 0000000000000000 100 0 0 10 0
+   1: 00000000000000000000000001000000:0277 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 20811 1 0000000000000000 100 0 0 10 0
+   2: 0000000000000000FFFF00000100007F:18EB 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000   999        0 35012 1 0000000000000000 100 0 0 10 0
+";
+        assert_eq!(
+            parsed(content, Protocol::Tcp),
+            vec![
+                ("::".to_string(), 8080, 34567),
+                ("::1".to_string(), 631, 20811),
+                ("127.0.0.1".to_string(), 6379, 35012),
+            ]
+        );
+    }
+
+    #[test]
+    fn udp_skips_connected_sockets() {
+        let content = "   sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops
+  345: 00000000:14E9 00000000:0000 07 00000000:00000000 00:00000000 00000000   104        0 19876 2 0000000000000000 0
+  346: 3500007F:0035 00000000:0000 07 00000000:00000000 00:00000000 00000000   101        0 17453 2 0000000000000000 0
+  347: 0F02000A:A1B2 08080808:0035 01 00000000:00000000 00:00000000 00000000  1000        0 52001 2 0000000000000000 0
+";
+        assert_eq!(
+            parsed(content, Protocol::Udp),
+            vec![
+                ("0.0.0.0".to_string(), 5353, 19876),
+                ("127.0.0.53".to_string(), 53, 17453),
+            ]
+        );
+    }
+
+    #[test]
+    fn tcp_state_is_not_udp_state() {
+        let content = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:0CEA 00000000:0000 0A 00000000:00000000 00:00000000 00000000   116        0 23456 1 0000000000000000 100 0 0 10 0
+";
+        assert!(parsed(content, Protocol::Udp).is_empty());
+    }
+}
@@ -0,0 +1,356 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::command;
+use tokio::net::TcpStream;
+
+use crate::openfrp_api::{OpenFrpClient, Proxy};
+use crate::{load_config, read_config, save_config, EventSink, FrpcProcesses};
+
+// 本地服务检查：启动隧道前检查 localIp:localPort 是否可以连接，
+// HTTP/S 隧道额外发起一次 HTTP 请求。根据设置仅提示或阻止启动，
+// 隧道运行期间定期复查，本地服务状态变化时发出 local-service-health 事件
+
+const MIN_RECHECK_SECS: u64 = 10;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightMode {
+    // 不检查
+    Off,
+    // 检查失败时仍然启动，并在结果中附带警告
+    #[default]
+    Warn,
+    // 检查失败时不启动
+    Block,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PreflightSettings {
+    pub mode: PreflightMode,
+    // 单次连接的超时（毫秒）
+    pub timeout_ms: u64,
+    // 运行期间是否定期复查
+    pub recheck: bool,
+    pub recheck_interval_secs: u64,
+}
+
+impl Default for PreflightSettings {
+    fn default() -> Self {
+        PreflightSettings {
+            mode: PreflightMode::Warn,
+            timeout_ms: 1500,
+            recheck: true,
+            recheck_interval_secs: 30,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeKind {
+    Tcp,
+    Http,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LocalServiceCheck {
+    pub tunnel_id: String,
+    pub name: String,
+    pub local_addr: String,
+    pub local_port: u16,
+    pub probe: ProbeKind,
+    pub reachable: bool,
+    pub latency_ms: Option<f64>,
+    // HTTP 检查的状态码
+    pub status: Option<u16>,
+    pub message: String,
+    pub checked_at: i64,
+}
+
+// 启动结果，warnings 为未通过的本地服务检查
+#[derive(Serialize, Debug)]
+pub struct StartResult {
+    pub message: String,
+    pub warnings: Vec<LocalServiceCheck>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StartError {
+    // 本地服务未就绪，按设置阻止启动
+    LocalServiceUnavailable {
+        message: String,
+        checks: Vec<LocalServiceCheck>,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+impl std::fmt::Display for StartError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartError::LocalServiceUnavailable { message, .. }
+            | StartError::Failed { message } => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for StartError {
+    fn from(message: String) -> Self {
+        StartError::Failed { message }
+    }
+}
+
+// 各运行中隧道最近一次的本地服务检查结果
+static LAST_CHECKS: Lazy<Mutex<HashMap<String, LocalServiceCheck>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn settings() -> PreflightSettings {
+    read_config()
+        .ok()
+        .and_then(|cfg| cfg.preflight)
+        .unwrap_or_default()
+}
+
+// 监听所有地址时从本机回环地址连接
+fn connect_host(local_ip: &str) -> &str {
+    match local_ip.trim() {
+        "" | "0.0.0.0" | "localhost" => "127.0.0.1",
+        "::" | "[::]" => "::1",
+        ip => ip,
+    }
+}
+
+// 本地服务的连接地址，域名需要解析
+async fn resolve(host: &str, port: u16) -> Result<SocketAddr, String> {
+    tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("解析本地地址 {} 失败: {}", host, e))?
+        .next()
+        .ok_or_else(|| format!("本地地址 {} 没有可用的 IP", host))
+}
+
+// 检查 HTTP 服务，HTTPS 隧道未开启自动证书时本地服务自行提供 TLS
+async fn probe_http(addr: SocketAddr, tls: bool, timeout: Duration) -> Result<u16, String> {
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .danger_accept_invalid_certs(true)
+        .timeout(timeout)
+        .build()
+        .map_err(|e| format!("创建请求客户端失败: {}", e))?;
+    let url = format!("{}://{}/", if tls { "https" } else { "http" }, addr);
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("HTTP 请求失败: {}", e))?;
+    Ok(response.status().as_u16())
+}
+
+// 检查隧道对应的本地服务，UDP 隧道无法通过连接判断，返回 None
+pub async fn check_proxy(proxy: &Proxy, timeout: Duration) -> Option<LocalServiceCheck> {
+    let proxy_type = proxy.proxy_type.to_lowercase();
+    if proxy_type == "udp" {
+        return None;
+    }
+    let web = proxy_type == "http" || proxy_type == "https";
+    let local_port = u16::try_from(proxy.local_port).ok()?;
+    let mut check = LocalServiceCheck {
+        tunnel_id: proxy.id.to_string(),
+        name: proxy.proxy_name.clone(),
+        local_addr: proxy.local_ip.clone(),
+        local_port,
+        probe: if web { ProbeKind::Http } else { ProbeKind::Tcp },
+        reachable: false,
+        latency_ms: None,
+        status: None,
+        message: String::new(),
+        checked_at: chrono::Utc::now().timestamp(),
+    };
+
+    let addr = match resolve(connect_host(&proxy.local_ip), local_port).await {
+        Ok(addr) => addr,
+        Err(e) => {
+            check.message = e;
+            return Some(check);
+        }
+    };
+    let start = Instant::now();
+    match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => {
+            check.message = format!("无法连接本地服务 {}: {}", addr, e);
+            return Some(check);
+        }
+        Err(_) => {
+            check.message = format!("连接本地服务 {} 超时", addr);
+            return Some(check);
+        }
+    }
+    check.latency_ms = Some(start.elapsed().as_secs_f64() * 1000.0);
+
+    if !web {
+        check.reachable = true;
+        check.message = format!("本地服务 {} 可以连接", addr);
+        return Some(check);
+    }
+    let tls = proxy_type == "https" && proxy.auto_tls.as_deref().unwrap_or("false") == "false";
+    match probe_http(addr, tls, timeout).await {
+        Ok(status) => {
+            check.status = Some(status);
+            // 5xx 通常表示本地反向代理的后端不可用
+            check.reachable = status < 500;
+            check.message = format!("本地服务 {} 返回状态码 {}", addr, status);
+        }
+        Err(e) => check.message = e,
+    }
+    Some(check)
+}
+
+// 检查 tunnel_ids（逗号分隔）对应的本地服务，获取不到隧道信息时跳过检查
+async fn check_tunnels(tunnel_ids: &str, timeout: Duration) -> Vec<LocalServiceCheck> {
+    let ids: Vec<&str> = tunnel_ids.split(',').map(str::trim).collect();
+    let proxies = match OpenFrpClient::new() {
+        Ok(client) => match client
+            .user_proxies_within(crate::monitor::PROXY_LIST_MAX_AGE)
+            .await
+        {
+            Ok(fetched) => fetched.data.list,
+            Err(e) => {
+                println!("获取隧道信息失败，跳过本地服务检查: {}", e);
+                return Vec::new();
+            }
+        },
+        Err(e) => {
+            println!("获取隧道信息失败，跳过本地服务检查: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut checks = Vec::new();
+    for proxy in proxies
+        .iter()
+        .filter(|p| ids.contains(&p.id.to_string().as_str()))
+    {
+        if let Some(check) = check_proxy(proxy, timeout).await {
+            checks.push(check);
+        }
+    }
+    checks
+}
+
+// 启动前检查，按设置返回未通过的检查或阻止启动
+pub async fn run(tunnel_ids: &str) -> Result<Vec<LocalServiceCheck>, StartError> {
+    let settings = settings();
+    if settings.mode == PreflightMode::Off {
+        return Ok(Vec::new());
+    }
+    let checks = check_tunnels(
+        tunnel_ids,
+        Duration::from_millis(settings.timeout_ms.max(1)),
+    )
+    .await;
+    {
+        let mut last = LAST_CHECKS.lock().unwrap();
+        for check in &checks {
+            last.insert(check.tunnel_id.clone(), check.clone());
+        }
+    }
+    let failed: Vec<LocalServiceCheck> = checks.into_iter().filter(|c| !c.reachable).collect();
+    if failed.is_empty() || settings.mode == PreflightMode::Warn {
+        return Ok(failed);
+    }
+    let message = failed
+        .iter()
+        .map(|c| format!("隧道 {}: {}", c.name, c.message))
+        .collect::<Vec<_>>()
+        .join("；");
+    Err(StartError::LocalServiceUnavailable {
+        message: format!("本地服务未就绪，已取消启动。{}", message),
+        checks: failed,
+    })
+}
+
+async fn recheck(processes: &FrpcProcesses, sink: &Arc<dyn EventSink>, timeout: Duration) {
+    let proxies = match crate::monitor::running_proxies(processes).await {
+        Ok(proxies) => proxies,
+        Err(e) => {
+            println!("本地服务复查{}", e);
+            return;
+        }
+    };
+    let running: Vec<String> = proxies.iter().map(|p| p.id.to_string()).collect();
+    LAST_CHECKS
+        .lock()
+        .unwrap()
+        .retain(|id, _| running.contains(id));
+
+    for proxy in &proxies {
+        let Some(check) = check_proxy(proxy, timeout).await else {
+            continue;
+        };
+        let previous = LAST_CHECKS
+            .lock()
+            .unwrap()
+            .insert(check.tunnel_id.clone(), check.clone());
+        if previous.is_some_and(|p| p.reachable == check.reachable) {
+            continue;
+        }
+        sink.emit_event("local-service-health", serde_json::json!(check));
+        if !check.reachable {
+            sink.notify(
+                &format!("隧道 {} 的本地服务不可用", check.name),
+                &check.message,
+            );
+        }
+    }
+}
+
+// 隧道运行期间定期复查本地服务
+pub fn spawn(processes: FrpcProcesses, sink: Arc<dyn EventSink>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let settings = settings();
+            if settings.mode != PreflightMode::Off && settings.recheck {
+                recheck(
+                    &processes,
+                    &sink,
+                    Duration::from_millis(settings.timeout_ms.max(1)),
+                )
+                .await;
+            }
+            let interval = settings.recheck_interval_secs.max(MIN_RECHECK_SECS);
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+    });
+}
+
+// 运行中隧道最近一次的本地服务检查结果
+#[command]
+pub fn get_local_service_health(tunnel_id: Option<String>) -> Vec<LocalServiceCheck> {
+    let last = LAST_CHECKS.lock().unwrap();
+    let mut list: Vec<LocalServiceCheck> = last
+        .values()
+        .filter(|c| tunnel_id.is_none() || tunnel_id.as_ref() == Some(&c.tunnel_id))
+        .cloned()
+        .collect();
+    list.sort_by(|a, b| a.tunnel_id.cmp(&b.tunnel_id));
+    list
+}
+
+#[command]
+pub fn get_preflight_settings() -> Result<PreflightSettings, String> {
+    Ok(settings())
+}
+
+#[command]
+pub fn set_preflight_settings(settings: PreflightSettings) -> Result<(), String> {
+    let mut cfg = load_config()?;
+    cfg.preflight = Some(settings);
+    save_config(&cfg)
+}
@@ -0,0 +1,186 @@
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// 按主机的令牌桶限流：多个页面同时刷新时排队发送，
+// 服务器返回 429 后在 Retry-After 指定的时间内暂停向该主机发送请求
+
+// 桶容量，即允许的突发请求数
+const BUCKET_CAPACITY: f64 = 10.0;
+// 每秒补充的令牌数
+const REFILL_PER_SEC: f64 = 5.0;
+// 排队等待的最长时间，超过时直接返回限流错误
+pub const MAX_WAIT: Duration = Duration::from_secs(10);
+// 429 响应未携带 Retry-After 时的等待时间
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+// Retry-After 的上限，避免异常的响应头导致长时间无法请求
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(600);
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    // 服务器要求暂停请求的截止时间
+    blocked_until: Option<Instant>,
+}
+
+impl Bucket {
+    fn new(now: Instant) -> Self {
+        Bucket {
+            tokens: BUCKET_CAPACITY,
+            updated: now,
+            blocked_until: None,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * REFILL_PER_SEC).min(BUCKET_CAPACITY);
+        self.updated = now;
+    }
+}
+
+static BUCKETS: Lazy<Mutex<HashMap<String, Bucket>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// 限流按 主机:端口 区分
+pub fn host_key(url: &Url) -> String {
+    format!(
+        "{}:{}",
+        url.host_str().unwrap_or_default(),
+        url.port_or_known_default().unwrap_or_default()
+    )
+}
+
+// 预留一个令牌，返回发送前需要等待的时间；需要等待过久时返回 Err(剩余时间)
+fn reserve(host: &str) -> Result<Duration, Duration> {
+    let now = Instant::now();
+    let mut buckets = BUCKETS.lock().unwrap();
+    let bucket = buckets
+        .entry(host.to_string())
+        .or_insert_with(|| Bucket::new(now));
+    bucket.refill(now);
+
+    let blocked = bucket
+        .blocked_until
+        .map(|until| until.saturating_duration_since(now))
+        .unwrap_or_default();
+    let queued = if bucket.tokens >= 1.0 {
+        Duration::ZERO
+    } else {
+        Duration::from_secs_f64((1.0 - bucket.tokens) / REFILL_PER_SEC)
+    };
+    let wait = blocked.max(queued);
+    if wait > MAX_WAIT {
+        return Err(wait);
+    }
+    // 令牌可以透支，后到的请求按顺序等待更长时间
+    bucket.tokens -= 1.0;
+    Ok(wait)
+}
+
+// 等待可以向该主机发送请求，需要等待过久时返回 Err(建议的重试间隔)
+pub async fn acquire(host: &str) -> Result<(), Duration> {
+    let wait = reserve(host)?;
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+    Ok(())
+}
+
+// 记录服务器返回的 429，在 retry_after 内暂停向该主机发送请求
+pub fn record_rate_limited(host: &str, retry_after: Duration) {
+    let now = Instant::now();
+    let mut buckets = BUCKETS.lock().unwrap();
+    let bucket = buckets
+        .entry(host.to_string())
+        .or_insert_with(|| Bucket::new(now));
+    bucket.refill(now);
+    bucket.tokens = bucket.tokens.min(0.0);
+    bucket.blocked_until = bucket.blocked_until.max(Some(now + retry_after));
+    println!("{} 请求过于频繁，{} 秒后重试", host, retry_after.as_secs());
+}
+
+// 解析 Retry-After 响应头，支持秒数和 HTTP 日期两种格式
+pub fn retry_after(headers: &HeaderMap) -> Duration {
+    let Some(value) = headers.get(RETRY_AFTER).and_then(|v| v.to_str().ok()) else {
+        return DEFAULT_RETRY_AFTER;
+    };
+    let value = value.trim();
+    let duration = if let Ok(secs) = value.parse::<u64>() {
+        Duration::from_secs(secs)
+    } else if let Ok(date) = chrono::DateTime::parse_from_rfc2822(value) {
+        (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default()
+    } else {
+        DEFAULT_RETRY_AFTER
+    };
+    duration.min(MAX_RETRY_AFTER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        assert_eq!(retry_after(&headers("30")), Duration::from_secs(30));
+        assert_eq!(retry_after(&headers(" 0 ")), Duration::ZERO);
+        assert_eq!(retry_after(&HeaderMap::new()), DEFAULT_RETRY_AFTER);
+    }
+
+    #[test]
+    fn retry_after_http_date() {
+        let date = chrono::Utc::now() + chrono::Duration::seconds(120);
+        let value = date.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        let wait = retry_after(&headers(&value));
+        assert!(wait > Duration::from_secs(115) && wait <= Duration::from_secs(120));
+    }
+
+    #[test]
+    fn retry_after_past_date() {
+        assert_eq!(
+            retry_after(&headers("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn retry_after_garbage() {
+        assert_eq!(retry_after(&headers("soon")), DEFAULT_RETRY_AFTER);
+        assert_eq!(retry_after(&headers("-5")), DEFAULT_RETRY_AFTER);
+        assert_eq!(retry_after(&headers("1.5")), DEFAULT_RETRY_AFTER);
+    }
+
+    #[test]
+    fn retry_after_clamped() {
+        assert_eq!(retry_after(&headers("86400")), MAX_RETRY_AFTER);
+        let date = chrono::Utc::now() + chrono::Duration::days(1);
+        let value = date.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        assert_eq!(retry_after(&headers(&value)), MAX_RETRY_AFTER);
+    }
+
+    #[test]
+    fn long_block_exceeds_max_wait() {
+        let host = "max-wait.test:443";
+        assert_eq!(reserve(host), Ok(Duration::ZERO));
+        record_rate_limited(host, MAX_WAIT + Duration::from_secs(5));
+        let wait = reserve(host).unwrap_err();
+        assert!(wait > MAX_WAIT);
+
+        // 不超过 MAX_WAIT 时排队等待
+        let host = "short-wait.test:443";
+        record_rate_limited(host, Duration::from_secs(2));
+        let wait = reserve(host).unwrap();
+        assert!(wait > Duration::from_secs(1) && wait <= Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,720 @@
+use serde::Serialize;
+use std::future::Future;
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Response, Server};
+
+use crate::diagnostics::{CheckResult, CheckStatus};
+use crate::endpoints::{EndpointSettings, Endpoints, EnvironmentProfile};
+use crate::openfrp_api::OpenFrpClient;
+use crate::{EventSink, FrpcProcesses};
+
+// 本地回环自检：在临时程序目录中启动模拟的 API 服务（commonQuery、下载、隧道列表）
+// 和 frp 官方发布的 frps，通过模拟服务下载安装 OpenFrp 发布的 frpc，
+// 再依次验证 API 地址切换、下载安装、启动、日志、重启与停止，全程不访问外部网络。
+// OpenFrp frpc 的 -u/-p 简易启动会向 OpenFrp 服务器获取隧道配置，
+// 因此安装后在原位置放一个由启动器自身充当的转接程序：
+// 它检查启动参数，生成连接本地 frps 的配置文件，再用 -c 启动真正的 frpc。
+// frpc 安装包与 frps 由 CI 下载并缓存，通过环境变量传入。
+// 只在启用 selftest 特性时编译（CI 中运行 `cargo run --features selftest -- selftest`）
+
+// OpenFrp 发布的 frpc 安装包路径
+pub const FRPC_ARCHIVE_ENV: &str = "OPENFRP_CPL_SELFTEST_FRPC_ARCHIVE";
+// frp 发布的 frps 程序路径
+pub const FRPS_ENV: &str = "OPENFRP_CPL_SELFTEST_FRPS";
+// 转接程序启动的真正 frpc 与要连接的 frps 端口，设置后按转接程序运行
+const SHIM_FRPC_ENV: &str = "OPENFRP_CPL_SELFTEST_REAL_FRPC";
+const SHIM_PORT_ENV: &str = "OPENFRP_CPL_SELFTEST_FRPS_PORT";
+
+const TOKEN: &str = "selftest-token";
+const AUTHORIZATION: &str = "selftest-authorization";
+const INSTANCE_ID: &str = "selftest";
+const TUNNEL_ID: i64 = 10001;
+const FRPC_VERSION: &str = "selftest";
+const DASHBOARD_USER: &str = "selftest";
+const WAIT_TIMEOUT: Duration = Duration::from_secs(15);
+
+// frpc 在 frps 中注册的隧道名称
+fn proxy_name(id: &str) -> String {
+    format!("selftest_{}", id)
+}
+
+// 由系统分配一个空闲的本地端口
+fn free_port() -> Result<u16, String> {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| format!("分配本地端口失败: {}", e))
+}
+
+// TOML 字符串，JSON 的转义规则对这里的内容同样适用
+fn toml_str(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+// spawn_frpc 传入的参数，与 OpenFrp frpc 的命令行一致，不认识的参数按 frpc 的方式报错
+#[derive(Default)]
+struct LaunchArgs {
+    user: String,
+    proxies: String,
+    debug: bool,
+    force_tls: bool,
+}
+
+fn parse_launch_args(args: &[String]) -> Result<LaunchArgs, String> {
+    let mut parsed = LaunchArgs::default();
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("flag needs an argument: {}", arg))
+        };
+        match arg.as_str() {
+            "-u" | "--user" => parsed.user = value()?,
+            "-p" | "--proxy" => parsed.proxies = value()?,
+            // DoH 只用于解析 OpenFrp 节点地址，连接本地 frps 时不需要
+            "--doh-addr" => {
+                value()?;
+            }
+            "--use-doh" => {}
+            "--debug" => parsed.debug = true,
+            "--force-tls" => parsed.force_tls = true,
+            other => return Err(format!("unknown flag: {}", other)),
+        }
+    }
+    if parsed.user.is_empty() || parsed.proxies.is_empty() {
+        return Err("user and proxy are required".to_string());
+    }
+    Ok(parsed)
+}
+
+// 与简易启动等效的 frpc 配置：用户密钥作为 frps 的认证令牌，每个隧道注册为 tcp 隧道
+fn frpc_config(args: &LaunchArgs, server_port: &str) -> Result<String, String> {
+    let mut config = format!(
+        "serverAddr = \"127.0.0.1\"
+serverPort = {}
+loginFailExit = true
+auth.token = {}
+transport.heartbeatInterval = 1
+transport.tls.enable = {}
+log.level = \"{}\"
+log.disablePrintColor = true
+",
+        server_port,
+        toml_str(&args.user),
+        args.force_tls,
+        if args.debug { "debug" } else { "info" }
+    );
+    for id in args
+        .proxies
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+    {
+        config.push_str(&format!(
+            "
+[[proxies]]
+name = {}
+type = \"tcp\"
+localIP = \"127.0.0.1\"
+localPort = 8080
+remotePort = {}
+",
+            toml_str(&proxy_name(id)),
+            free_port()?
+        ));
+    }
+    Ok(config)
+}
+
+pub fn shim_requested(args: &[String]) -> bool {
+    std::env::var_os(SHIM_FRPC_ENV).is_some() && args.get(1).map(String::as_str) == Some("-u")
+}
+
+// 转接程序：检查参数后用生成的配置启动真正的 frpc，退出码与 frpc 一致
+pub fn run_frpc_shim(args: &[String]) -> i32 {
+    let args = match parse_launch_args(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    };
+    let frpc = PathBuf::from(std::env::var_os(SHIM_FRPC_ENV).unwrap_or_default());
+    let server_port = std::env::var(SHIM_PORT_ENV).unwrap_or_default();
+    let path = frpc.with_file_name(format!("selftest-frpc-{}.toml", std::process::id()));
+    let written = frpc_config(&args, &server_port)
+        .and_then(|config| std::fs::write(&path, config).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        eprintln!("selftest: 生成 frpc 配置失败: {}", e);
+        return 1;
+    }
+    // 输出到标准错误，用于检查启动器是否给标准错误加上前缀
+    eprintln!("selftest: 使用 {} 启动 {}", path.display(), frpc.display());
+
+    let mut cmd = Command::new(&frpc);
+    cmd.arg("-c").arg(&path);
+    // Unix 下直接替换当前进程，停止隧道时结束的就是 frpc 本身；
+    // Windows 下 kill_process 会结束整个进程树
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let e = cmd.exec();
+        eprintln!("selftest: 启动 frpc 失败: {}", e);
+        1
+    }
+    #[cfg(not(unix))]
+    {
+        match cmd.status() {
+            Ok(status) => status.code().unwrap_or(1),
+            Err(e) => {
+                eprintln!("selftest: 启动 frpc 失败: {}", e);
+                1
+            }
+        }
+    }
+}
+
+// frp 发布的 frps，通过管理接口查询隧道状态
+struct LocalFrps {
+    child: Child,
+    bind_port: u16,
+    dashboard: String,
+    client: reqwest::Client,
+}
+
+impl LocalFrps {
+    fn start(app_dir: &Path) -> Result<Self, String> {
+        let frps = std::env::var_os(FRPS_ENV)
+            .ok_or_else(|| format!("未设置 {}，请指定 frp 发布的 frps", FRPS_ENV))?;
+        let (bind_port, dashboard_port) = (free_port()?, free_port()?);
+        let config = format!(
+            "bindAddr = \"127.0.0.1\"
+bindPort = {}
+auth.token = {}
+webServer.addr = \"127.0.0.1\"
+webServer.port = {}
+webServer.user = {}
+webServer.password = {}
+log.disablePrintColor = true
+",
+            bind_port,
+            toml_str(TOKEN),
+            dashboard_port,
+            toml_str(DASHBOARD_USER),
+            toml_str(TOKEN)
+        );
+        let config_path = app_dir.join("frps.toml");
+        std::fs::write(&config_path, config).map_err(|e| format!("写入 frps 配置失败: {}", e))?;
+        // frps 的输出保存到程序目录，--keep 时可以查看
+        let log = std::fs::File::create(app_dir.join("frps.log"))
+            .map_err(|e| format!("创建 frps 日志失败: {}", e))?;
+        let child = Command::new(frps)
+            .arg("-c")
+            .arg(&config_path)
+            .stdout(log.try_clone().map_err(|e| e.to_string())?)
+            .stderr(log)
+            .stdin(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("启动 frps 失败: {}", e))?;
+        let client = reqwest::Client::builder()
+            .no_proxy()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(LocalFrps {
+            child,
+            bind_port,
+            dashboard: format!("http://127.0.0.1:{}", dashboard_port),
+            client,
+        })
+    }
+
+    fn ready(&self) -> bool {
+        TcpStream::connect(("127.0.0.1", self.bind_port)).is_ok()
+    }
+
+    // 隧道在 frps 中的状态（online / offline），未注册过时返回 None
+    async fn proxy_status(&self, name: &str) -> Option<String> {
+        let body: serde_json::Value = self
+            .client
+            .get(format!("{}/api/proxy/tcp", self.dashboard))
+            .basic_auth(DASHBOARD_USER, Some(TOKEN))
+            .send()
+            .await
+            .ok()?
+            .json()
+            .await
+            .ok()?;
+        body["proxies"]
+            .as_array()?
+            .iter()
+            .find(|p| p["name"].as_str() == Some(name))
+            .and_then(|p| p["status"].as_str())
+            .map(String::from)
+    }
+
+    async fn wait_status(&self, name: &str, status: &str) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < WAIT_TIMEOUT {
+            if self.proxy_status(name).await.as_deref() == Some(status) {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        false
+    }
+}
+
+impl Drop for LocalFrps {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// 模拟的 API 服务，记录收到的请求路径
+struct MockApi {
+    base: String,
+    server: Arc<Server>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockApi {
+    fn start(archive: Vec<u8>) -> Result<Self, String> {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").map_err(|e| format!("启动模拟 API 失败: {}", e))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("启动模拟 API 失败: {}", e))?
+            .port();
+        let server = Arc::new(
+            Server::from_listener(listener, None)
+                .map_err(|e| format!("启动模拟 API 失败: {}", e))?,
+        );
+        let base = format!("http://127.0.0.1:{}", port);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let (incoming, recorded, download_base) = (server.clone(), requests.clone(), base.clone());
+        std::thread::spawn(move || {
+            for req in incoming.incoming_requests() {
+                let url = req.url().to_string();
+                recorded.lock().unwrap().push(url.clone());
+                let authorized = req
+                    .headers()
+                    .iter()
+                    .any(|h| h.field.equiv("Authorization") && h.value.as_str() == AUTHORIZATION);
+                let response = if url.starts_with("/commonQuery/get") {
+                    json_response(serde_json::json!({
+                        "flag": true,
+                        "data": {
+                            "latest": format!("/{}/", FRPC_VERSION),
+                            "source": [{ "label": "selftest", "value": format!("{}/download", download_base) }],
+                        },
+                    }))
+                } else if url.starts_with("/download/") {
+                    Response::from_data(archive.clone())
+                } else if url.starts_with("/frp/api/getUserProxies") && authorized {
+                    json_response(serde_json::json!({
+                        "flag": true,
+                        "msg": "OK",
+                        "data": {
+                            "total": 1,
+                            "list": [{
+                                "id": TUNNEL_ID,
+                                "proxyName": "selftest",
+                                "proxyType": "tcp",
+                                "localIp": "127.0.0.1",
+                                "localPort": 8080,
+                            }],
+                        },
+                    }))
+                } else if url.starts_with("/frp/api/") {
+                    Response::from_string("Unauthorized").with_status_code(401)
+                } else {
+                    Response::from_string("Not Found").with_status_code(404)
+                };
+                let _ = req.respond(response);
+            }
+        });
+        Ok(MockApi {
+            base,
+            server,
+            requests,
+        })
+    }
+
+    fn requested(&self, prefix: &str) -> bool {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|url| url.starts_with(prefix))
+    }
+}
+
+impl Drop for MockApi {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+fn json_response(body: serde_json::Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(body.to_string())
+        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
+}
+
+// 记录收到的日志与事件
+#[derive(Default)]
+struct CaptureSink {
+    logs: Mutex<Vec<(String, String)>>,
+}
+
+impl CaptureSink {
+    fn has(&self, event: &str, needle: &str) -> bool {
+        self.logs
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(e, message)| e == event && message.contains(needle))
+    }
+}
+
+impl EventSink for CaptureSink {
+    fn emit_log(&self, event: &str, message: String) {
+        self.logs.lock().unwrap().push((event.to_string(), message));
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    // 临时程序目录，--keep 时保留
+    pub app_dir: String,
+    pub checks: Vec<CheckResult>,
+}
+
+async fn wait_for(mut condition: impl FnMut() -> bool) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < WAIT_TIMEOUT {
+        if condition() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    condition()
+}
+
+struct Harness {
+    api: MockApi,
+    frps: LocalFrps,
+    processes: FrpcProcesses,
+    sink: Arc<CaptureSink>,
+}
+
+// 执行一步检查，之前的步骤失败时跳过
+async fn step<F, Fut>(checks: &mut Vec<CheckResult>, category: &str, name: &str, check: F)
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    let failed = checks.iter().any(|c| c.status == CheckStatus::Fail);
+    let start = Instant::now();
+    let (status, message) = if failed {
+        (CheckStatus::Skip, "之前的步骤失败".to_string())
+    } else {
+        match check().await {
+            Ok(message) => (CheckStatus::Pass, message),
+            Err(message) => (CheckStatus::Fail, message),
+        }
+    };
+    checks.push(CheckResult {
+        category: category.to_string(),
+        name: name.to_string(),
+        status,
+        message,
+        duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+        details: Vec::new(),
+    });
+}
+
+impl Harness {
+    fn start_frpc(&self) -> Result<String, String> {
+        crate::spawn_frpc(
+            &self.processes,
+            self.sink.clone(),
+            INSTANCE_ID.to_string(),
+            Some(TOKEN.to_string()),
+            TUNNEL_ID.to_string(),
+        )
+    }
+
+    async fn check_proxies(&self) -> Result<String, String> {
+        let fetched = OpenFrpClient::with_authorization(AUTHORIZATION)
+            .map_err(|e| e.to_string())?
+            .user_proxies()
+            .await
+            .map_err(|e| format!("获取隧道列表失败: {}", e))?;
+        match fetched.data.list.as_slice() {
+            [proxy] if proxy.id == TUNNEL_ID && !fetched.stale => {
+                Ok(format!("获取到隧道 {}", proxy.proxy_name))
+            }
+            list => Err(format!("隧道列表不符合预期: {} 条", list.len())),
+        }
+    }
+
+    async fn check_download(&self) -> Result<String, String> {
+        crate::install_frpc(self.sink.as_ref()).await?;
+        let config = crate::load_config()?;
+        let filename = config.frpc_filename.unwrap_or_default();
+        if config.frpc_version.as_deref() != Some(FRPC_VERSION) {
+            return Err(format!("frpc 版本未更新: {:?}", config.frpc_version));
+        }
+        if !self.api.requested("/download/") {
+            return Err("未从模拟服务下载安装包".to_string());
+        }
+        let installed = crate::get_app_dir().join(&filename);
+        if !installed.exists() {
+            return Err(format!("安装后找不到 {}", filename));
+        }
+        let output = Command::new(&installed)
+            .arg("-v")
+            .output()
+            .map_err(|e| format!("运行安装的 frpc 失败: {}", e))?;
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        install_shim(&installed)?;
+        Ok(format!("已安装 {}，版本 {}", filename, version))
+    }
+
+    async fn check_start(&self) -> Result<String, String> {
+        if !wait_for(|| self.frps.ready()).await {
+            return Err("frps 未能启动".to_string());
+        }
+        self.start_frpc()?;
+        let name = proxy_name(&TUNNEL_ID.to_string());
+        if !self.frps.wait_status(&name, "online").await {
+            return Err("frps 中隧道未上线".to_string());
+        }
+        let event = format!("frpc-log-{}", INSTANCE_ID);
+        if !wait_for(|| self.sink.has(&event, "start proxy success")).await {
+            return Err("未收到隧道启动成功的日志".to_string());
+        }
+        Ok("frpc 已登录 frps 并启动隧道".to_string())
+    }
+
+    async fn check_logs(&self) -> Result<String, String> {
+        let path = crate::frpc_log_path(INSTANCE_ID);
+        // 启用了 FRPC_DEBUG，frpc 收到 --debug 时转接程序按 debug 级别启动，会输出心跳等调试日志
+        let ready = |content: &str| {
+            content.contains("login to server success") && content.contains("[D] [")
+        };
+        if !wait_for(|| ready(&std::fs::read_to_string(&path).unwrap_or_default())).await {
+            return Err(format!(
+                "日志文件 {} 缺少登录成功或调试日志",
+                path.display()
+            ));
+        }
+        let content = std::fs::read_to_string(&path).unwrap_or_default();
+        if !content
+            .lines()
+            .any(|line| line.starts_with("错误: ") && line.contains("selftest: "))
+        {
+            return Err("标准错误输出未加上错误前缀".to_string());
+        }
+        let event = format!("frpc-log-{}", INSTANCE_ID);
+        if !self.sink.has(&event, "start proxy success") {
+            return Err("未发出日志事件".to_string());
+        }
+        Ok("frpc 参数与标准输出、标准错误已写入日志文件并发出事件".to_string())
+    }
+
+    async fn check_restart(&self) -> Result<String, String> {
+        let name = proxy_name(&TUNNEL_ID.to_string());
+        crate::stop_frpc(&self.processes, INSTANCE_ID)?;
+        if !self.frps.wait_status(&name, "offline").await {
+            return Err("停止后 frps 中隧道未下线".to_string());
+        }
+        self.start_frpc()?;
+        if !self.frps.wait_status(&name, "online").await {
+            return Err("重启后 frps 中隧道未重新上线".to_string());
+        }
+        Ok("停止后重新登录并启动隧道".to_string())
+    }
+
+    async fn check_stop(&self) -> Result<String, String> {
+        let name = proxy_name(&TUNNEL_ID.to_string());
+        crate::stop_frpc(&self.processes, INSTANCE_ID)?;
+        if !self.frps.wait_status(&name, "offline").await {
+            return Err("停止后 frps 中隧道未下线".to_string());
+        }
+        if !self.processes.0.lock().unwrap().is_empty() {
+            return Err("进程列表中仍有实例".to_string());
+        }
+        if crate::stop_frpc(&self.processes, INSTANCE_ID).is_ok() {
+            return Err("重复停止没有返回错误".to_string());
+        }
+        Ok("frpc 已结束并从进程列表移除".to_string())
+    }
+}
+
+// 把安装的 frpc 移到旁边，原位置换成转接程序：
+// Unix 下为调用启动器自身的脚本，Windows 下为启动器的副本
+fn install_shim(installed: &Path) -> Result<(), String> {
+    let name = installed
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let released = installed.with_file_name(format!("openfrp-{}", name));
+    std::fs::rename(installed, &released).map_err(|e| format!("移动 frpc 失败: {}", e))?;
+    // 转接程序继承环境变量，通过它找到真正的 frpc
+    std::env::set_var(SHIM_FRPC_ENV, &released);
+
+    let exe = std::env::current_exe().map_err(|e| format!("获取程序路径失败: {}", e))?;
+    #[cfg(target_os = "windows")]
+    {
+        std::fs::copy(&exe, installed).map_err(|e| format!("复制转接程序失败: {}", e))?;
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let script = format!("#!/bin/sh\nexec '{}' \"$@\"\n", exe.display());
+        std::fs::write(installed, script).map_err(|e| format!("写入转接程序失败: {}", e))?;
+        std::fs::set_permissions(installed, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("写入转接程序失败: {}", e))?;
+    }
+    Ok(())
+}
+
+// 第一个地址不可用时应切换到模拟服务
+async fn check_failover(base: &str) -> Result<String, String> {
+    crate::endpoints::set_endpoint_settings(EndpointSettings {
+        profile: EnvironmentProfile::Custom,
+        staging: None,
+        custom: Some(Endpoints {
+            api_hosts: vec!["http://127.0.0.1:1".to_string(), base.to_string()],
+            access_host: base.to_string(),
+        }),
+    })?;
+    crate::endpoints::check_health().await;
+    let active = crate::endpoints::api_base();
+    if active != base {
+        return Err(format!("未切换到可用地址，当前为 {}", active));
+    }
+    Ok(format!("跳过不可用地址，使用 {}", active))
+}
+
+async fn run_checks(app_dir: &Path) -> Result<SelfTestReport, String> {
+    let archive_path = std::env::var_os(FRPC_ARCHIVE_ENV).ok_or_else(|| {
+        format!(
+            "未设置 {}，请指定 OpenFrp 发布的 frpc 安装包",
+            FRPC_ARCHIVE_ENV
+        )
+    })?;
+    let archive =
+        std::fs::read(&archive_path).map_err(|e| format!("读取 frpc 安装包失败: {}", e))?;
+    let frps = LocalFrps::start(app_dir)?;
+    std::env::set_var(SHIM_PORT_ENV, frps.bind_port.to_string());
+    let api = MockApi::start(archive)?;
+    let harness = Harness {
+        api,
+        frps,
+        processes: FrpcProcesses::default(),
+        sink: Arc::new(CaptureSink::default()),
+    };
+
+    let mut checks = Vec::new();
+    step(&mut checks, "api", "API 地址切换", || {
+        check_failover(&harness.api.base)
+    })
+    .await;
+    step(&mut checks, "api", "隧道列表", || {
+        harness.check_proxies()
+    })
+    .await;
+    step(&mut checks, "download", "下载安装 frpc", || {
+        harness.check_download()
+    })
+    .await;
+    step(&mut checks, "process", "启动隧道", || {
+        harness.check_start()
+    })
+    .await;
+    step(&mut checks, "process", "日志输出", || {
+        harness.check_logs()
+    })
+    .await;
+    step(&mut checks, "process", "重启隧道", || {
+        harness.check_restart()
+    })
+    .await;
+    step(&mut checks, "process", "停止隧道", || {
+        harness.check_stop()
+    })
+    .await;
+
+    let _ = crate::stop_frpc(&harness.processes, INSTANCE_ID);
+    Ok(SelfTestReport {
+        passed: checks.iter().all(|c| c.status == CheckStatus::Pass),
+        app_dir: app_dir.to_string_lossy().to_string(),
+        checks,
+    })
+}
+
+// 运行自检并输出结果，返回进程退出码
+pub fn run(json: bool, keep: bool) -> i32 {
+    let app_dir: PathBuf =
+        std::env::temp_dir().join(format!("openfrp-cpl-selftest-{}", std::process::id()));
+    if let Err(e) = crate::prepare_app_directory(app_dir.clone()) {
+        eprintln!("初始化临时程序目录失败: {}", e);
+        return 1;
+    }
+    // 模拟服务在本机，不经过系统代理
+    std::env::set_var("BYPASS_PROXY", "true");
+    // 检查启动参数是否传给 frpc
+    std::env::set_var("FRPC_DEBUG", "true");
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("创建运行时失败: {}", e);
+            return 1;
+        }
+    };
+    let result = runtime.block_on(run_checks(&app_dir));
+    let code = match result {
+        Ok(report) => {
+            if json {
+                match serde_json::to_string_pretty(&report) {
+                    Ok(output) => println!("{}", output),
+                    Err(e) => eprintln!("序列化自检结果失败: {}", e),
+                }
+            } else {
+                for c in &report.checks {
+                    println!(
+                        "[{}] {}: {}（{:.0}ms）",
+                        c.status.label(),
+                        c.name,
+                        c.message,
+                        c.duration_ms
+                    );
+                }
+                println!("\n自检{}", if report.passed { "通过" } else { "未通过" });
+            }
+            i32::from(!report.passed)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    };
+    if keep {
+        println!("临时程序目录: {}", app_dir.display());
+    } else {
+        let _ = std::fs::remove_dir_all(&app_dir);
+    }
+    code
+}
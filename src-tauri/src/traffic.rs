@@ -0,0 +1,753 @@
+use chrono::{Datelike, Local, NaiveDate, TimeZone};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::command;
+
+use crate::{load_config, read_config, save_config, EventSink, FrpcProcesses};
+
+// 流量统计：定期读取各 frpc 进程的读写字节数，累加到按小时和按天的统计中，
+// 保存到 traffic/stats.json，并发出 traffic-rate 实时速率事件。
+// Linux 读取 /proc/<pid>/io 的 rchar/wchar，Windows 使用 GetProcessIoCounters，macOS 使用 nettop。
+// 界面与守护进程各自统计自己启动的实例，保存时在文件锁内合并，互不覆盖。
+// 前两者统计的是进程全部读写（包括日志、配置文件等），并非严格的网络字节数；
+// frpc 转发的每个字节都会被读入一次、写出一次，读写之和的一半作为经过节点的流量估算值，
+// 事件与提醒中都标明为估算。本月流量接近设置的额度时发出 traffic-quota 事件和桌面通知
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+// 统计写入文件的间隔
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+// 按小时统计保留 7 天，按天统计保留约一年
+const HOURLY_RETENTION_SECS: i64 = 7 * 24 * 3600;
+const DAILY_RETENTION_SECS: i64 = 400 * 24 * 3600;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TrafficSettings {
+    pub enabled: bool,
+    // 每月流量额度（MiB），为空时不提醒
+    pub monthly_quota_mb: Option<u64>,
+    // 本月流量达到额度的这些百分比时提醒
+    pub warn_percents: Vec<u8>,
+    pub notify: bool,
+}
+
+impl Default for TrafficSettings {
+    fn default() -> Self {
+        TrafficSettings {
+            enabled: true,
+            monthly_quota_mb: None,
+            warn_percents: vec![80, 100],
+            notify: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+pub struct Bucket {
+    // frpc 读入与写出的字节数
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+impl Bucket {
+    fn add(&mut self, other: Bucket) {
+        self.read_bytes += other.read_bytes;
+        self.write_bytes += other.write_bytes;
+    }
+
+    // 经过节点的流量估算值
+    fn traffic_bytes(&self) -> u64 {
+        (self.read_bytes + self.write_bytes) / 2
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct TrafficStore {
+    // 实例 ID 到以小时开始时间为键的统计
+    hourly: HashMap<String, BTreeMap<i64, Bucket>>,
+    // 实例 ID 到以当地日期零点为键的统计
+    daily: HashMap<String, BTreeMap<i64, Bucket>>,
+    // 已提醒过的月份（如 2026-10）及百分比
+    quota_month: String,
+    quota_warned: Vec<u8>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum TrafficRange {
+    // 最近 24 小时，按小时
+    #[serde(rename = "24h")]
+    Day,
+    // 最近 7、30、90 天，按天
+    #[serde(rename = "7d")]
+    Week,
+    #[serde(rename = "30d")]
+    Month,
+    #[serde(rename = "90d")]
+    Quarter,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TrafficPoint {
+    // 时间段的开始时间
+    pub start: i64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub traffic_bytes: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TrafficStats {
+    // 实例 ID，为空时为全部实例之和
+    pub id: Option<String>,
+    // 每个数据点的时长（秒），按天统计时为 86400
+    pub granularity_secs: i64,
+    pub points: Vec<TrafficPoint>,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    // 由进程读写字节数估算
+    pub traffic_bytes: u64,
+    pub estimated: bool,
+    // 本月全部实例的流量及额度
+    pub month_traffic_bytes: u64,
+    pub monthly_quota_bytes: Option<u64>,
+    // 当前平台能否统计
+    pub supported: bool,
+}
+
+// 上一次读取的计数，pid 变化说明实例已重启
+struct Sample {
+    pid: u32,
+    counters: Bucket,
+    at: Instant,
+}
+
+// 最近一次保存时文件中的统计加上本进程之后新增的部分，用于查询
+static STORE: Lazy<Mutex<TrafficStore>> = Lazy::new(|| Mutex::new(load_store()));
+// 本进程上次保存之后新增的部分，保存时累加到文件中
+static PENDING: Lazy<Mutex<TrafficStore>> = Lazy::new(|| Mutex::new(TrafficStore::default()));
+static LAST: Lazy<Mutex<HashMap<String, Sample>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn store_path() -> PathBuf {
+    crate::get_app_dir().join("traffic").join("stats.json")
+}
+
+fn load_store() -> TrafficStore {
+    std::fs::read_to_string(store_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_store(store: &TrafficStore) {
+    match serde_json::to_string(store) {
+        Ok(content) => {
+            if let Err(e) = crate::write_atomic(&store_path(), content.as_bytes()) {
+                println!("保存流量统计失败: {}", e);
+            }
+        }
+        Err(e) => println!("序列化流量统计失败: {}", e),
+    }
+}
+
+pub fn settings() -> TrafficSettings {
+    read_config()
+        .ok()
+        .and_then(|cfg| cfg.traffic)
+        .unwrap_or_default()
+}
+
+pub fn supported() -> bool {
+    cfg!(any(
+        target_os = "linux",
+        target_os = "windows",
+        target_os = "macos"
+    ))
+}
+
+// 进程自启动以来读入与写出的字节数
+#[cfg(target_os = "linux")]
+fn io_counters(pid: u32) -> Option<Bucket> {
+    let content = std::fs::read_to_string(format!("/proc/{}/io", pid)).ok()?;
+    let field = |name: &str| {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|value| value.trim().parse().ok())
+    };
+    Some(Bucket {
+        read_bytes: field("rchar:")?,
+        write_bytes: field("wchar:")?,
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn io_counters(pid: u32) -> Option<Bucket> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        GetProcessIoCounters, OpenProcess, IO_COUNTERS, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return None;
+        }
+        let mut counters: IO_COUNTERS = std::mem::zeroed();
+        let ok = GetProcessIoCounters(handle, &mut counters);
+        CloseHandle(handle);
+        (ok != 0).then_some(Bucket {
+            read_bytes: counters.ReadTransferCount,
+            write_bytes: counters.WriteTransferCount,
+        })
+    }
+}
+
+// nettop 输出形如 "时间,frpc.1234,接收字节,发送字节,"
+#[cfg(target_os = "macos")]
+fn io_counters(pid: u32) -> Option<Bucket> {
+    let output = std::process::Command::new("nettop")
+        .args(["-P", "-x", "-L", "1", "-J", "bytes_in,bytes_out"])
+        .args(["-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let suffix = format!(".{}", pid);
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            let fields: Vec<&str> = line.split(',').collect();
+            let index = fields.iter().position(|f| f.ends_with(&suffix))?;
+            Some(Bucket {
+                read_bytes: fields.get(index + 1)?.trim().parse().ok()?,
+                write_bytes: fields.get(index + 2)?.trim().parse().ok()?,
+            })
+        })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn io_counters(_pid: u32) -> Option<Bucket> {
+    None
+}
+
+fn hour_start(ts: i64) -> i64 {
+    ts - ts.rem_euclid(3600)
+}
+
+fn midnight(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|t| Local.from_local_datetime(&t).earliest())
+        .map(|t| t.timestamp())
+        .unwrap_or_default()
+}
+
+fn local_date(ts: i64) -> NaiveDate {
+    Local
+        .timestamp_opt(ts, 0)
+        .single()
+        .map(|t| t.date_naive())
+        .unwrap_or_else(|| Local::now().date_naive())
+}
+
+// today 所在月份全部实例经过节点的流量
+fn month_traffic(store: &TrafficStore, today: NaiveDate) -> u64 {
+    let start = midnight(today.with_day(1).unwrap_or(today));
+    store
+        .daily
+        .values()
+        .flat_map(|days| days.range(start..))
+        .map(|(_, bucket)| bucket.traffic_bytes())
+        .sum()
+}
+
+fn record(store: &mut TrafficStore, id: &str, delta: Bucket, now: i64) {
+    store
+        .hourly
+        .entry(id.to_string())
+        .or_default()
+        .entry(hour_start(now))
+        .or_default()
+        .add(delta);
+    store
+        .daily
+        .entry(id.to_string())
+        .or_default()
+        .entry(midnight(local_date(now)))
+        .or_default()
+        .add(delta);
+}
+
+fn merge_series(
+    target: &mut HashMap<String, BTreeMap<i64, Bucket>>,
+    source: HashMap<String, BTreeMap<i64, Bucket>>,
+) {
+    for (id, buckets) in source {
+        let series = target.entry(id).or_default();
+        for (start, bucket) in buckets {
+            series.entry(start).or_default().add(bucket);
+        }
+    }
+}
+
+// 重新读取文件，累加本进程新增的统计后写回；额度提醒也在锁内检查，
+// 避免界面与守护进程重复提醒
+fn flush(sink: &Arc<dyn EventSink>, settings: &TrafficSettings) {
+    let path = store_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let result = crate::with_file_lock(&path, || {
+        let mut store = load_store();
+        let pending = std::mem::take(&mut *PENDING.lock().unwrap());
+        merge_series(&mut store.hourly, pending.hourly);
+        merge_series(&mut store.daily, pending.daily);
+        check_quota(&mut store, sink, settings, Local::now().date_naive());
+        prune(&mut store, chrono::Utc::now().timestamp());
+        save_store(&store);
+        *STORE.lock().unwrap() = store;
+    });
+    if let Err(e) = result {
+        println!("锁定流量统计失败: {}", e);
+    }
+}
+
+fn prune(store: &mut TrafficStore, now: i64) {
+    for hours in store.hourly.values_mut() {
+        hours.retain(|start, _| *start >= now - HOURLY_RETENTION_SECS);
+    }
+    for days in store.daily.values_mut() {
+        days.retain(|start, _| *start >= now - DAILY_RETENTION_SECS);
+    }
+    store.hourly.retain(|_, hours| !hours.is_empty());
+    store.daily.retain(|_, days| !days.is_empty());
+}
+
+// 本月流量达到新的提醒百分比时发出提醒，每月每个百分比只提醒一次
+fn check_quota(
+    store: &mut TrafficStore,
+    sink: &Arc<dyn EventSink>,
+    settings: &TrafficSettings,
+    today: NaiveDate,
+) {
+    let Some(quota_mb) = settings.monthly_quota_mb.filter(|q| *q > 0) else {
+        return;
+    };
+    let month = today.format("%Y-%m").to_string();
+    if store.quota_month != month {
+        store.quota_month = month;
+        store.quota_warned.clear();
+    }
+    let used = month_traffic(store, today);
+    let quota = quota_mb * 1024 * 1024;
+    let percent = used.saturating_mul(100) / quota;
+    let Some(reached) = settings
+        .warn_percents
+        .iter()
+        .copied()
+        .filter(|p| u64::from(*p) <= percent && !store.quota_warned.contains(p))
+        .max()
+    else {
+        return;
+    };
+    // 一次跨过多个百分比时只提醒最高的一个
+    store
+        .quota_warned
+        .extend(settings.warn_percents.iter().filter(|p| **p <= reached));
+    let used_mb = used / 1024 / 1024;
+    sink.emit_event(
+        "traffic-quota",
+        serde_json::json!({
+            "percent": reached,
+            "used_bytes": used,
+            "quota_bytes": quota,
+            "estimated": true,
+        }),
+    );
+    if settings.notify {
+        sink.notify(
+            &format!("本月流量已达到额度的 {}%", reached),
+            &format!(
+                "按 frpc 读写量估算，本月已使用约 {} MiB，额度为 {} MiB",
+                used_mb, quota_mb
+            ),
+        );
+    }
+}
+
+// 与上一次读取相比新增的字节数。pid 变化或计数变小都说明实例已重启
+// （pid 可能被新进程复用），新进程的计数从零开始
+fn counter_delta(previous: Option<&Sample>, pid: u32, counters: Bucket) -> Bucket {
+    match previous {
+        Some(p)
+            if p.pid == pid
+                && counters.read_bytes >= p.counters.read_bytes
+                && counters.write_bytes >= p.counters.write_bytes =>
+        {
+            Bucket {
+                read_bytes: counters.read_bytes - p.counters.read_bytes,
+                write_bytes: counters.write_bytes - p.counters.write_bytes,
+            }
+        }
+        _ => counters,
+    }
+}
+
+// 运行中实例的 ID、隧道 ID 与计数，读取计数可能较慢，在阻塞线程中执行
+async fn read_running(processes: &FrpcProcesses) -> Vec<(String, String, u32, Option<Bucket>)> {
+    let running: Vec<(String, String, u32)> = {
+        let map = processes.0.lock().unwrap();
+        map.iter()
+            .map(|(id, info)| (id.clone(), info.tunnel_id.clone(), info.child.id()))
+            .collect()
+    };
+    tokio::task::spawn_blocking(move || {
+        running
+            .into_iter()
+            .map(|(id, tunnel_id, pid)| (id, tunnel_id, pid, io_counters(pid)))
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+// 累加一轮读取的计数并发出实时速率，返回统计是否有变化
+fn record_readings(
+    readings: Vec<(String, String, u32, Option<Bucket>)>,
+    sink: &Arc<dyn EventSink>,
+) -> bool {
+    let mut last = LAST.lock().unwrap();
+    last.retain(|id, _| readings.iter().any(|(running, ..)| running == id));
+
+    let now = chrono::Utc::now().timestamp();
+    let today = midnight(local_date(now));
+    let mut changed = false;
+    let mut store = STORE.lock().unwrap();
+    let mut pending = PENDING.lock().unwrap();
+    for (id, tunnel_id, pid, counters) in readings {
+        let Some(counters) = counters else {
+            continue;
+        };
+        let at = Instant::now();
+        let previous = last.insert(id.clone(), Sample { pid, counters, at });
+        let delta = counter_delta(previous.as_ref(), pid, counters);
+        let elapsed = previous
+            .filter(|p| p.pid == pid)
+            .map(|p| at.duration_since(p.at))
+            .unwrap_or(SAMPLE_INTERVAL);
+        if delta.read_bytes > 0 || delta.write_bytes > 0 {
+            record(&mut store, &id, delta, now);
+            record(&mut pending, &id, delta, now);
+            changed = true;
+        }
+        let secs = elapsed.as_secs_f64().max(1.0);
+        let today_traffic = store
+            .daily
+            .get(&id)
+            .and_then(|days| days.get(&today))
+            .map(Bucket::traffic_bytes)
+            .unwrap_or_default();
+        sink.emit_event(
+            "traffic-rate",
+            serde_json::json!({
+                "id": id,
+                "tunnel_id": tunnel_id,
+                "read_bytes_per_sec": (delta.read_bytes as f64 / secs) as u64,
+                "write_bytes_per_sec": (delta.write_bytes as f64 / secs) as u64,
+                "traffic_bytes_per_sec": (delta.traffic_bytes() as f64 / secs) as u64,
+                "today_traffic_bytes": today_traffic,
+                "estimated": true,
+            }),
+        );
+    }
+    changed
+}
+
+// 在后台持续统计，界面与守护进程各启动一次
+pub fn spawn(processes: FrpcProcesses, sink: Arc<dyn EventSink>) {
+    if !supported() {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        let mut last_save = Instant::now();
+        let mut dirty = false;
+        loop {
+            let settings = settings();
+            if settings.enabled {
+                let readings = read_running(&processes).await;
+                dirty |= record_readings(readings, &sink);
+            }
+            if last_save.elapsed() >= SAVE_INTERVAL {
+                if dirty {
+                    flush(&sink, &settings);
+                    dirty = false;
+                } else {
+                    // 读取另一个进程保存的统计
+                    *STORE.lock().unwrap() = load_store();
+                }
+                last_save = Instant::now();
+            }
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+        }
+    });
+}
+
+// 实例的流量统计，id 为空时返回全部实例之和
+#[command]
+pub fn get_traffic_stats(id: Option<String>, range: TrafficRange) -> TrafficStats {
+    let store = STORE.lock().unwrap();
+    let now = chrono::Utc::now().timestamp();
+    let (source, starts, granularity_secs) = match range {
+        TrafficRange::Day => {
+            let current = hour_start(now);
+            let starts: Vec<i64> = (0..24).rev().map(|i| current - i * 3600).collect();
+            (&store.hourly, starts, 3600)
+        }
+        TrafficRange::Week | TrafficRange::Month | TrafficRange::Quarter => {
+            let days = match range {
+                TrafficRange::Week => 7,
+                TrafficRange::Month => 30,
+                _ => 90,
+            };
+            let today = Local::now().date_naive();
+            let starts: Vec<i64> = (0..days)
+                .rev()
+                .filter_map(|i| today.checked_sub_days(chrono::Days::new(i)))
+                .map(midnight)
+                .collect();
+            (&store.daily, starts, 86400)
+        }
+    };
+
+    let series: Vec<&BTreeMap<i64, Bucket>> = match &id {
+        Some(id) => source.get(id).into_iter().collect(),
+        None => source.values().collect(),
+    };
+    let mut total = Bucket::default();
+    let points: Vec<TrafficPoint> = starts
+        .into_iter()
+        .map(|start| {
+            let mut bucket = Bucket::default();
+            for buckets in &series {
+                if let Some(b) = buckets.get(&start) {
+                    bucket.add(*b);
+                }
+            }
+            total.add(bucket);
+            TrafficPoint {
+                start,
+                read_bytes: bucket.read_bytes,
+                write_bytes: bucket.write_bytes,
+                traffic_bytes: bucket.traffic_bytes(),
+            }
+        })
+        .collect();
+
+    TrafficStats {
+        id,
+        granularity_secs,
+        points,
+        read_bytes: total.read_bytes,
+        write_bytes: total.write_bytes,
+        traffic_bytes: total.traffic_bytes(),
+        estimated: true,
+        month_traffic_bytes: month_traffic(&store, Local::now().date_naive()),
+        monthly_quota_bytes: settings().monthly_quota_mb.map(|q| q * 1024 * 1024),
+        supported: supported(),
+    }
+}
+
+#[command]
+pub fn get_traffic_settings() -> Result<TrafficSettings, String> {
+    Ok(settings())
+}
+
+#[command]
+pub fn set_traffic_settings(settings: TrafficSettings) -> Result<(), String> {
+    let mut cfg = load_config()?;
+    cfg.traffic = Some(settings);
+    save_config(&cfg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    // 记录提醒事件
+    #[derive(Default)]
+    struct QuotaEvents(Mutex<Vec<u64>>);
+
+    impl EventSink for QuotaEvents {
+        fn emit_log(&self, _event: &str, _message: String) {}
+
+        fn emit_event(&self, event: &str, payload: serde_json::Value) {
+            if event == "traffic-quota" {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(payload["percent"].as_u64().unwrap());
+            }
+        }
+
+        fn notify(&self, _title: &str, _body: &str) {}
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    // 当天中午，避开夏令时切换
+    fn noon(date: NaiveDate) -> i64 {
+        midnight(date) + 12 * 3600
+    }
+
+    // 读写各 mib MiB，估算流量为 mib MiB
+    fn traffic(mib: u64) -> Bucket {
+        Bucket {
+            read_bytes: mib * MIB,
+            write_bytes: mib * MIB,
+        }
+    }
+
+    fn sample(pid: u32, read_bytes: u64, write_bytes: u64) -> Sample {
+        Sample {
+            pid,
+            counters: Bucket {
+                read_bytes,
+                write_bytes,
+            },
+            at: Instant::now(),
+        }
+    }
+
+    fn quota_settings() -> TrafficSettings {
+        TrafficSettings {
+            monthly_quota_mb: Some(100),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn delta_between_readings() {
+        let counters = Bucket {
+            read_bytes: 1500,
+            write_bytes: 800,
+        };
+        let delta = counter_delta(Some(&sample(42, 1000, 500)), 42, counters);
+        assert_eq!((delta.read_bytes, delta.write_bytes), (500, 300));
+        // 首次读取，计数从零开始
+        let delta = counter_delta(None, 42, counters);
+        assert_eq!((delta.read_bytes, delta.write_bytes), (1500, 800));
+    }
+
+    #[test]
+    fn delta_resets_on_restart() {
+        let counters = Bucket {
+            read_bytes: 300,
+            write_bytes: 200,
+        };
+        // pid 变化
+        let delta = counter_delta(Some(&sample(42, 1000, 100)), 43, counters);
+        assert_eq!((delta.read_bytes, delta.write_bytes), (300, 200));
+        // pid 被复用但计数变小
+        let delta = counter_delta(Some(&sample(42, 1000, 100)), 42, counters);
+        assert_eq!((delta.read_bytes, delta.write_bytes), (300, 200));
+    }
+
+    #[test]
+    fn record_into_hour_and_day() {
+        let mut store = TrafficStore::default();
+        let now = noon(date(2026, 10, 15));
+        record(&mut store, "a", traffic(1), now);
+        record(&mut store, "a", traffic(2), now + 60);
+        record(&mut store, "a", traffic(4), now + 3600);
+        let hours = &store.hourly["a"];
+        assert_eq!(hours.len(), 2);
+        assert_eq!(hours[&hour_start(now)].traffic_bytes(), 3 * MIB);
+        let days = &store.daily["a"];
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[&midnight(date(2026, 10, 15))].traffic_bytes(), 7 * MIB);
+    }
+
+    #[test]
+    fn month_traffic_counts_current_month() {
+        let mut store = TrafficStore::default();
+        record(&mut store, "a", traffic(10), noon(date(2026, 9, 30)));
+        record(&mut store, "a", traffic(20), noon(date(2026, 10, 1)));
+        record(&mut store, "b", traffic(5), noon(date(2026, 10, 15)));
+        assert_eq!(month_traffic(&store, date(2026, 10, 20)), 25 * MIB);
+        assert_eq!(month_traffic(&store, date(2026, 9, 30)), 35 * MIB);
+        // 进入下个月后重新计算
+        assert_eq!(month_traffic(&store, date(2026, 11, 1)), 0);
+    }
+
+    #[test]
+    fn quota_warns_each_percent_once() {
+        let events = Arc::new(QuotaEvents::default());
+        let sink: Arc<dyn EventSink> = events.clone();
+        let settings = quota_settings();
+        let today = date(2026, 10, 15);
+        let mut store = TrafficStore::default();
+
+        record(&mut store, "a", traffic(50), noon(today));
+        check_quota(&mut store, &sink, &settings, today);
+        assert!(events.0.lock().unwrap().is_empty());
+
+        record(&mut store, "a", traffic(35), noon(today));
+        check_quota(&mut store, &sink, &settings, today);
+        check_quota(&mut store, &sink, &settings, today);
+        assert_eq!(*events.0.lock().unwrap(), vec![80]);
+
+        record(&mut store, "a", traffic(20), noon(today));
+        check_quota(&mut store, &sink, &settings, today);
+        check_quota(&mut store, &sink, &settings, today);
+        assert_eq!(*events.0.lock().unwrap(), vec![80, 100]);
+    }
+
+    #[test]
+    fn quota_warns_only_highest_crossed() {
+        let events = Arc::new(QuotaEvents::default());
+        let sink: Arc<dyn EventSink> = events.clone();
+        let today = date(2026, 10, 15);
+        let mut store = TrafficStore::default();
+        record(&mut store, "a", traffic(120), noon(today));
+        check_quota(&mut store, &sink, &quota_settings(), today);
+        assert_eq!(*events.0.lock().unwrap(), vec![100]);
+        assert_eq!(store.quota_warned, vec![80, 100]);
+    }
+
+    #[test]
+    fn quota_resets_next_month() {
+        let events = Arc::new(QuotaEvents::default());
+        let sink: Arc<dyn EventSink> = events.clone();
+        let settings = quota_settings();
+        let mut store = TrafficStore::default();
+        record(&mut store, "a", traffic(90), noon(date(2026, 10, 31)));
+        check_quota(&mut store, &sink, &settings, date(2026, 10, 31));
+        assert_eq!(store.quota_month, "2026-10");
+
+        // 上个月的流量不计入本月
+        let next = date(2026, 11, 1);
+        check_quota(&mut store, &sink, &settings, next);
+        assert_eq!(store.quota_month, "2026-11");
+        assert!(store.quota_warned.is_empty());
+        record(&mut store, "a", traffic(85), noon(next));
+        check_quota(&mut store, &sink, &settings, next);
+        assert_eq!(*events.0.lock().unwrap(), vec![80, 80]);
+    }
+
+    #[test]
+    fn quota_disabled_without_limit() {
+        let events = Arc::new(QuotaEvents::default());
+        let sink: Arc<dyn EventSink> = events.clone();
+        let today = date(2026, 10, 15);
+        let mut store = TrafficStore::default();
+        record(&mut store, "a", traffic(1000), noon(today));
+        check_quota(&mut store, &sink, &TrafficSettings::default(), today);
+        assert!(events.0.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,430 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use tauri::command;
+
+use crate::openfrp_api::{OpenFrpClient, Proxy};
+use crate::tunnels::{self, TunnelError, TunnelParams};
+
+// 隧道模板：用 TOML 或 JSON 描述一组隧道，与当前账户的隧道按名称比对后
+// 新建、修改或删除，使账户中的隧道与模板一致。示例：
+//
+// prune = false
+//
+// [[tunnels]]
+// name = "ssh"
+// node_id = 44
+// type = "tcp"
+// local_port = 22
+// remote_port = 27388
+//
+// [[tunnels]]
+// name = "web"
+// node_id = 12
+// type = "http"
+// local_port = 80
+// domains = ["example.com"]
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TunnelSpec {
+    // 是否删除模板中没有的隧道
+    #[serde(default)]
+    pub prune: bool,
+    #[serde(default)]
+    pub tunnels: Vec<TunnelParams>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SpecAction {
+    Create,
+    Update,
+    Delete,
+    Unchanged,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlanItem {
+    pub action: SpecAction,
+    pub name: String,
+    pub proxy_id: Option<i64>,
+    // 需要修改的字段
+    pub changes: Vec<String>,
+    // 不阻止执行的提示，如本地端口暂未监听（模板可能先于本地服务部署）
+    pub warnings: Vec<String>,
+    // 执行结果，仅预览或未执行时为 None
+    pub applied: Option<bool>,
+    pub message: Option<String>,
+    pub error: Option<TunnelError>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SpecReport {
+    pub dry_run: bool,
+    // 模板中的隧道是否全部通过检查，未通过时不会执行任何修改
+    pub valid: bool,
+    pub plan: Vec<PlanItem>,
+}
+
+fn invalid_spec(message: String) -> TunnelError {
+    TunnelError::InvalidSpec { message }
+}
+
+// 解析模板，format 为 toml 或 json，未指定时先按 JSON 再按 TOML 解析
+pub fn parse_spec(content: &str, format: Option<&str>) -> Result<TunnelSpec, TunnelError> {
+    let from_json = |content: &str| {
+        serde_json::from_str::<TunnelSpec>(content)
+            .map_err(|e| invalid_spec(format!("解析 JSON 模板失败: {}", e)))
+    };
+    let from_toml = |content: &str| {
+        toml::from_str::<TunnelSpec>(content)
+            .map_err(|e| invalid_spec(format!("解析 TOML 模板失败: {}", e)))
+    };
+    let spec = match format.map(str::to_lowercase).as_deref() {
+        Some("json") => from_json(content)?,
+        Some("toml") => from_toml(content)?,
+        Some(other) => return Err(invalid_spec(format!("不支持的模板格式: {}", other))),
+        None if content.trim_start().starts_with('{') => from_json(content)?,
+        None => from_toml(content)?,
+    };
+
+    let mut names = HashSet::new();
+    for tunnel in &spec.tunnels {
+        if !names.insert(tunnel.name.trim().to_string()) {
+            return Err(invalid_spec(format!(
+                "模板中的隧道名称重复: {}",
+                tunnel.name
+            )));
+        }
+    }
+    Ok(spec)
+}
+
+pub fn load_spec(path: &Path) -> Result<TunnelSpec, TunnelError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| invalid_spec(format!("读取模板 {} 失败: {}", path.display(), e)))?;
+    let format = path.extension().and_then(|ext| ext.to_str());
+    parse_spec(&content, format)
+}
+
+// 比较模板与现有隧道，返回不一致的字段
+fn diff(params: &TunnelParams, proxy: &Proxy) -> Vec<String> {
+    let config = params.to_config(Some(proxy.id));
+    let mut changes = Vec::new();
+    let mut check = |field: &str, same: bool| {
+        if !same {
+            changes.push(field.to_string());
+        }
+    };
+    check("node_id", config.node_id == proxy.nid);
+    check(
+        "type",
+        config.proxy_type.eq_ignore_ascii_case(&proxy.proxy_type),
+    );
+    check("local_addr", config.local_addr == proxy.local_ip);
+    check(
+        "local_port",
+        i64::from(params.local_port) == proxy.local_port,
+    );
+    if params.proxy_type.uses_remote_port() {
+        check("remote_port", config.remote_port == proxy.remote_port);
+    }
+    if params.proxy_type.uses_domain() {
+        let current: Vec<String> = proxy
+            .domain
+            .as_deref()
+            .and_then(|d| serde_json::from_str(d).ok())
+            .unwrap_or_default();
+        let wanted: Vec<String> = params
+            .domains
+            .iter()
+            .map(|d| d.trim().to_string())
+            .collect();
+        check("domains", current == wanted);
+        check("force_https", params.force_https == proxy.force_https);
+        check(
+            "auto_tls",
+            config.auto_tls == proxy.auto_tls.as_deref().unwrap_or("false"),
+        );
+    }
+    check(
+        "proxy_protocol",
+        params.proxy_protocol == proxy.proxy_protocol_version,
+    );
+    check("encrypt", params.encrypt == proxy.use_encryption);
+    check("compress", params.compress == proxy.use_compression);
+    check(
+        "custom",
+        params.custom.trim() == proxy.custom.as_deref().unwrap_or("").trim(),
+    );
+    changes
+}
+
+// 执行顺序：先删除再修改和新建，释放远程端口与隧道数量，同类操作保持模板顺序
+fn execution_order(plan: &[PlanItem]) -> Vec<usize> {
+    [SpecAction::Delete, SpecAction::Update, SpecAction::Create]
+        .iter()
+        .flat_map(|action| {
+            plan.iter()
+                .enumerate()
+                .filter(move |(_, item)| item.action == *action)
+                .map(|(index, _)| index)
+        })
+        .collect()
+}
+
+fn plan_item(action: SpecAction, name: &str, proxy_id: Option<i64>) -> PlanItem {
+    PlanItem {
+        action,
+        name: name.to_string(),
+        proxy_id,
+        changes: Vec::new(),
+        warnings: Vec::new(),
+        applied: None,
+        message: None,
+        error: None,
+    }
+}
+
+// 按名称比对模板与现有隧道并检查参数，dry_run 为 false 且全部通过检查时执行修改
+pub async fn apply(spec: &TunnelSpec, dry_run: bool) -> Result<SpecReport, TunnelError> {
+    let client = OpenFrpClient::new()?;
+    let proxies = client.user_proxies().await?;
+    if proxies.stale && !dry_run {
+        return Err(invalid_spec(
+            "无法连接服务器，只能预览基于缓存数据的修改计划".to_string(),
+        ));
+    }
+    let proxies = proxies.data.list;
+    let nodes = client.node_list().await?.data.list;
+
+    let mut plan = Vec::new();
+    for params in &spec.tunnels {
+        let name = params.name.trim();
+        let existing = proxies.iter().find(|p| p.proxy_name == name);
+        let mut item = match existing {
+            None => plan_item(SpecAction::Create, name, None),
+            Some(proxy) => {
+                let changes = diff(params, proxy);
+                let action = if changes.is_empty() {
+                    SpecAction::Unchanged
+                } else {
+                    SpecAction::Update
+                };
+                PlanItem {
+                    changes,
+                    ..plan_item(action, name, Some(proxy.id))
+                }
+            }
+        };
+        if item.action != SpecAction::Unchanged {
+            match tunnels::validate_params(params, &nodes) {
+                Ok(()) => {
+                    if let Err(e) = tunnels::check_local_port(params).await {
+                        item.warnings.push(e.to_string());
+                    }
+                }
+                Err(e) => {
+                    item.message = Some(e.to_string());
+                    item.error = Some(e);
+                }
+            }
+        }
+        plan.push(item);
+    }
+    if spec.prune {
+        for proxy in &proxies {
+            if !spec
+                .tunnels
+                .iter()
+                .any(|t| t.name.trim() == proxy.proxy_name)
+            {
+                plan.push(plan_item(
+                    SpecAction::Delete,
+                    &proxy.proxy_name,
+                    Some(proxy.id),
+                ));
+            }
+        }
+    }
+
+    let valid = plan.iter().all(|item| item.error.is_none());
+    if dry_run || !valid {
+        return Ok(SpecReport {
+            dry_run,
+            valid,
+            plan,
+        });
+    }
+
+    // plan 的前 spec.tunnels.len() 项与模板中的隧道一一对应
+    for index in execution_order(&plan) {
+        let item = &mut plan[index];
+        let result = match (item.action, item.proxy_id) {
+            (SpecAction::Delete, Some(id)) => client.remove_proxy(id).await,
+            (SpecAction::Update, Some(id)) => {
+                client
+                    .edit_proxy(&spec.tunnels[index].to_config(Some(id)))
+                    .await
+            }
+            (SpecAction::Create, _) => client.new_proxy(&spec.tunnels[index].to_config(None)).await,
+            _ => continue,
+        };
+        match result {
+            Ok(message) => {
+                item.applied = Some(true);
+                item.message = Some(message);
+            }
+            Err(e) => {
+                let e = TunnelError::from(e);
+                item.applied = Some(false);
+                item.message = Some(e.to_string());
+                item.error = Some(e);
+            }
+        }
+    }
+    Ok(SpecReport {
+        dry_run,
+        valid,
+        plan,
+    })
+}
+
+// path 与 content 二选一；dry_run 为 true 时只返回修改计划
+#[command]
+pub async fn apply_tunnel_spec(
+    path: Option<String>,
+    content: Option<String>,
+    format: Option<String>,
+    dry_run: bool,
+) -> Result<SpecReport, TunnelError> {
+    let spec = match (path, content) {
+        (Some(path), _) => load_spec(Path::new(&path))?,
+        (None, Some(content)) => parse_spec(&content, format.as_deref())?,
+        (None, None) => return Err(invalid_spec("请提供模板文件路径或内容".to_string())),
+    };
+    apply(&spec, dry_run).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tunnels::ProxyType;
+
+    const TOML_SPEC: &str = r#"
+prune = true
+
+[[tunnels]]
+name = "ssh"
+node_id = 44
+type = "tcp"
+local_port = 22
+remote_port = 27388
+
+[[tunnels]]
+name = "web"
+node_id = 12
+type = "http"
+local_port = 80
+domains = ["example.com"]
+"#;
+
+    fn as_json(spec: &TunnelSpec) -> serde_json::Value {
+        serde_json::to_value(spec).unwrap()
+    }
+
+    #[test]
+    fn parse_toml_and_defaults() {
+        let spec = parse_spec(TOML_SPEC, Some("toml")).unwrap();
+        assert!(spec.prune);
+        assert_eq!(spec.tunnels.len(), 2);
+        let ssh = &spec.tunnels[0];
+        assert_eq!(ssh.proxy_type, ProxyType::Tcp);
+        assert_eq!(ssh.local_addr, "127.0.0.1");
+        assert_eq!(ssh.remote_port, Some(27388));
+        assert_eq!(spec.tunnels[1].domains, vec!["example.com".to_string()]);
+        // 未指定格式时按内容判断
+        assert_eq!(
+            as_json(&parse_spec(TOML_SPEC, None).unwrap()),
+            as_json(&spec)
+        );
+    }
+
+    #[test]
+    fn toml_and_json_round_trip() {
+        let spec = parse_spec(TOML_SPEC, Some("TOML")).unwrap();
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(
+            as_json(&parse_spec(&json, Some("json")).unwrap()),
+            as_json(&spec)
+        );
+        assert_eq!(as_json(&parse_spec(&json, None).unwrap()), as_json(&spec));
+        let toml = toml::to_string(&spec).unwrap();
+        assert_eq!(
+            as_json(&parse_spec(&toml, Some("toml")).unwrap()),
+            as_json(&spec)
+        );
+    }
+
+    #[test]
+    fn parse_rejects_duplicates_and_unknown_format() {
+        let duplicated = r#"{"tunnels": [
+            {"name": "a", "node_id": 1, "type": "tcp", "local_port": 22, "remote_port": 1},
+            {"name": " a ", "node_id": 2, "type": "udp", "local_port": 53, "remote_port": 2}
+        ]}"#;
+        assert!(parse_spec(duplicated, None)
+            .unwrap_err()
+            .to_string()
+            .contains("重复"));
+        assert!(parse_spec(TOML_SPEC, Some("yaml")).is_err());
+        assert!(parse_spec("{ not json", None).is_err());
+    }
+
+    fn existing(params: &TunnelParams, id: i64) -> Proxy {
+        let config = params.to_config(Some(id));
+        Proxy {
+            id,
+            nid: config.node_id,
+            proxy_name: config.name,
+            proxy_type: config.proxy_type.to_uppercase(),
+            local_ip: config.local_addr,
+            local_port: i64::from(params.local_port),
+            remote_port: config.remote_port,
+            domain: Some(config.domain_bind).filter(|d| !d.is_empty()),
+            auto_tls: Some(config.auto_tls),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn diff_reports_changed_fields() {
+        let spec = parse_spec(TOML_SPEC, None).unwrap();
+        for params in &spec.tunnels {
+            assert!(diff(params, &existing(params, 1)).is_empty());
+        }
+
+        let mut ssh = spec.tunnels[0].clone();
+        let proxy = existing(&ssh, 1);
+        ssh.remote_port = Some(27389);
+        ssh.encrypt = true;
+        assert_eq!(diff(&ssh, &proxy), vec!["remote_port", "encrypt"]);
+
+        let mut web = spec.tunnels[1].clone();
+        let proxy = existing(&web, 2);
+        web.domains.push("www.example.com".to_string());
+        web.force_https = true;
+        assert_eq!(diff(&web, &proxy), vec!["domains", "force_https"]);
+    }
+
+    #[test]
+    fn plan_runs_delete_then_update_then_create() {
+        let plan = vec![
+            plan_item(SpecAction::Create, "new-a", None),
+            plan_item(SpecAction::Update, "changed", Some(2)),
+            plan_item(SpecAction::Unchanged, "same", Some(3)),
+            plan_item(SpecAction::Create, "new-b", None),
+            plan_item(SpecAction::Delete, "old", Some(4)),
+        ];
+        assert_eq!(execution_order(&plan), vec![4, 1, 0, 3]);
+    }
+}
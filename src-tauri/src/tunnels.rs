@@ -0,0 +1,559 @@
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use tauri::command;
+
+use crate::openfrp_api::{ApiError, Node, OpenFrpClient, ProtocolSupport, ProxyConfig};
+
+// 隧道的新建、编辑与删除。提交前先在本地检查端口、节点支持的协议、
+// 节点允许的端口段、域名格式以及本地服务是否在监听，避免只能看到服务端的提示
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TunnelError {
+    // 隧道名称为空或包含不支持的字符
+    InvalidName {
+        message: String,
+    },
+    // field 为 local_port 或 remote_port
+    InvalidPort {
+        field: String,
+        message: String,
+    },
+    // 节点不支持该隧道类型
+    UnsupportedProtocol {
+        proxy_type: ProxyType,
+        node_id: i64,
+        message: String,
+    },
+    // 远程端口不在节点允许的范围内
+    PortNotAllowed {
+        port: u16,
+        min: u16,
+        max: u16,
+        message: String,
+    },
+    InvalidDomain {
+        domain: String,
+        message: String,
+    },
+    // 本地没有程序监听该端口
+    LocalPortNotListening {
+        local_addr: String,
+        local_port: u16,
+        message: String,
+    },
+    NodeNotFound {
+        node_id: i64,
+        message: String,
+    },
+    TunnelNotFound {
+        proxy_id: i64,
+        message: String,
+    },
+    // 隧道模板无法解析或内容有误
+    InvalidSpec {
+        message: String,
+    },
+    // 请求接口失败或服务端拒绝
+    Api {
+        message: String,
+        error: ApiError,
+    },
+}
+
+impl TunnelError {
+    pub fn message(&self) -> &str {
+        match self {
+            TunnelError::InvalidName { message }
+            | TunnelError::InvalidPort { message, .. }
+            | TunnelError::UnsupportedProtocol { message, .. }
+            | TunnelError::PortNotAllowed { message, .. }
+            | TunnelError::InvalidDomain { message, .. }
+            | TunnelError::LocalPortNotListening { message, .. }
+            | TunnelError::NodeNotFound { message, .. }
+            | TunnelError::TunnelNotFound { message, .. }
+            | TunnelError::InvalidSpec { message }
+            | TunnelError::Api { message, .. } => message,
+        }
+    }
+}
+
+impl std::fmt::Display for TunnelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl From<ApiError> for TunnelError {
+    fn from(error: ApiError) -> Self {
+        TunnelError::Api {
+            message: error.to_string(),
+            error,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyType {
+    Tcp,
+    Udp,
+    Http,
+    Https,
+    Stcp,
+    Xtcp,
+}
+
+impl ProxyType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProxyType::Tcp => "tcp",
+            ProxyType::Udp => "udp",
+            ProxyType::Http => "http",
+            ProxyType::Https => "https",
+            ProxyType::Stcp => "stcp",
+            ProxyType::Xtcp => "xtcp",
+        }
+    }
+
+    pub fn supported_by(self, support: &ProtocolSupport) -> bool {
+        match self {
+            ProxyType::Tcp => support.tcp,
+            ProxyType::Udp => support.udp,
+            ProxyType::Http => support.http,
+            ProxyType::Https => support.https,
+            ProxyType::Stcp => support.stcp,
+            ProxyType::Xtcp => support.xtcp,
+        }
+    }
+
+    // HTTP/S 隧道通过域名访问，不使用远程端口
+    pub fn uses_domain(self) -> bool {
+        matches!(self, ProxyType::Http | ProxyType::Https)
+    }
+
+    // 需要在节点上占用远程端口的类型
+    pub fn uses_remote_port(self) -> bool {
+        matches!(self, ProxyType::Tcp | ProxyType::Udp)
+    }
+}
+
+fn default_local_addr() -> String {
+    "127.0.0.1".to_string()
+}
+
+// 新建、编辑隧道的参数
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TunnelParams {
+    pub name: String,
+    pub node_id: i64,
+    #[serde(alias = "type")]
+    pub proxy_type: ProxyType,
+    #[serde(default = "default_local_addr")]
+    pub local_addr: String,
+    pub local_port: u16,
+    // TCP/UDP 隧道必填
+    #[serde(default)]
+    pub remote_port: Option<u16>,
+    // HTTP/S 隧道绑定的域名
+    #[serde(default)]
+    pub domains: Vec<String>,
+    #[serde(default)]
+    pub auto_tls: bool,
+    #[serde(default)]
+    pub force_https: bool,
+    #[serde(default)]
+    pub proxy_protocol: bool,
+    #[serde(default)]
+    pub encrypt: bool,
+    #[serde(default)]
+    pub compress: bool,
+    #[serde(default)]
+    pub custom: String,
+    // 不检查本地端口是否在监听，例如本地服务尚未启动
+    #[serde(default)]
+    pub skip_local_check: bool,
+}
+
+impl TunnelParams {
+    pub fn to_config(&self, proxy_id: Option<i64>) -> ProxyConfig {
+        ProxyConfig {
+            proxy_id,
+            name: self.name.trim().to_string(),
+            proxy_type: self.proxy_type.as_str().to_string(),
+            node_id: self.node_id,
+            local_addr: self.local_addr.trim().to_string(),
+            local_port: self.local_port.to_string(),
+            remote_port: self
+                .remote_port
+                .filter(|_| !self.proxy_type.uses_domain())
+                .map(i64::from),
+            domain_bind: if self.proxy_type.uses_domain() {
+                let domains: Vec<&str> = self.domains.iter().map(|d| d.trim()).collect();
+                serde_json::to_string(&domains).unwrap_or_default()
+            } else {
+                String::new()
+            },
+            auto_tls: self.auto_tls.to_string(),
+            force_https: self.force_https,
+            proxy_protocol_version: self.proxy_protocol,
+            data_encrypt: self.encrypt,
+            data_gzip: self.compress,
+            custom: self.custom.clone(),
+        }
+    }
+}
+
+// 解析节点允许的端口段，如 "(50000,60000)" 或单个端口，为空表示不限制
+pub fn parse_allow_port(allow_port: &str) -> Option<(u16, u16)> {
+    let inner = allow_port
+        .trim()
+        .trim_start_matches(['(', '['])
+        .trim_end_matches([')', ']']);
+    let (min, max) = inner.split_once(',').unwrap_or((inner, inner));
+    let min: u16 = min.trim().parse().ok()?;
+    let max: u16 = max.trim().parse().ok()?;
+    // 兼容上下限写反的配置
+    Some((min.min(max), min.max(max)))
+}
+
+// 检查域名格式，允许 *.example.com 形式的泛域名
+pub fn validate_domain(domain: &str) -> Result<(), String> {
+    let host = domain.strip_prefix("*.").unwrap_or(domain);
+    if host.is_empty() || host.len() > 253 {
+        return Err("域名长度无效".to_string());
+    }
+    let labels: Vec<&str> = host.split('.').collect();
+    if labels.len() < 2 {
+        return Err("域名至少需要包含两级".to_string());
+    }
+    for label in &labels {
+        if label.is_empty() || label.len() > 63 {
+            return Err("域名中每一级的长度应为 1-63 个字符".to_string());
+        }
+        if !label.is_ascii() {
+            return Err("中文等国际化域名请填写 Punycode 形式（xn--）".to_string());
+        }
+        if !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err("域名只能包含字母、数字和连字符".to_string());
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err("域名的每一级不能以连字符开头或结尾".to_string());
+        }
+    }
+    if labels
+        .last()
+        .is_some_and(|tld| tld.chars().all(|c| c.is_ascii_digit()))
+    {
+        return Err("顶级域名不能为纯数字".to_string());
+    }
+    Ok(())
+}
+
+// 检查隧道名称，OpenFrp 的隧道名称不支持中文等非 ASCII 字符
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("隧道名称不能为空".to_string());
+    }
+    if !name.is_ascii() {
+        return Err("隧道名称不支持中文等非 ASCII 字符".to_string());
+    }
+    if name.chars().any(|c| c.is_ascii_control()) {
+        return Err("隧道名称不能包含控制字符".to_string());
+    }
+    Ok(())
+}
+
+// 检查不依赖节点信息的字段
+pub fn validate_fields(params: &TunnelParams) -> Result<(), TunnelError> {
+    validate_name(&params.name).map_err(|message| TunnelError::InvalidName { message })?;
+
+    if params.local_port == 0 {
+        return Err(TunnelError::InvalidPort {
+            field: "local_port".to_string(),
+            message: "本地端口应为 1-65535".to_string(),
+        });
+    }
+    if params.proxy_type.uses_remote_port() {
+        match params.remote_port {
+            None => {
+                return Err(TunnelError::InvalidPort {
+                    field: "remote_port".to_string(),
+                    message: format!(
+                        "{} 隧道需要指定远程端口",
+                        params.proxy_type.as_str().to_uppercase()
+                    ),
+                })
+            }
+            Some(0) => {
+                return Err(TunnelError::InvalidPort {
+                    field: "remote_port".to_string(),
+                    message: "远程端口应为 1-65535".to_string(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    if params.proxy_type.uses_domain() {
+        if params.domains.is_empty() {
+            return Err(TunnelError::InvalidDomain {
+                domain: String::new(),
+                message: "HTTP/S 隧道至少需要绑定一个域名".to_string(),
+            });
+        }
+        for domain in &params.domains {
+            validate_domain(domain.trim()).map_err(|e| TunnelError::InvalidDomain {
+                domain: domain.clone(),
+                message: format!("域名 {} 无效: {}", domain, e),
+            })?;
+        }
+    }
+    Ok(())
+}
+
+// 检查节点是否支持该隧道类型以及远程端口是否在允许范围内
+pub fn validate_for_node(params: &TunnelParams, node: &Node) -> Result<(), TunnelError> {
+    if !params.proxy_type.supported_by(&node.protocol_support) {
+        return Err(TunnelError::UnsupportedProtocol {
+            proxy_type: params.proxy_type,
+            node_id: node.id,
+            message: format!(
+                "节点 {} 不支持 {} 隧道",
+                node.name,
+                params.proxy_type.as_str().to_uppercase()
+            ),
+        });
+    }
+    if let (true, Some(port)) = (params.proxy_type.uses_remote_port(), params.remote_port) {
+        if let Some((min, max)) = node.allow_port.as_deref().and_then(parse_allow_port) {
+            if port < min || port > max {
+                return Err(TunnelError::PortNotAllowed {
+                    port,
+                    min,
+                    max,
+                    message: format!(
+                        "节点 {} 只允许使用 {}-{} 之间的远程端口",
+                        node.name, min, max
+                    ),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+// 本地地址是否指向本机，只有本机端口可以检查监听状态
+fn is_local_addr(addr: &str) -> bool {
+    let addr = addr.trim();
+    addr.eq_ignore_ascii_case("localhost")
+        || addr
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback() || ip.is_unspecified())
+}
+
+// 使用 get_local_ports 检查本地端口是否有程序在监听，无法获取端口列表时跳过检查
+pub async fn check_local_port(params: &TunnelParams) -> Result<(), TunnelError> {
+    if params.skip_local_check || !is_local_addr(&params.local_addr) {
+        return Ok(());
+    }
+    let ports = match crate::ports::local_ports().await {
+        Ok(ports) => ports,
+        Err(e) => {
+            println!("获取本地端口失败，跳过监听检查: {}", e);
+            return Ok(());
+        }
+    };
+    let (proto, listeners) = if params.proxy_type == ProxyType::Udp {
+        ("udp", &ports.udp)
+    } else {
+        ("tcp", &ports.tcp)
+    };
+    let listening = listeners.iter().any(|l| l.port == params.local_port);
+    if listening {
+        Ok(())
+    } else {
+        Err(TunnelError::LocalPortNotListening {
+            local_addr: params.local_addr.clone(),
+            local_port: params.local_port,
+            message: format!(
+                "本地 {} 端口 {} 没有程序在监听，请先启动本地服务",
+                proto.to_uppercase(),
+                params.local_port
+            ),
+        })
+    }
+}
+
+// 检查字段及节点限制，不检查本地端口，nodes 为节点列表
+pub fn validate_params(params: &TunnelParams, nodes: &[Node]) -> Result<(), TunnelError> {
+    validate_fields(params)?;
+    let node = nodes
+        .iter()
+        .find(|n| n.id == params.node_id)
+        .ok_or_else(|| TunnelError::NodeNotFound {
+            node_id: params.node_id,
+            message: format!("节点 #{} 不存在或当前不可用", params.node_id),
+        })?;
+    validate_for_node(params, node)
+}
+
+// 完整检查隧道参数，包括本地端口是否在监听，用于界面中新建和编辑隧道
+pub async fn validate(params: &TunnelParams, nodes: &[Node]) -> Result<(), TunnelError> {
+    validate_params(params, nodes)?;
+    check_local_port(params).await
+}
+
+// 隧道列表为最新数据时检查隧道是否存在，离线时交给接口判断
+async fn ensure_exists(client: &OpenFrpClient, proxy_id: i64) -> Result<(), TunnelError> {
+    let proxies = client.user_proxies().await?;
+    if !proxies.stale && !proxies.data.list.iter().any(|p| p.id == proxy_id) {
+        return Err(TunnelError::TunnelNotFound {
+            proxy_id,
+            message: format!("隧道 #{} 不存在", proxy_id),
+        });
+    }
+    Ok(())
+}
+
+#[command]
+pub async fn create_tunnel(params: TunnelParams) -> Result<String, TunnelError> {
+    let client = OpenFrpClient::new()?;
+    let nodes = client.node_list().await?.data.list;
+    validate(&params, &nodes).await?;
+    Ok(client.new_proxy(&params.to_config(None)).await?)
+}
+
+#[command]
+pub async fn edit_tunnel(proxy_id: i64, params: TunnelParams) -> Result<String, TunnelError> {
+    let client = OpenFrpClient::new()?;
+    ensure_exists(&client, proxy_id).await?;
+    let nodes = client.node_list().await?.data.list;
+    validate(&params, &nodes).await?;
+    Ok(client.edit_proxy(&params.to_config(Some(proxy_id))).await?)
+}
+
+#[command]
+pub async fn delete_tunnel(proxy_id: i64) -> Result<String, TunnelError> {
+    let client = OpenFrpClient::new()?;
+    ensure_exists(&client, proxy_id).await?;
+    Ok(client.remove_proxy(proxy_id).await?)
+}
+
+#[derive(Serialize, Debug)]
+pub struct DeleteResult {
+    pub proxy_id: i64,
+    pub success: bool,
+    // 成功时为服务端的提示信息
+    pub message: String,
+    pub error: Option<TunnelError>,
+}
+
+// 逐个删除隧道，单个隧道失败不影响其余隧道
+#[command]
+pub async fn batch_delete_tunnels(proxy_ids: Vec<i64>) -> Result<Vec<DeleteResult>, TunnelError> {
+    let client = OpenFrpClient::new()?;
+    let proxies = client.user_proxies().await?;
+    let mut results = Vec::new();
+    for proxy_id in proxy_ids {
+        let result = if !proxies.stale && !proxies.data.list.iter().any(|p| p.id == proxy_id) {
+            Err(TunnelError::TunnelNotFound {
+                proxy_id,
+                message: format!("隧道 #{} 不存在", proxy_id),
+            })
+        } else {
+            client
+                .remove_proxy(proxy_id)
+                .await
+                .map_err(TunnelError::from)
+        };
+        results.push(match result {
+            Ok(message) => DeleteResult {
+                proxy_id,
+                success: true,
+                message,
+                error: None,
+            },
+            Err(e) => DeleteResult {
+                proxy_id,
+                success: false,
+                message: e.to_string(),
+                error: Some(e),
+            },
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allow_port_ranges() {
+        assert_eq!(parse_allow_port("(50000,60000)"), Some((50000, 60000)));
+        assert_eq!(parse_allow_port("[10000, 20000]"), Some((10000, 20000)));
+        assert_eq!(
+            parse_allow_port(" ( 30000 , 40000 ) "),
+            Some((30000, 40000))
+        );
+        // 上下限写反
+        assert_eq!(parse_allow_port("(60000,50000)"), Some((50000, 60000)));
+    }
+
+    #[test]
+    fn allow_port_single_and_invalid() {
+        assert_eq!(parse_allow_port("25565"), Some((25565, 25565)));
+        assert_eq!(parse_allow_port("(8080)"), Some((8080, 8080)));
+        assert_eq!(parse_allow_port(""), None);
+        assert_eq!(parse_allow_port("()"), None);
+        assert_eq!(parse_allow_port("(1,70000)"), None);
+        assert_eq!(parse_allow_port("(a,b)"), None);
+    }
+
+    #[test]
+    fn domain_valid() {
+        assert!(validate_domain("example.com").is_ok());
+        assert!(validate_domain("a-b.example.co.uk").is_ok());
+        assert!(validate_domain("*.example.com").is_ok());
+        assert!(validate_domain("xn--fsqu00a.xn--fiqs8s").is_ok());
+        assert!(validate_domain("123.example.com").is_ok());
+    }
+
+    #[test]
+    fn domain_invalid() {
+        assert!(validate_domain("").is_err());
+        assert!(validate_domain("localhost").is_err());
+        assert!(validate_domain("*.com").is_err());
+        assert!(validate_domain("a..com").is_err());
+        assert!(validate_domain("-a.com").is_err());
+        assert!(validate_domain("a-.com").is_err());
+        assert!(validate_domain("a_b.com").is_err());
+        assert!(validate_domain(" example.com").is_err());
+        assert!(validate_domain(&format!("{}.com", "a".repeat(64))).is_err());
+        // 纯数字顶级域名（如 IP 地址）
+        assert!(validate_domain("192.168.1.1").is_err());
+        assert!(validate_domain("example.123").is_err());
+        assert!(validate_domain("例子.中国")
+            .unwrap_err()
+            .contains("Punycode"));
+    }
+
+    #[test]
+    fn name_valid() {
+        assert!(validate_name("minecraft").is_ok());
+        assert!(validate_name("web_server-01.test").is_ok());
+        assert!(validate_name(" my tunnel ").is_ok());
+    }
+
+    #[test]
+    fn name_invalid() {
+        assert!(validate_name("").is_err());
+        assert!(validate_name("   ").is_err());
+        assert!(validate_name("我的隧道").unwrap_err().contains("中文"));
+        assert!(validate_name("web隧道").is_err());
+        assert!(validate_name("ｗｅｂ").is_err());
+        assert!(validate_name("a\tb").is_err());
+    }
+}
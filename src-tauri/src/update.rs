@@ -20,11 +20,8 @@ pub async fn check_update() -> Result<Option<UpdateInfo>, Box<dyn Error>> {
     println!("当前版本: {}", current_version);
 
     // 使用 Tauri 配置的更新源
-    let client = reqwest::Client::new();
-    let response = client
-        .get("https://api.zyghit.cn/updater/ofcpl")
-        .send()
-        .await?;
+    let client = crate::http::client()?;
+    let response = crate::http::send(client.get("https://api.zyghit.cn/updater/ofcpl")).await?;
 
     if !response.status().is_success() {
         return Err(format!("API 请求失败: {}", response.status()).into());
@@ -2,7 +2,8 @@ import { invoke } from '@tauri-apps/api/core';
 import Cookies from '@/utils/cookies';
 
 interface ApiOptions {
-  method?: 'GET' | 'POST' | 'PUT' | 'DELETE';
+  method?: 'GET' | 'POST' | 'PUT' | 'PATCH' | 'DELETE' | 'HEAD';
+  timeoutMs?: number;
   headers?: Record<string, string>;
   body?: any;
 }
@@ -41,7 +42,7 @@ export async function callApi<T>(endpoint: string, options: ApiOptions = {}): Pr
     throw new Error('未登录');
   }
 
-  const { method = 'GET', headers = {}, body } = options;
+  const { method = 'GET', headers = {}, body, timeoutMs } = options;
   
   const authHeaders = {
     ...headers,
@@ -54,6 +55,7 @@ export async function callApi<T>(endpoint: string, options: ApiOptions = {}): Pr
       method,
       headers: authHeaders,
       body,
+      timeoutMs,
     });
     
     // 检查响应头中的 authorization（忽略大小写）